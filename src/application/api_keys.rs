@@ -0,0 +1,338 @@
+use crate::application::service::sorted;
+use crate::config::ApiKeysConfig;
+use crate::domain::api_key::{format_key, hash_secret, parse_key, ApiKey, IssuedApiKey, KEY_ID_ATTRIBUTE, KEY_ID_LEN};
+use crate::domain::auth::{authorize, Principal};
+use crate::domain::clock::{Clock, SystemClock};
+use crate::domain::condition::{Condition, PutOptions};
+use crate::domain::errors::{DomainError, DomainResult};
+use crate::domain::models::RequestPayload;
+use crate::domain::ports::DatabasePort;
+use crate::domain::update::{ReturnValues, UpdateRequest};
+use crate::domain::validation::Validator;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Scope needed to issue and revoke API keys
+pub const API_KEYS_ADMIN: &str = "api-keys:admin";
+/// How long a looked-up key is trusted before it is read again
+pub const DEFAULT_CACHE_TTL_SECS: i64 = 60;
+/// Longest lifetime a key can be issued with
+pub const MAX_KEY_LIFETIME_SECS: u64 = 365 * 24 * 60 * 60;
+/// Lookups kept per instance before the cache is reset
+const MAX_CACHED_KEYS: usize = 10_000;
+
+struct CachedKey {
+    fetched_at: DateTime<Utc>,
+    /// `None` caches a miss, so unknown keys do not hit the database on every call
+    record: Option<ApiKey>,
+}
+
+/// `x-api-key` authentication and key administration, backed by `DatabasePort`.
+///
+/// Lookups are cached in memory for the cache TTL, so a revoked key keeps
+/// working on other warm instances until their entry expires.
+pub struct ApiKeyService {
+    database: Arc<dyn DatabasePort>,
+    table: String,
+    cache_ttl: Duration,
+    clock: Arc<dyn Clock>,
+    cache: Mutex<HashMap<String, CachedKey>>,
+}
+
+impl ApiKeyService {
+    pub fn new(database: Arc<dyn DatabasePort>, table: &str) -> Self {
+        Self {
+            database,
+            table: table.to_string(),
+            cache_ttl: Duration::seconds(DEFAULT_CACHE_TTL_SECS),
+            clock: Arc::new(SystemClock),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Service over `config.table` (partition key `key_id`).
+    ///
+    /// `None` when no table is configured, which disables API keys.
+    pub fn from_config(database: Arc<dyn DatabasePort>, config: &ApiKeysConfig) -> Option<Self> {
+        let table = config.table.as_ref()?;
+        Some(Self::new(database, table).with_cache_ttl(Duration::seconds(config.cache_ttl_secs as i64)))
+    }
+
+    /// Check a presented key and record its use
+    pub async fn authenticate(&self, key: &str) -> DomainResult<Principal> {
+        let (key_id, secret) = parse_key(key).ok_or_else(|| DomainError::Unauthorized("Malformed API key".to_string()))?;
+        let record = self
+            .lookup(key_id)
+            .await?
+            .filter(|record| record.verify_secret(secret))
+            .ok_or_else(|| DomainError::Unauthorized("Invalid API key".to_string()))?;
+        if !record.is_active(self.clock.now()) {
+            return Err(DomainError::Unauthorized("API key is disabled or expired".to_string()));
+        }
+
+        self.record_usage(&record).await;
+        Ok(record.principal())
+    }
+
+    async fn lookup(&self, key_id: &str) -> DomainResult<Option<ApiKey>> {
+        let now = self.clock.now();
+        if let Some(cached) = self.cache.lock().unwrap().get(key_id) {
+            if now - cached.fetched_at < self.cache_ttl {
+                return Ok(cached.record.clone());
+            }
+        }
+
+        let record = match self.database.get_item(&self.table, ApiKey::key(key_id)).await? {
+            Some(item) => Some(ApiKey::from_item(&item)?),
+            None => None,
+        };
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED_KEYS {
+            cache.clear();
+        }
+        cache.insert(
+            key_id.to_string(),
+            CachedKey {
+                fetched_at: now,
+                record: record.clone(),
+            },
+        );
+        Ok(record)
+    }
+
+    /// Count the call and stamp the key's last use. Failures are logged rather
+    /// than failing a request that was already authenticated.
+    async fn record_usage(&self, record: &ApiKey) {
+        let request = UpdateRequest::new(ApiKey::key(&record.key_id))
+            .increment("usage_count", 1)
+            .set("last_used_at", self.clock.now().timestamp())
+            // Never recreate a key deleted since it was cached
+            .condition(Condition::attribute_exists(KEY_ID_ATTRIBUTE));
+        if let Err(e) = self.database.update_item(&self.table, request).await {
+            warn!("Failed to record usage of API key {}: {}", record.key_id, e);
+        }
+    }
+
+    /// Issue a key from the body's `data`: `owner` and `scopes` are required,
+    /// `expires_in` (seconds) is optional and unset keys never expire
+    pub async fn issue(&self, principal: Option<&Principal>, payload: Option<RequestPayload>) -> DomainResult<IssuedApiKey> {
+        authorize(principal, API_KEYS_ADMIN)?;
+        let data = sorted(payload.and_then(|p| p.data));
+
+        let mut v = Validator::at("data");
+        v.known_fields(data.keys(), &["expires_in", "owner", "scopes"]);
+        let owner = v
+            .required("owner", data.get("owner"))
+            .and_then(|owner| v.string("owner", owner))
+            .filter(|owner| v.length("owner", owner, 1, 128));
+        let scopes = v
+            .required("scopes", data.get("scopes"))
+            .and_then(|scopes| v.deserialize::<Vec<String>>("scopes", scopes))
+            .filter(|scopes| {
+                let valid = scopes.iter().all(|s| !s.is_empty() && !s.contains(char::is_whitespace));
+                v.format("scopes", valid, "a list of scope names without spaces")
+            });
+        let expires_in = match data.get("expires_in") {
+            Some(expires_in) => v
+                .unsigned("expires_in", expires_in)
+                .filter(|secs| v.range("expires_in", *secs, 60, MAX_KEY_LIFETIME_SECS)),
+            None => None,
+        };
+        v.finish()?;
+
+        let now = self.clock.now();
+        let key_id = hex::encode(random_bytes::<{ KEY_ID_LEN / 2 }>()?);
+        let secret = URL_SAFE_NO_PAD.encode(random_bytes::<32>()?);
+        let api_key = ApiKey {
+            key_id: key_id.clone(),
+            // Present whenever validation passed
+            owner: owner.unwrap_or_default().to_string(),
+            scopes: scopes.unwrap_or_default(),
+            disabled: false,
+            expires_at: expires_in.map(|secs| now + Duration::seconds(secs as i64)),
+            created_at: now,
+            secret_hash: hash_secret(&secret),
+        };
+
+        let options = PutOptions::new().condition(Condition::attribute_not_exists(KEY_ID_ATTRIBUTE));
+        self.database.put_item_with(&self.table, api_key.to_item(), options).await?;
+        Ok(IssuedApiKey {
+            key: format_key(&key_id, &secret),
+            api_key,
+        })
+    }
+
+    /// Disable the key at `/api-keys/{key_id}`; the record is kept for auditing
+    pub async fn revoke(&self, principal: Option<&Principal>, path_params: &HashMap<String, String>) -> DomainResult<ApiKey> {
+        authorize(principal, API_KEYS_ADMIN)?;
+        let key_id = path_params
+            .get("key_id")
+            .ok_or_else(|| DomainError::Validation("Missing path parameter 'key_id'".to_string()))?;
+
+        let request = UpdateRequest::new(ApiKey::key(key_id))
+            .set("disabled", true)
+            .condition(Condition::attribute_exists(KEY_ID_ATTRIBUTE))
+            .returning(ReturnValues::AllNew);
+        let item = match self.database.update_item(&self.table, request).await {
+            Ok(item) => item.ok_or_else(|| DomainError::Internal("Revoke returned no item".to_string()))?,
+            Err(DomainError::ConditionFailed(_)) => {
+                return Err(DomainError::NotFound(format!("API key '{}' not found", key_id)));
+            }
+            Err(e) => return Err(e),
+        };
+        self.cache.lock().unwrap().remove(key_id.as_str());
+        ApiKey::from_item(&item)
+    }
+}
+
+fn random_bytes<const N: usize>() -> DomainResult<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| DomainError::Internal(format!("No randomness available: {}", e)))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::item::ItemValue;
+    use crate::domain::mocks::{MockClock, MockDatabase};
+
+    const TABLE: &str = "api-keys";
+
+    fn admin() -> Principal {
+        Principal {
+            subject: "admin".to_string(),
+            scopes: vec![API_KEYS_ADMIN.to_string()],
+            ..Principal::default()
+        }
+    }
+
+    fn issue_payload(data: serde_json::Value) -> Option<RequestPayload> {
+        Some(RequestPayload {
+            message: None,
+            data: serde_json::from_value(data).ok(),
+        })
+    }
+
+    fn service(db: Arc<MockDatabase>, clock: Arc<MockClock>) -> ApiKeyService {
+        ApiKeyService::new(db, TABLE).with_clock(clock)
+    }
+
+    #[tokio::test]
+    async fn test_issue_and_authenticate() {
+        let db = Arc::new(MockDatabase::new().with_table(TABLE, &[KEY_ID_ATTRIBUTE]));
+        let keys = service(db.clone(), Arc::new(MockClock::default()));
+
+        let payload = issue_payload(serde_json::json!({"owner": "billing-service", "scopes": ["items:read"]}));
+        let issued = keys.issue(Some(&admin()), payload).await.unwrap();
+        assert!(issued.key.starts_with("ak_"));
+
+        let principal = keys.authenticate(&issued.key).await.unwrap();
+        assert_eq!(principal.subject, "billing-service");
+        assert!(principal.has_scope("items:read"));
+        assert_eq!(principal.claims["api_key_id"], issued.api_key.key_id.as_str());
+
+        // Only the hash is stored
+        let (_, secret) = parse_key(&issued.key).unwrap();
+        let item = db.get_item(TABLE, ApiKey::key(&issued.api_key.key_id)).await.unwrap().unwrap();
+        assert_eq!(item["secret_hash"], ItemValue::from(hash_secret(secret)));
+        assert!(!item.values().any(|v| v.as_str() == Some(secret)));
+
+        let wrong = issued.key.replace(secret, "wrong");
+        assert!(matches!(keys.authenticate(&wrong).await, Err(DomainError::Unauthorized(_))));
+        assert!(matches!(keys.authenticate("not-a-key").await, Err(DomainError::Unauthorized(_))));
+
+        // Ids that were never issued are turned away without a lookup
+        let before = db.call_count();
+        assert!(matches!(keys.authenticate("ak_junk.x").await, Err(DomainError::Unauthorized(_))));
+        assert_eq!(db.call_count(), before);
+    }
+
+    #[tokio::test]
+    async fn test_lookups_are_cached_and_usage_recorded() {
+        let db = Arc::new(MockDatabase::new().with_table(TABLE, &[KEY_ID_ATTRIBUTE]));
+        let clock = Arc::new(MockClock::default());
+        let keys = service(db.clone(), clock.clone());
+        let payload = issue_payload(serde_json::json!({"owner": "billing-service", "scopes": []}));
+        let issued = keys.issue(Some(&admin()), payload).await.unwrap();
+
+        let before = db.call_count();
+        keys.authenticate(&issued.key).await.unwrap();
+        keys.authenticate(&issued.key).await.unwrap();
+        // One lookup, then one usage update per call
+        assert_eq!(db.call_count() - before, 3);
+
+        clock.advance(Duration::seconds(DEFAULT_CACHE_TTL_SECS));
+        keys.authenticate(&issued.key).await.unwrap();
+        assert_eq!(db.call_count() - before, 5);
+
+        let item = db.get_item(TABLE, ApiKey::key(&issued.api_key.key_id)).await.unwrap().unwrap();
+        assert_eq!(item["usage_count"], ItemValue::number(3));
+        assert_eq!(item["last_used_at"], ItemValue::number(clock.now().timestamp()));
+    }
+
+    #[tokio::test]
+    async fn test_cached_misses_are_bounded() {
+        let db = Arc::new(MockDatabase::new().with_table(TABLE, &[KEY_ID_ATTRIBUTE]));
+        let keys = service(db, Arc::new(MockClock::default()));
+
+        for n in 0..=MAX_CACHED_KEYS {
+            let key = format_key(&format!("{:016x}", n), "guess");
+            assert!(keys.authenticate(&key).await.is_err());
+        }
+        assert_eq!(keys.cache.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_and_expired_keys_are_rejected() {
+        let db = Arc::new(MockDatabase::new().with_table(TABLE, &[KEY_ID_ATTRIBUTE]));
+        let clock = Arc::new(MockClock::default());
+        let keys = service(db.clone(), clock.clone());
+
+        let payload = issue_payload(serde_json::json!({"owner": "a", "scopes": [], "expires_in": 3600}));
+        let expiring = keys.issue(Some(&admin()), payload).await.unwrap();
+        clock.advance(Duration::seconds(3600));
+        assert!(matches!(keys.authenticate(&expiring.key).await, Err(DomainError::Unauthorized(_))));
+
+        let payload = issue_payload(serde_json::json!({"owner": "b", "scopes": []}));
+        let revoked = keys.issue(Some(&admin()), payload).await.unwrap();
+        keys.authenticate(&revoked.key).await.unwrap();
+        let path = HashMap::from([("key_id".to_string(), revoked.api_key.key_id.clone())]);
+        assert!(keys.revoke(Some(&admin()), &path).await.unwrap().disabled);
+        assert!(matches!(keys.authenticate(&revoked.key).await, Err(DomainError::Unauthorized(_))));
+
+        let missing = HashMap::from([("key_id".to_string(), "0000".to_string())]);
+        assert!(matches!(keys.revoke(Some(&admin()), &missing).await, Err(DomainError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_issue_requires_admin_scope_and_valid_fields() {
+        let db = Arc::new(MockDatabase::new().with_table(TABLE, &[KEY_ID_ATTRIBUTE]));
+        let keys = service(db, Arc::new(MockClock::default()));
+
+        let payload = issue_payload(serde_json::json!({"owner": "a", "scopes": []}));
+        let user = Principal { subject: "user".to_string(), ..Principal::default() };
+        assert!(matches!(keys.issue(Some(&user), payload).await, Err(DomainError::Forbidden(_))));
+
+        let payload = issue_payload(serde_json::json!({"scopes": ["items read"], "expires_in": 1, "extra": 1}));
+        let fields = match keys.issue(Some(&admin()), payload).await {
+            Err(DomainError::InvalidInput { errors, .. }) => errors.into_iter().map(|e| e.field).collect::<Vec<_>>(),
+            other => panic!("expected InvalidInput, got {:?}", other),
+        };
+        assert_eq!(fields, vec!["data.extra", "data.owner", "data.scopes", "data.expires_in"]);
+    }
+}
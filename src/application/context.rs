@@ -0,0 +1,62 @@
+use crate::application::api_keys::ApiKeyService;
+use crate::application::idempotency::IdempotencyService;
+use crate::application::rate_limiter::RateLimiter;
+use crate::application::service::RequestProcessor;
+use crate::config::Config;
+use crate::domain::ports::{DatabasePort, StoragePort};
+use crate::metrics::Metrics;
+use std::sync::Arc;
+
+/// Application state built once per cold start and shared across invocations.
+///
+/// Holding the adapters here keeps credential resolution and connection
+/// setup out of the warm invocation path.
+pub struct AppContext {
+    pub processor: RequestProcessor,
+    /// `x-api-key` authentication; `None` when API keys are disabled
+    pub api_keys: Option<ApiKeyService>,
+    /// Per-client request limits; `None` when rate limiting is disabled
+    pub rate_limiter: Option<RateLimiter>,
+    /// `Idempotency-Key` handling; `None` when idempotency keys are ignored
+    pub idempotency: Option<IdempotencyService>,
+    /// CloudWatch metrics, flushed after every invocation; `None` when disabled
+    pub metrics: Option<Arc<Metrics>>,
+}
+
+impl AppContext {
+    pub fn new(database: Arc<dyn DatabasePort>, storage: Arc<dyn StoragePort>) -> Self {
+        Self {
+            processor: RequestProcessor::new(database, storage),
+            api_keys: None,
+            rate_limiter: None,
+            idempotency: None,
+            metrics: None,
+        }
+    }
+
+    /// Point the processor at the configured items table and bucket
+    pub fn with_config(mut self, config: &Config) -> Self {
+        self.processor = self.processor.with_config(config.database.clone(), config.storage.clone());
+        self
+    }
+
+    pub fn with_api_keys(mut self, api_keys: Option<ApiKeyService>) -> Self {
+        self.api_keys = api_keys;
+        self
+    }
+
+    pub fn with_rate_limiter(mut self, rate_limiter: Option<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    pub fn with_idempotency(mut self, idempotency: Option<IdempotencyService>) -> Self {
+        self.idempotency = idempotency;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Option<Arc<Metrics>>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+}
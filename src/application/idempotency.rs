@@ -0,0 +1,245 @@
+use crate::config::IdempotencyConfig;
+use crate::domain::clock::{Clock, SystemClock};
+use crate::domain::condition::{Condition, PutOptions};
+use crate::domain::errors::{DomainError, DomainResult, FieldError};
+use crate::domain::idempotency::{
+    IdempotencyRecord, IdempotencyStatus, StoredResponse, IDEMPOTENCY_KEY_ATTRIBUTE,
+};
+use crate::domain::ports::DatabasePort;
+use std::sync::Arc;
+use tracing::warn;
+
+/// How long a completed response is replayed
+pub const DEFAULT_TTL_SECS: i64 = 24 * 60 * 60;
+/// How long a request may run before its key is considered abandoned; at
+/// least the function timeout
+pub const DEFAULT_LOCK_SECS: i64 = 60;
+
+/// What to do with a request that carries an idempotency key
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdempotencyOutcome {
+    /// First use of the key: run the request, then `complete` or `release`
+    /// it. `locked_until` identifies this request's claim.
+    Proceed { locked_until: i64 },
+    /// The key's request already completed: answer with its response
+    Replay(StoredResponse),
+}
+
+/// `Idempotency-Key` bookkeeping, backed by `DatabasePort`.
+///
+/// The first request with a key claims it with a conditional put, which
+/// makes concurrent duplicates lose the race rather than run twice.
+pub struct IdempotencyService {
+    database: Arc<dyn DatabasePort>,
+    table: String,
+    ttl_secs: i64,
+    lock_secs: i64,
+    clock: Arc<dyn Clock>,
+}
+
+impl IdempotencyService {
+    pub fn new(database: Arc<dyn DatabasePort>, table: &str) -> Self {
+        Self {
+            database,
+            table: table.to_string(),
+            ttl_secs: DEFAULT_TTL_SECS,
+            lock_secs: DEFAULT_LOCK_SECS,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl_secs: i64) -> Self {
+        self.ttl_secs = ttl_secs;
+        self
+    }
+
+    pub fn with_lock_timeout(mut self, lock_secs: i64) -> Self {
+        self.lock_secs = lock_secs;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Service over `config.table` (partition key `idempotency_key`).
+    ///
+    /// `None` when no table is configured, which disables idempotency keys.
+    pub fn from_config(database: Arc<dyn DatabasePort>, config: &IdempotencyConfig) -> Option<Self> {
+        let table = config.table.as_ref()?;
+        Some(
+            Self::new(database, table)
+                .with_ttl(config.ttl_secs as i64)
+                .with_lock_timeout(config.lock_secs as i64),
+        )
+    }
+
+    /// Claim `key` for a request with `request_hash`.
+    ///
+    /// A key still being processed is a `Conflict`; a key first used with a
+    /// different request is `InvalidInput`. Expired records and requests
+    /// abandoned past the lock timeout are taken over.
+    pub async fn begin(&self, key: &str, request_hash: &str) -> DomainResult<IdempotencyOutcome> {
+        let now = self.clock.now().timestamp();
+        let claim = IdempotencyRecord {
+            key: key.to_string(),
+            request_hash: request_hash.to_string(),
+            status: IdempotencyStatus::InProgress,
+            response: None,
+            locked_until: now + self.lock_secs,
+            expires_at: now + self.ttl_secs,
+        };
+        let proceed = IdempotencyOutcome::Proceed {
+            locked_until: claim.locked_until,
+        };
+        if self.put(&claim, Condition::attribute_not_exists(IDEMPOTENCY_KEY_ATTRIBUTE)).await? {
+            return Ok(proceed);
+        }
+
+        let existing = match self.database.get_item(&self.table, IdempotencyRecord::key_item(key)).await? {
+            Some(item) => IdempotencyRecord::from_item(&item)?,
+            // Released between the put and the read
+            None => return Err(in_progress()),
+        };
+        let abandoned = existing.status == IdempotencyStatus::InProgress && existing.locked_until <= now;
+        if existing.expires_at <= now || abandoned {
+            // Only one taker can replace the record it read
+            return match self.put(&claim, Condition::attribute_equals("locked_until", existing.locked_until)).await? {
+                true => Ok(proceed),
+                false => Err(in_progress()),
+            };
+        }
+        if existing.request_hash != request_hash {
+            return Err(DomainError::InvalidInput {
+                message: "Idempotency-Key was already used with a different request".to_string(),
+                errors: vec![FieldError {
+                    field: "Idempotency-Key".to_string(),
+                    rule: "reused".to_string(),
+                    message: "Send a new key for a different request".to_string(),
+                }],
+            });
+        }
+        match (existing.status, existing.response) {
+            (IdempotencyStatus::Completed, Some(response)) => Ok(IdempotencyOutcome::Replay(response)),
+            _ => Err(in_progress()),
+        }
+    }
+
+    /// Store the response of the claim that `begin` returned `locked_until` for.
+    /// Nothing is stored if another request took the key over once the lock
+    /// ran out, as the record is then that request's.
+    pub async fn complete(
+        &self,
+        key: &str,
+        request_hash: &str,
+        locked_until: i64,
+        response: StoredResponse,
+    ) -> DomainResult<()> {
+        let now = self.clock.now().timestamp();
+        let record = IdempotencyRecord {
+            key: key.to_string(),
+            request_hash: request_hash.to_string(),
+            status: IdempotencyStatus::Completed,
+            response: Some(response),
+            locked_until: now,
+            expires_at: now + self.ttl_secs,
+        };
+        if !self.put(&record, Condition::attribute_equals("locked_until", locked_until)).await? {
+            warn!("Idempotency-Key {} was taken over after its lock ran out; the response is not stored", key);
+        }
+        Ok(())
+    }
+
+    /// Give up a claimed key, so the request can be retried with it
+    pub async fn release(&self, key: &str) -> DomainResult<()> {
+        self.database
+            .delete_item(&self.table, IdempotencyRecord::key_item(key), Vec::new())
+            .await
+            .map(|_| ())
+    }
+
+    /// Conditional put; `false` when the condition did not hold
+    async fn put(&self, record: &IdempotencyRecord, condition: Condition) -> DomainResult<bool> {
+        let options = PutOptions::new().condition(condition);
+        match self.database.put_item_with(&self.table, record.to_item(), options).await {
+            Ok(_) => Ok(true),
+            Err(DomainError::ConditionFailed(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn in_progress() -> DomainError {
+    DomainError::Conflict("A request with this Idempotency-Key is still in progress".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::mocks::{MockClock, MockDatabase};
+    use chrono::Duration;
+    use std::collections::BTreeMap;
+
+    const TABLE: &str = "idempotency";
+
+    fn service(clock: Arc<MockClock>) -> IdempotencyService {
+        let db = MockDatabase::new().with_table(TABLE, &[IDEMPOTENCY_KEY_ATTRIBUTE]);
+        IdempotencyService::new(Arc::new(db), TABLE).with_clock(clock)
+    }
+
+    /// The claim of a request that may proceed
+    fn proceed(outcome: DomainResult<IdempotencyOutcome>) -> i64 {
+        match outcome {
+            Ok(IdempotencyOutcome::Proceed { locked_until }) => locked_until,
+            other => panic!("expected Proceed, got {:?}", other),
+        }
+    }
+
+    fn created() -> StoredResponse {
+        StoredResponse {
+            status: 200,
+            headers: BTreeMap::from([("content-type".to_string(), "application/json".to_string())]),
+            body: b"{\"status\":\"success\"}".to_vec(),
+            is_text: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_completed_response_is_replayed() {
+        let idempotency = service(Arc::new(MockClock::default()));
+
+        let claim = proceed(idempotency.begin("k1", "hash-a").await);
+        // A concurrent duplicate while the first is running
+        assert!(matches!(idempotency.begin("k1", "hash-a").await, Err(DomainError::Conflict(_))));
+
+        idempotency.complete("k1", "hash-a", claim, created()).await.unwrap();
+        assert_eq!(idempotency.begin("k1", "hash-a").await.unwrap(), IdempotencyOutcome::Replay(created()));
+        assert!(matches!(idempotency.begin("k1", "hash-b").await, Err(DomainError::InvalidInput { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_released_abandoned_and_expired_keys_can_be_reused() {
+        let clock = Arc::new(MockClock::default());
+        let idempotency = service(clock.clone()).with_ttl(3600).with_lock_timeout(60);
+
+        idempotency.begin("released", "hash-a").await.unwrap();
+        idempotency.release("released").await.unwrap();
+        proceed(idempotency.begin("released", "hash-b").await);
+
+        let abandoned = proceed(idempotency.begin("abandoned", "hash-a").await);
+        clock.advance(Duration::seconds(60));
+        let takeover = proceed(idempotency.begin("abandoned", "hash-a").await);
+        // The takeover holds the lock again
+        assert!(matches!(idempotency.begin("abandoned", "hash-a").await, Err(DomainError::Conflict(_))));
+
+        // The abandoned request finishing late does not overwrite the takeover's record
+        idempotency.complete("abandoned", "hash-a", abandoned, created()).await.unwrap();
+        assert!(matches!(idempotency.begin("abandoned", "hash-a").await, Err(DomainError::Conflict(_))));
+
+        idempotency.complete("abandoned", "hash-a", takeover, created()).await.unwrap();
+        assert_eq!(idempotency.begin("abandoned", "hash-a").await.unwrap(), IdempotencyOutcome::Replay(created()));
+        clock.advance(Duration::seconds(3600));
+        proceed(idempotency.begin("abandoned", "hash-b").await);
+    }
+}
@@ -1 +1,6 @@
+pub mod api_keys;
+pub mod rate_limiter;
+pub mod context;
+pub mod idempotency;
 pub mod service;
+pub mod secrets;
@@ -0,0 +1,236 @@
+use crate::config::RateLimitConfig;
+use crate::domain::clock::{Clock, SystemClock};
+use crate::domain::errors::DomainResult;
+use crate::domain::item::{Item, ItemValue};
+use crate::domain::ports::DatabasePort;
+use crate::domain::rate_limit::{RateLimit, RateLimitStatus};
+use crate::domain::request::RequestContext;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Partition key attribute of the rate limit table
+pub const COUNTER_KEY_ATTRIBUTE: &str = "counter_key";
+/// Request count of one client, route and window
+pub const COUNT_ATTRIBUTE: &str = "request_count";
+/// Epoch seconds after which DynamoDB TTL may delete a counter
+pub const EXPIRES_AT_ATTRIBUTE: &str = "expires_at";
+/// Finished-window counts kept per instance before the cache is reset
+const MAX_CACHED_COUNTERS: usize = 10_000;
+
+/// Per-client, per-route rate limiting with counters stored through `DatabasePort`.
+///
+/// Each client gets one counter per route and fixed window, incremented
+/// atomically with `increment_counter`; the previous window's count is
+/// weighted in to approximate a sliding window. A finished window no longer
+/// changes, so its count is read once and cached per instance.
+pub struct RateLimiter {
+    database: Arc<dyn DatabasePort>,
+    table: String,
+    default_limit: Option<RateLimit>,
+    route_limits: HashMap<String, RateLimit>,
+    clock: Arc<dyn Clock>,
+    /// Final count of the last finished window, by client and route
+    finished: Mutex<HashMap<String, (u64, u64)>>,
+}
+
+impl RateLimiter {
+    /// Limiter over `table` (partition key `counter_key`) that limits nothing
+    /// until a default or route limit is set
+    pub fn new(database: Arc<dyn DatabasePort>, table: &str) -> Self {
+        Self {
+            database,
+            table: table.to_string(),
+            default_limit: None,
+            route_limits: HashMap::new(),
+            clock: Arc::new(SystemClock),
+            finished: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limit for routes without a limit of their own
+    pub fn with_default_limit(mut self, limit: RateLimit) -> Self {
+        self.default_limit = Some(limit);
+        self
+    }
+
+    /// Limit for one route, keyed like `GET /items/{pk}`
+    pub fn with_route_limit(mut self, route_key: &str, limit: RateLimit) -> Self {
+        self.route_limits.insert(route_key.to_string(), limit);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Limiter over `config.table` with its default and per-route limits.
+    ///
+    /// `None` when no table is configured, which disables rate limiting.
+    pub fn from_config(database: Arc<dyn DatabasePort>, config: &RateLimitConfig) -> Option<Self> {
+        let mut limiter = Self::new(database, config.table.as_ref()?);
+        limiter.default_limit = config.default;
+        for (route_key, limit) in &config.routes {
+            limiter = limiter.with_route_limit(route_key, *limit);
+        }
+        Some(limiter)
+    }
+
+    pub fn limit_for(&self, route_key: &str) -> Option<RateLimit> {
+        self.route_limits.get(route_key).copied().or(self.default_limit)
+    }
+
+    /// Count a request to `route_key` and report whether it is within the limit.
+    ///
+    /// `None` when the route is not limited or the client cannot be identified.
+    /// Counter failures are logged and the request is let through, so an
+    /// unavailable table does not take the API down with it.
+    pub async fn check(&self, request: &RequestContext, route_key: &str) -> Option<RateLimitStatus> {
+        let limit = self.limit_for(route_key)?;
+        let client = request.client_key()?;
+        match self.count(&client, route_key, limit).await {
+            Ok(status) => Some(status),
+            Err(e) => {
+                warn!("Rate limit check for {} on {} failed: {}", client, route_key, e);
+                None
+            }
+        }
+    }
+
+    async fn count(&self, client: &str, route_key: &str, limit: RateLimit) -> DomainResult<RateLimitStatus> {
+        let now = self.clock.now().timestamp().max(0) as u64;
+        let window = now / limit.window_secs;
+        let elapsed = now % limit.window_secs;
+        let counter = format!("{}#{}", client, route_key);
+
+        // Kept through the next window, which weighs this one in
+        let expires_at = ((window + 2) * limit.window_secs) as i64;
+        let current = self
+            .database
+            .increment_counter(
+                &self.table,
+                counter_key(&counter, window),
+                COUNT_ATTRIBUTE,
+                1,
+                Some((EXPIRES_AT_ATTRIBUTE, expires_at)),
+            )
+            .await?;
+        let previous = match window.checked_sub(1) {
+            Some(previous) => self.finished_count(&counter, previous).await?,
+            None => 0,
+        };
+        Ok(limit.evaluate(previous, current.max(0) as u64, elapsed))
+    }
+
+    async fn finished_count(&self, counter: &str, window: u64) -> DomainResult<u64> {
+        if let Some((cached_window, count)) = self.finished.lock().unwrap().get(counter) {
+            if *cached_window == window {
+                return Ok(*count);
+            }
+        }
+        let count = self
+            .database
+            .get_item(&self.table, counter_key(counter, window))
+            .await?
+            .and_then(|item| item.get(COUNT_ATTRIBUTE).and_then(ItemValue::as_number::<u64>))
+            .unwrap_or(0);
+
+        let mut finished = self.finished.lock().unwrap();
+        if finished.len() >= MAX_CACHED_COUNTERS {
+            finished.clear();
+        }
+        finished.insert(counter.to_string(), (window, count));
+        Ok(count)
+    }
+}
+
+fn counter_key(counter: &str, window: u64) -> Item {
+    Item::from([(
+        COUNTER_KEY_ATTRIBUTE.to_string(),
+        ItemValue::from(format!("{}#{}", counter, window)),
+    )])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::mocks::{MockClock, MockDatabase};
+    use chrono::{DateTime, Duration};
+
+    const TABLE: &str = "rate-limits";
+    const ROUTE: &str = "GET /items/{pk}";
+
+    fn from_ip(ip: &str) -> RequestContext {
+        RequestContext {
+            source_ip: Some(ip.to_string()),
+            ..RequestContext::default()
+        }
+    }
+
+    /// A clock at the start of a minute, so window boundaries are easy to reason about
+    fn clock() -> Arc<MockClock> {
+        Arc::new(MockClock::at(DateTime::from_timestamp(1_699_999_980, 0).unwrap()))
+    }
+
+    fn limiter(db: Arc<MockDatabase>, clock: Arc<MockClock>, limit: RateLimit) -> RateLimiter {
+        RateLimiter::new(db, TABLE).with_route_limit(ROUTE, limit).with_clock(clock)
+    }
+
+    #[tokio::test]
+    async fn test_limits_each_client_per_route() {
+        let db = Arc::new(MockDatabase::new().with_table(TABLE, &[COUNTER_KEY_ATTRIBUTE]));
+        let limiter = limiter(db, clock(), RateLimit::new(3, 60));
+        let client = from_ip("203.0.113.7");
+
+        for remaining in [2, 1, 0] {
+            let status = limiter.check(&client, ROUTE).await.unwrap();
+            assert_eq!((status.remaining, status.retry_after), (remaining, None));
+        }
+        let status = limiter.check(&client, ROUTE).await.unwrap();
+        assert!(status.is_limited());
+        assert_eq!((status.limit, status.reset_after), (3, 60));
+
+        // Other clients and unlimited routes are unaffected
+        assert!(!limiter.check(&from_ip("198.51.100.20"), ROUTE).await.unwrap().is_limited());
+        assert_eq!(limiter.check(&client, "POST /api-keys").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_previous_window_is_weighted_and_cached() {
+        let db = Arc::new(MockDatabase::new().with_table(TABLE, &[COUNTER_KEY_ATTRIBUTE]));
+        let clock = clock();
+        let limiter = limiter(db.clone(), clock.clone(), RateLimit::new(4, 60));
+        let client = from_ip("203.0.113.7");
+        for _ in 0..4 {
+            limiter.check(&client, ROUTE).await.unwrap();
+        }
+
+        // Halfway into the next window, half of the previous 4 requests still count
+        clock.advance(Duration::seconds(90));
+        let before = db.call_count();
+        assert_eq!(limiter.check(&client, ROUTE).await.unwrap().remaining, 1);
+        assert_eq!(limiter.check(&client, ROUTE).await.unwrap().remaining, 0);
+        let status = limiter.check(&client, ROUTE).await.unwrap();
+        assert_eq!(status.retry_after, Some(30));
+        // Three increments, and the finished window read only once
+        assert_eq!(db.call_count() - before, 4);
+
+        let stored = db
+            .get_item(TABLE, counter_key("ip:203.0.113.7#GET /items/{pk}", 28_333_334))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored[COUNT_ATTRIBUTE], ItemValue::number(3));
+        assert_eq!(stored[EXPIRES_AT_ATTRIBUTE], ItemValue::number(1_700_000_160));
+    }
+
+    #[tokio::test]
+    async fn test_counter_failures_let_requests_through() {
+        // No table registered, so every counter write fails
+        let limiter = limiter(Arc::new(MockDatabase::new()), clock(), RateLimit::new(1, 60));
+        for _ in 0..3 {
+            assert_eq!(limiter.check(&from_ip("203.0.113.7"), ROUTE).await, None);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::config::SecretsConfig;
+use crate::domain::clock::{Clock, SystemClock};
+use crate::domain::errors::{DomainError, DomainResult};
+use crate::domain::ports::SecretsPort;
+use crate::domain::secret::SecretReference;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// How long a fetched secret is used before it is read again
+pub const DEFAULT_CACHE_TTL_SECS: i64 = 300;
+
+struct CachedSecret {
+    fetched_at: DateTime<Utc>,
+    value: String,
+}
+
+/// Secret lookups through `SecretsPort`, cached in memory.
+///
+/// A cached value is used until the cache TTL passes, then fetched again on
+/// the next lookup, so rotated secrets are picked up without a cold start.
+/// If that refresh fails, the previous value keeps being served until the
+/// secret can be read again.
+pub struct SecretsService {
+    port: Arc<dyn SecretsPort>,
+    cache_ttl: Duration,
+    clock: Arc<dyn Clock>,
+    cache: Mutex<HashMap<String, CachedSecret>>,
+}
+
+impl SecretsService {
+    pub fn new(port: Arc<dyn SecretsPort>) -> Self {
+        Self {
+            port,
+            cache_ttl: Duration::seconds(DEFAULT_CACHE_TTL_SECS),
+            clock: Arc::new(SystemClock),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn from_config(port: Arc<dyn SecretsPort>, config: &SecretsConfig) -> Self {
+        Self::new(port).with_cache_ttl(Duration::seconds(config.cache_ttl_secs as i64))
+    }
+
+    /// Value of the secret called `name`
+    pub async fn get(&self, name: &str) -> DomainResult<String> {
+        let now = self.clock.now();
+        let stale = match self.cache.lock().unwrap().get(name) {
+            Some(cached) if now - cached.fetched_at < self.cache_ttl => return Ok(cached.value.clone()),
+            Some(cached) => Some(cached.value.clone()),
+            None => None,
+        };
+
+        match self.port.get_secret(name).await {
+            Ok(value) => {
+                self.cache.lock().unwrap().insert(
+                    name.to_string(),
+                    CachedSecret {
+                        fetched_at: now,
+                        value: value.clone(),
+                    },
+                );
+                Ok(value)
+            }
+            // A deleted secret stops working; any other failure keeps the last value
+            Err(e @ DomainError::NotFound(_)) => {
+                self.cache.lock().unwrap().remove(name);
+                Err(e)
+            }
+            Err(e) => match stale {
+                Some(value) => {
+                    warn!("Refreshing secret '{}' failed, using the cached value: {}", name, e);
+                    Ok(value)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// `value` itself, or the secret it references when it is a `secret://` reference
+    pub async fn resolve(&self, value: &str) -> DomainResult<String> {
+        match SecretReference::parse(value)? {
+            Some(reference) => reference.select(&self.get(&reference.name).await?),
+            None => Ok(value.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::mocks::{MockClock, MockSecrets};
+
+    #[tokio::test]
+    async fn test_cached_until_expiry_then_refreshed() {
+        let port = Arc::new(MockSecrets::new().with_secret("payments", r#"{"api_key": "sk_1"}"#));
+        let clock = Arc::new(MockClock::default());
+        let secrets = SecretsService::new(port.clone())
+            .with_cache_ttl(Duration::seconds(300))
+            .with_clock(clock.clone());
+
+        assert_eq!(secrets.resolve("secret://payments#api_key").await.unwrap(), "sk_1");
+        port.set_secret("payments", r#"{"api_key": "sk_2"}"#);
+        assert_eq!(secrets.resolve("secret://payments#api_key").await.unwrap(), "sk_1");
+        assert_eq!(port.call_count(), 1);
+
+        clock.advance(Duration::seconds(300));
+        assert_eq!(secrets.resolve("secret://payments#api_key").await.unwrap(), "sk_2");
+        assert_eq!(port.call_count(), 2);
+
+        // Plain values pass through without a lookup
+        assert_eq!(secrets.resolve("orders-table").await.unwrap(), "orders-table");
+        assert_eq!(port.call_count(), 2);
+    }
+
+    /// Fails every call after the first
+    struct FlakyPort(MockSecrets);
+
+    #[async_trait::async_trait]
+    impl SecretsPort for FlakyPort {
+        async fn get_secret(&self, name: &str) -> DomainResult<String> {
+            match self.0.call_count() {
+                0 => self.0.get_secret(name).await,
+                _ => Err(DomainError::Upstream("Secrets Manager error: timeout".to_string())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_serves_last_value() {
+        let clock = Arc::new(MockClock::default());
+        let secrets = SecretsService::new(Arc::new(FlakyPort(MockSecrets::new().with_secret("jwks", "{}"))))
+            .with_clock(clock.clone());
+
+        assert_eq!(secrets.get("jwks").await.unwrap(), "{}");
+        clock.advance(Duration::seconds(DEFAULT_CACHE_TTL_SECS));
+        assert_eq!(secrets.get("jwks").await.unwrap(), "{}");
+        assert!(matches!(secrets.get("other").await, Err(DomainError::Upstream(_))));
+
+        let missing = SecretsService::new(Arc::new(MockSecrets::new()));
+        assert!(matches!(missing.resolve("secret://absent#field").await, Err(DomainError::NotFound(_))));
+    }
+}
@@ -1,158 +1,841 @@
+use crate::config::{DatabaseConfig, SortKeyType, StorageConfig};
+use crate::domain::auth::authorize;
+use crate::domain::condition::{Condition, PutOptions};
+use crate::domain::errors::{DomainError, DomainResult};
+use crate::domain::item::{is_valid_number, Item, ItemValue};
 use crate::domain::models::RequestPayload;
+use crate::domain::object::{ObjectMetadata, PresignRequest, PresignedUrl, PutObjectOptions};
 use crate::domain::ports::{DatabasePort, StoragePort};
-use std::collections::HashMap;
-use std::error::Error;
+use crate::domain::query::{Page, QueryRequest};
+use crate::domain::request::RequestContext;
+use crate::domain::update::{ReturnValues, UpdateAction, UpdateRequest};
+use crate::domain::validation::{is_content_type, Validator};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
 pub struct RequestProcessor {
-    database: Box<dyn DatabasePort>,
-    storage: Box<dyn StoragePort>,
+    database: Arc<dyn DatabasePort>,
+    storage: Arc<dyn StoragePort>,
+    database_config: DatabaseConfig,
+    storage_config: StorageConfig,
 }
 
 impl RequestProcessor {
-    pub fn new(database: Box<dyn DatabasePort>, storage: Box<dyn StoragePort>) -> Self {
-        Self { database, storage }
+    /// Processor over the default `demo-table` table and `demo-bucket` bucket
+    pub fn new(database: Arc<dyn DatabasePort>, storage: Arc<dyn StoragePort>) -> Self {
+        Self {
+            database,
+            storage,
+            database_config: DatabaseConfig::default(),
+            storage_config: StorageConfig::default(),
+        }
+    }
+
+    /// Use the items table and bucket of the loaded configuration
+    pub fn with_config(mut self, database: DatabaseConfig, storage: StorageConfig) -> Self {
+        self.database_config = database;
+        self.storage_config = storage;
+        self
     }
 
-    pub async fn process_request(
+    /// The `/demo` endpoint: echo the body's `message` and look up a fixed
+    /// item and object, reporting what was found rather than failing.
+    ///
+    /// With `?health=true` it only reports that the service is up.
+    pub async fn demo(
         &self,
         payload: Option<RequestPayload>,
         query_params: &HashMap<String, String>,
-        _path_params: &HashMap<String, String>,
-    ) -> Result<String, Box<dyn Error + Send + Sync>> {
-        // Example business logic
+    ) -> DomainResult<DemoReport> {
+        if query_params.get("health").map(|v| v.as_str()) == Some("true") {
+            return Ok(DemoReport {
+                message: "Service is healthy".to_string(),
+                item: None,
+                object: None,
+            });
+        }
         let message = match payload {
             Some(p) => p.message.unwrap_or_else(|| "No message provided".to_string()),
             None => "No payload provided".to_string(),
         };
 
-        // Check if this is a health check request
-        if query_params.get("health").map(|v| v.as_str()) == Some("true") {
-            return Ok("Service is healthy".to_string());
+        let key_params = HashMap::from([
+            ("pk".to_string(), DEMO_ITEM_PK.to_string()),
+            ("sk".to_string(), DEMO_ITEM_SK.to_string()),
+        ]);
+        let key = item_key(&self.database_config, &key_params)?;
+        let item = Lookup::from(self.database.get_item(&self.database_config.table, key).await);
+        let object = Lookup::from(self.storage.head_object(&self.storage_config.bucket, DEMO_OBJECT_KEY).await);
+
+        Ok(DemoReport {
+            message: format!("Received message: {}", message),
+            item: Some(item),
+            object: Some(object),
+        })
+    }
+
+    /// Read the item at `/items/{pk}/{sk}`
+    pub async fn get_item(&self, request: &RequestContext, path_params: &HashMap<String, String>) -> DomainResult<Item> {
+        authorize(request.principal.as_ref(), ITEMS_READ)?;
+        let key = item_key(&self.database_config, path_params)?;
+        self.database
+            .get_item(&self.database_config.table, key)
+            .await?
+            .ok_or_else(|| DomainError::NotFound("Item not found".to_string()))
+    }
+
+    /// List the items of one partition, a page at a time.
+    ///
+    /// Query parameters: `limit`, `next_token` and `order` (`asc` or `desc`).
+    pub async fn list_items(
+        &self,
+        request: &RequestContext,
+        query_params: &HashMap<String, String>,
+        path_params: &HashMap<String, String>,
+    ) -> DomainResult<Page> {
+        authorize(request.principal.as_ref(), ITEMS_READ)?;
+        let partition_key = path_params
+            .get("pk")
+            .ok_or_else(|| DomainError::Validation("Missing path parameter 'pk'".to_string()))?;
+
+        let table_name = &self.database_config.table;
+
+        let mut request = QueryRequest::new(&self.database_config.partition_key, partition_key.as_str())
+            .starting_after(query_params.get("next_token").cloned());
+
+        if let Some(limit) = query_params.get("limit") {
+            let limit = limit
+                .parse::<u32>()
+                .ok()
+                .filter(|l| (1..=1000).contains(l))
+                .ok_or_else(|| DomainError::Validation("limit must be between 1 and 1000".to_string()))?;
+            request = request.limit(limit);
         }
 
-        // Example DynamoDB operation - Check if item exists in a demo table
-        // This demonstrates port usage for database operations
-        let table_name = std::env::var("DYNAMO_TABLE").unwrap_or_else(|_| "demo-table".to_string());
-        let mut key = HashMap::new();
-        key.insert("order_id".to_string(), "1111".to_string());
-        key.insert("segment".to_string(), "10".to_string());
-        
-        let dynamo_info = match self.database.get_item(&table_name, key).await {
-            Ok(Some(item)) => {
-                tracing::info!("Found item in DynamoDB: {:?}", item);
-                let item_json = serde_json::to_string_pretty(&item).unwrap_or_else(|_| format!("{:?}", item));
-                format!("DynamoDB Item Found:\n{}", item_json)
-            },
-            Ok(None) => {
-                tracing::info!("Item not found in DynamoDB");
-                "DynamoDB: Item not found".to_string()
-            },
-            Err(e) => {
-                tracing::warn!("DynamoDB error (expected in demo): {}", e);
-                format!("DynamoDB Error: {}", e)
+        match query_params.get("order").map(|o| o.as_str()) {
+            None | Some("asc") => {}
+            Some("desc") => request = request.descending(),
+            Some(other) => {
+                return Err(DomainError::Validation(format!("order must be 'asc' or 'desc', got '{}'", other)))
             }
+        }
+
+        self.database.query(table_name, request).await
+    }
+
+    /// Create or replace the item at `/items/{pk}/{sk}` with optimistic locking.
+    ///
+    /// The body's `data` holds the attributes in DynamoDB JSON. Omit `version`
+    /// to create the item, or send the version last read to update it; a stale
+    /// or missing version fails with `ConditionFailed`.
+    pub async fn put_item(
+        &self,
+        request: &RequestContext,
+        payload: Option<RequestPayload>,
+        path_params: &HashMap<String, String>,
+    ) -> DomainResult<Item> {
+        authorize(request.principal.as_ref(), ITEMS_WRITE)?;
+        let key = item_key(&self.database_config, path_params)?;
+
+        let mut v = Validator::new();
+        let data = sorted(v.required("data", payload.and_then(|p| p.data)));
+        let mut item = Item::new();
+        v.nested("data", |v| {
+            for (name, value) in &data {
+                if let Some(value) = v.deserialize::<ItemValue>(name, value) {
+                    item.insert(name.clone(), value);
+                }
+            }
+        });
+        v.finish()?;
+        // The key always comes from the path
+        item.extend(key);
+
+        let table_name = &self.database_config.table;
+        self.database
+            .put_item_with(table_name, item, PutOptions::new().versioned(VERSION_ATTRIBUTE))
+            .await
+    }
+
+    /// Partially update the existing item at `/items/{pk}/{sk}`, returning it as updated.
+    ///
+    /// The body's `data` may hold `set` and `add` (attributes in DynamoDB JSON),
+    /// `remove` (attribute names) and `version` (the version last read, to fail
+    /// with `ConditionFailed` if it is stale). Every patch bumps the version.
+    pub async fn patch_item(
+        &self,
+        request: &RequestContext,
+        payload: Option<RequestPayload>,
+        path_params: &HashMap<String, String>,
+    ) -> DomainResult<Item> {
+        authorize(request.principal.as_ref(), ITEMS_WRITE)?;
+        let mut request = UpdateRequest::new(item_key(&self.database_config, path_params)?)
+            .condition(Condition::attribute_exists(&self.database_config.partition_key))
+            .returning(ReturnValues::AllNew);
+        let mut expected_version = None;
+
+        let mut v = Validator::new();
+        let data = sorted(v.required("data", payload.and_then(|p| p.data)));
+        v.nested("data", |v| {
+            v.known_fields(data.keys(), &["set", "add", "remove", "version"]);
+            for (field, value) in &data {
+                match field.as_str() {
+                    "set" | "add" => {
+                        let Some(attributes) = v.object(field, value) else { continue };
+                        v.nested(field, |v| {
+                            for (name, value) in attributes {
+                                if name == VERSION_ATTRIBUTE {
+                                    v.add(name, "reserved", format!("'{}' is managed by the service", VERSION_ATTRIBUTE));
+                                } else if let Some(value) = v.deserialize::<ItemValue>(name, value) {
+                                    request.actions.push(if field == "set" {
+                                        UpdateAction::Set(name.clone(), value)
+                                    } else {
+                                        UpdateAction::Add(name.clone(), value)
+                                    });
+                                }
+                            }
+                        });
+                    }
+                    "remove" => {
+                        let Some(names) = v.deserialize::<Vec<String>>(field, value) else { continue };
+                        for (i, name) in names.iter().enumerate() {
+                            if name == VERSION_ATTRIBUTE {
+                                let path = format!("{}[{}]", field, i);
+                                v.add(&path, "reserved", format!("'{}' is managed by the service", VERSION_ATTRIBUTE));
+                            } else {
+                                request.actions.push(UpdateAction::Remove(name.clone()));
+                            }
+                        }
+                    }
+                    "version" => expected_version = v.unsigned(field, value),
+                    // Reported by known_fields
+                    _ => {}
+                }
+            }
+        });
+        v.finish()?;
+
+        request = match expected_version {
+            Some(version) => request.expect_version(VERSION_ATTRIBUTE, version),
+            None => request.increment(VERSION_ATTRIBUTE, 1),
         };
 
-        // Example S3 operation - Check if object exists
-        // This demonstrates port usage for storage operations
-        let bucket = std::env::var("S3_BUCKET").unwrap_or_else(|_| "demo-bucket".to_string());
-        let key = "demo-object.txt";
-        
-        let s3_info = match self.storage.get_object(&bucket, key).await {
-            Ok(data) => {
-                let content = String::from_utf8_lossy(&data);
-                tracing::info!("Found object in S3, size: {} bytes", data.len());
-                format!("S3 Object Found ({})\nContent:\n{}", key, content)
-            },
-            Err(e) => {
-                tracing::warn!("S3 error (expected in demo): {}", e);
-                format!("S3 Error: {}", e)
+        let table_name = &self.database_config.table;
+        match self.database.update_item(table_name, request).await {
+            // Without a version the only condition is that the item exists
+            Err(DomainError::ConditionFailed(_)) if expected_version.is_none() => {
+                Err(DomainError::NotFound("Item not found".to_string()))
+            }
+            result => result?.ok_or_else(|| DomainError::Internal("Update returned no item".to_string())),
+        }
+    }
+
+    /// Delete the item at `/items/{pk}/{sk}`, returning the deleted item.
+    ///
+    /// The optional `version` query parameter makes the delete conditional on it.
+    pub async fn delete_item(
+        &self,
+        request: &RequestContext,
+        query_params: &HashMap<String, String>,
+        path_params: &HashMap<String, String>,
+    ) -> DomainResult<Item> {
+        authorize(request.principal.as_ref(), ITEMS_WRITE)?;
+        let key = item_key(&self.database_config, path_params)?;
+        let conditions = match query_params.get("version") {
+            Some(version) => {
+                let version = version
+                    .parse::<u64>()
+                    .map_err(|_| DomainError::Validation("version must be a non-negative integer".to_string()))?;
+                vec![Condition::version_equals(VERSION_ATTRIBUTE, version)]
             }
+            None => Vec::new(),
         };
 
-        Ok(format!(
-            "Hello from Rust Lambda! Received message: {}\n\n--- AWS Services Info ---\n\n{}\n\n{}\n\nDatabase and storage services invoked successfully.",
-            message, dynamo_info, s3_info
-        ))
+        let table_name = &self.database_config.table;
+        self.database
+            .delete_item(table_name, key, conditions)
+            .await?
+            .ok_or_else(|| DomainError::NotFound("Item not found".to_string()))
+    }
+
+    /// Presigned PUT URL for uploading `/upload-urls/{key+}` straight to S3.
+    ///
+    /// The body's `data` must hold `content_length` (at most the configured `max_upload_bytes`)
+    /// and may hold `content_type`; both are signed, so the upload must send
+    /// exactly those headers. The optional `expires_in` query parameter sets
+    /// the lifetime in seconds.
+    pub async fn upload_url(
+        &self,
+        request: &RequestContext,
+        payload: Option<RequestPayload>,
+        query_params: &HashMap<String, String>,
+        path_params: &HashMap<String, String>,
+    ) -> DomainResult<PresignedUrl> {
+        authorize(request.principal.as_ref(), OBJECTS_WRITE)?;
+        let key = object_key(path_params)?;
+        let expires_in = presign_expiry(query_params)?;
+        let data = sorted(payload.and_then(|p| p.data));
+
+        let mut v = Validator::at("data");
+        v.known_fields(data.keys(), &["content_length", "content_type"]);
+        let content_length = v
+            .required("content_length", data.get("content_length"))
+            .and_then(|length| v.unsigned("content_length", length))
+            .filter(|length| v.range("content_length", *length, 0, self.storage_config.max_upload_bytes));
+        let content_type = data
+            .get("content_type")
+            .and_then(|content_type| v.string("content_type", content_type))
+            .filter(|content_type| v.format("content_type", is_content_type(content_type), "a media type such as image/png"));
+        v.finish()?;
+
+        // Present whenever validation passed
+        let mut request = PresignRequest::put(expires_in).content_length(content_length.unwrap_or_default());
+        if let Some(content_type) = content_type {
+            request = request.content_type(content_type);
+        }
+
+        let bucket = &self.storage_config.bucket;
+        self.storage.presign(bucket, key, request).await
     }
+
+    /// Presigned GET URL for downloading `/download-urls/{key+}` straight from S3.
+    ///
+    /// Fails with `NotFound` if the object does not exist. The optional
+    /// `expires_in` query parameter sets the lifetime in seconds.
+    pub async fn download_url(
+        &self,
+        request: &RequestContext,
+        query_params: &HashMap<String, String>,
+        path_params: &HashMap<String, String>,
+    ) -> DomainResult<PresignedUrl> {
+        authorize(request.principal.as_ref(), OBJECTS_READ)?;
+        let key = object_key(path_params)?;
+        let expires_in = presign_expiry(query_params)?;
+
+        let bucket = &self.storage_config.bucket;
+        if self.storage.head_object(bucket, key).await?.is_none() {
+            return Err(DomainError::NotFound(format!("Object '{}' not found", key)));
+        }
+        self.storage.presign(bucket, key, PresignRequest::get(expires_in)).await
+    }
+
+    /// Store the request body as the object at `/objects/{key+}`.
+    ///
+    /// `content_type` is kept with the object and served back on download;
+    /// it defaults to `application/octet-stream`.
+    pub async fn upload_object(
+        &self,
+        request: &RequestContext,
+        path_params: &HashMap<String, String>,
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> DomainResult<ObjectMetadata> {
+        authorize(request.principal.as_ref(), OBJECTS_WRITE)?;
+        let key = object_key(path_params)?;
+        let content_type = content_type.filter(|c| !c.is_empty()).unwrap_or(DEFAULT_CONTENT_TYPE);
+        let size = body.len() as u64;
+
+        let bucket = &self.storage_config.bucket;
+        self.storage
+            .put_object_with(bucket, key, body, PutObjectOptions::new().content_type(content_type))
+            .await?;
+
+        Ok(ObjectMetadata {
+            key: key.to_string(),
+            size,
+            etag: None,
+            content_type: Some(content_type.to_string()),
+            last_modified: None,
+            metadata: HashMap::new(),
+        })
+    }
+
+    /// Read the object at `/objects/{key+}` to return it in the response body.
+    ///
+    /// Objects over `MAX_INLINE_OBJECT_BYTES` do not fit a Lambda response and
+    /// must be fetched through `/download-urls/{key+}` instead.
+    pub async fn download_object(
+        &self,
+        request: &RequestContext,
+        path_params: &HashMap<String, String>,
+    ) -> DomainResult<(ObjectMetadata, Vec<u8>)> {
+        authorize(request.principal.as_ref(), OBJECTS_READ)?;
+        let key = object_key(path_params)?;
+
+        let bucket = &self.storage_config.bucket;
+        let metadata = self
+            .storage
+            .head_object(bucket, key)
+            .await?
+            .ok_or_else(|| DomainError::NotFound(format!("Object '{}' not found", key)))?;
+        if metadata.size > MAX_INLINE_OBJECT_BYTES {
+            return Err(DomainError::Validation(format!(
+                "Object '{}' is {} bytes, over the {} byte limit; use /download-urls/{} instead",
+                key, metadata.size, MAX_INLINE_OBJECT_BYTES, key
+            )));
+        }
+
+        let body = self.storage.get_object(bucket, key).await?;
+        Ok((metadata, body))
+    }
+}
+
+/// Outcome of one `/demo` lookup
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Lookup<T> {
+    Found { value: T },
+    NotFound,
+    /// The lookup failed, e.g. because the demo table or bucket does not exist
+    Error { error: String },
+}
+
+impl<T> From<DomainResult<Option<T>>> for Lookup<T> {
+    fn from(result: DomainResult<Option<T>>) -> Self {
+        match result {
+            Ok(Some(value)) => Lookup::Found { value },
+            Ok(None) => Lookup::NotFound,
+            Err(e) => Lookup::Error { error: e.to_string() },
+        }
+    }
+}
+
+/// What `/demo` found; the lookups are left out of health checks
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DemoReport {
+    /// Returned as the response message rather than in `data`
+    #[serde(skip)]
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<Lookup<Item>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object: Option<Lookup<ObjectMetadata>>,
+}
+
+/// Key of the item `/demo` looks up
+pub const DEMO_ITEM_PK: &str = "1111";
+pub const DEMO_ITEM_SK: &str = "10";
+/// Object `/demo` looks up
+pub const DEMO_OBJECT_KEY: &str = "demo-object.txt";
+
+/// Scope needed to read items
+pub const ITEMS_READ: &str = "items:read";
+/// Scope needed to create, update or delete items
+pub const ITEMS_WRITE: &str = "items:write";
+/// Scope needed to download objects or get download URLs
+pub const OBJECTS_READ: &str = "objects:read";
+/// Scope needed to upload objects or get upload URLs
+pub const OBJECTS_WRITE: &str = "objects:write";
+
+/// Attribute holding the item version used for optimistic locking
+pub const VERSION_ATTRIBUTE: &str = "version";
+
+/// Largest object served inline: Lambda caps responses at 6 MB, and binary
+/// bodies grow by a third when base64-encoded
+pub const MAX_INLINE_OBJECT_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Content type of uploads that do not declare one
+pub const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Lifetime of presigned URLs when the request does not set `expires_in`
+pub const DEFAULT_PRESIGN_EXPIRY_SECS: u64 = 900;
+
+/// Body `data` in key order, so violations are reported deterministically
+pub(crate) fn sorted(data: Option<HashMap<String, serde_json::Value>>) -> BTreeMap<String, serde_json::Value> {
+    data.unwrap_or_default().into_iter().collect()
+}
+
+fn presign_expiry(query_params: &HashMap<String, String>) -> DomainResult<u64> {
+    match query_params.get("expires_in") {
+        Some(value) => value
+            .parse::<u64>()
+            .map_err(|_| DomainError::Validation("expires_in must be a number of seconds".to_string())),
+        None => Ok(DEFAULT_PRESIGN_EXPIRY_SECS),
+    }
+}
+
+fn object_key(path_params: &HashMap<String, String>) -> DomainResult<&str> {
+    path_params
+        .get("key")
+        .map(String::as_str)
+        .filter(|k| !k.is_empty())
+        .ok_or_else(|| DomainError::Validation("Missing path parameter 'key'".to_string()))
+}
+
+/// Build the primary key of an item from the `pk` and `sk` path parameters.
+///
+/// The sort key is parsed as a number unless the table's sort key type is `S`.
+fn item_key(config: &DatabaseConfig, path_params: &HashMap<String, String>) -> DomainResult<Item> {
+    let param = |name: &str| {
+        path_params
+            .get(name)
+            .ok_or_else(|| DomainError::Validation(format!("Missing path parameter '{}'", name)))
+    };
+    let sk = param("sk")?;
+    let sk_value = match config.sort_key_type {
+        SortKeyType::String => ItemValue::from(sk.as_str()),
+        _ if is_valid_number(sk) => ItemValue::number(sk),
+        _ => return Err(DomainError::Validation(format!("Sort key '{}' must be a number", sk))),
+    };
+
+    Ok(Item::from([
+        (config.partition_key.clone(), ItemValue::from(param("pk")?.as_str())),
+        (config.sort_key.clone(), sk_value),
+    ]))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::auth::Principal;
     use crate::domain::mocks::{MockDatabase, MockStorage};
+    use crate::domain::object::PresignMethod;
 
     #[tokio::test]
-    async fn test_process_request_with_payload() {
-        let db = Box::new(MockDatabase::new());
-        let storage = Box::new(MockStorage::new());
-        let processor = RequestProcessor::new(db, storage);
+    async fn test_demo_echoes_message() {
+        let processor = RequestProcessor::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
 
         let payload = Some(RequestPayload {
             message: Some("Test message".to_string()),
             data: None,
         });
+        let report = processor.demo(payload, &HashMap::new()).await.unwrap();
+        assert_eq!(report.message, "Received message: Test message");
 
-        let result = processor.process_request(payload, &HashMap::new(), &HashMap::new()).await;
-        assert!(result.is_ok());
-        assert!(result.unwrap().contains("Test message"));
+        let report = processor.demo(None, &HashMap::new()).await.unwrap();
+        assert_eq!(report.message, "Received message: No payload provided");
+        assert_eq!(report.item, Some(Lookup::NotFound));
+        assert_eq!(report.object, Some(Lookup::NotFound));
     }
 
     #[tokio::test]
-    async fn test_process_request_without_payload() {
-        let db = Box::new(MockDatabase::new());
-        let storage = Box::new(MockStorage::new());
-        let processor = RequestProcessor::new(db, storage);
+    async fn test_demo_health_check() {
+        let db = Arc::new(MockDatabase::new());
+        let processor = RequestProcessor::new(db.clone(), Arc::new(MockStorage::new()));
 
-        let result = processor.process_request(None, &HashMap::new(), &HashMap::new()).await;
-        assert!(result.is_ok());
-        assert!(result.unwrap().contains("No payload provided"));
+        let query_params = HashMap::from([("health".to_string(), "true".to_string())]);
+        let report = processor.demo(None, &query_params).await.unwrap();
+        assert_eq!(report.message, "Service is healthy");
+        assert_eq!(serde_json::to_value(&report).unwrap(), serde_json::json!({}));
+        assert_eq!(db.call_count(), 0);
     }
 
     #[tokio::test]
-    async fn test_health_check() {
-        let db = Box::new(MockDatabase::new());
-        let storage = Box::new(MockStorage::new());
-        let processor = RequestProcessor::new(db, storage);
+    async fn test_demo_reports_item_and_object() {
+        let mut item = Item::new();
+        item.insert("order_id".to_string(), ItemValue::from("1111"));
+        item.insert("segment".to_string(), ItemValue::number(10));
+        item.insert("name".to_string(), ItemValue::from("test-item"));
+        let db = MockDatabase::new()
+            .with_table("demo-table", &["order_id", "segment"])
+            .with_item("demo-table", item.clone());
+        let storage = MockStorage::new().with_object("demo-bucket", "demo-object.txt", b"test data".to_vec());
+        let processor = RequestProcessor::new(Arc::new(db), Arc::new(storage));
 
-        let mut query_params = HashMap::new();
-        query_params.insert("health".to_string(), "true".to_string());
+        let report = processor.demo(None, &HashMap::new()).await.unwrap();
+        assert_eq!(report.item, Some(Lookup::Found { value: item }));
+        let data = serde_json::to_value(&report).unwrap();
+        assert_eq!(data["item"]["status"], "found");
+        assert_eq!(data["item"]["value"]["name"], serde_json::json!({"S": "test-item"}));
+        assert_eq!(data["object"]["value"]["size"], 9);
+    }
+
+    #[tokio::test]
+    async fn test_get_item() {
+        let mut item = Item::new();
+        item.insert("order_id".to_string(), ItemValue::from("1111"));
+        item.insert("segment".to_string(), ItemValue::number(10));
+        let db = MockDatabase::new()
+            .with_table("demo-table", &["order_id", "segment"])
+            .with_item("demo-table", item.clone());
+        let processor = RequestProcessor::new(Arc::new(db), Arc::new(MockStorage::new()));
 
-        let result = processor.process_request(None, &query_params, &HashMap::new()).await;
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), "Service is healthy");
+        assert_eq!(processor.get_item(&RequestContext::default(), &item_path()).await.unwrap(), item);
+        let missing = HashMap::from([
+            ("pk".to_string(), "1111".to_string()),
+            ("sk".to_string(), "11".to_string()),
+        ]);
+        let result = processor.get_item(&RequestContext::default(), &missing).await;
+        assert!(matches!(result, Err(DomainError::NotFound(_))));
     }
 
     #[tokio::test]
-    async fn test_process_request_with_database_item() {
-        let mut item = HashMap::new();
-        item.insert("name".to_string(), "test-item".to_string());
+    async fn test_list_items_paginates() {
+        let mut db = MockDatabase::new().with_table("demo-table", &["order_id", "segment"]);
+        for segment in 1..=3 {
+            let mut item = Item::new();
+            item.insert("order_id".to_string(), ItemValue::from("1111"));
+            item.insert("segment".to_string(), ItemValue::number(segment));
+            db = db.with_item("demo-table", item);
+        }
+        let processor = RequestProcessor::new(Arc::new(db), Arc::new(MockStorage::new()));
 
-        let db = Box::new(MockDatabase::new().with_item("demo-table", "demo-key", item));
-        let storage = Box::new(MockStorage::new());
-        let processor = RequestProcessor::new(db, storage);
+        let path_params = HashMap::from([("pk".to_string(), "1111".to_string())]);
+        let mut query_params = HashMap::from([
+            ("limit".to_string(), "2".to_string()),
+            ("order".to_string(), "desc".to_string()),
+        ]);
 
-        let payload = Some(RequestPayload {
-            message: Some("Test".to_string()),
-            data: None,
-        });
+        let first = processor.list_items(&RequestContext::default(), &query_params, &path_params).await.unwrap();
+        assert_eq!(first.items.len(), 2);
+        assert_eq!(first.items[0]["segment"].as_number::<i64>(), Some(3));
 
-        let result = processor.process_request(payload, &HashMap::new(), &HashMap::new()).await;
-        assert!(result.is_ok());
+        query_params.insert("next_token".to_string(), first.next_token.unwrap());
+        let second = processor.list_items(&RequestContext::default(), &query_params, &path_params).await.unwrap();
+        assert_eq!(second.items.len(), 1);
+        assert!(second.next_token.is_none());
     }
 
     #[tokio::test]
-    async fn test_process_request_with_storage_object() {
-        let db = Box::new(MockDatabase::new());
-        let storage = Box::new(MockStorage::new().with_object("demo-bucket", "demo-object.txt", b"test data".to_vec()));
-        let processor = RequestProcessor::new(db, storage);
+    async fn test_list_items_rejects_bad_limit() {
+        let processor = RequestProcessor::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
+        let path_params = HashMap::from([("pk".to_string(), "1111".to_string())]);
+        let query_params = HashMap::from([("limit".to_string(), "0".to_string())]);
 
-        let payload = Some(RequestPayload {
-            message: Some("Test".to_string()),
-            data: None,
+        let result = processor.list_items(&RequestContext::default(), &query_params, &path_params).await;
+        assert!(matches!(result, Err(DomainError::Validation(_))));
+    }
+
+    /// Field path and rule of every violation in an `InvalidInput` error
+    fn invalid_fields<T: std::fmt::Debug>(result: DomainResult<T>) -> Vec<(String, String)> {
+        match result {
+            Err(DomainError::InvalidInput { errors, .. }) => errors.into_iter().map(|e| (e.field, e.rule)).collect(),
+            other => panic!("expected InvalidInput, got {:?}", other),
+        }
+    }
+
+    fn put_payload(data: serde_json::Value) -> Option<RequestPayload> {
+        Some(RequestPayload {
+            message: None,
+            data: serde_json::from_value(data).unwrap(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_put_item_versions_updates() {
+        let db = Arc::new(MockDatabase::new().with_table("demo-table", &["order_id", "segment"]));
+        let processor = RequestProcessor::new(db.clone(), Arc::new(MockStorage::new()));
+        let path_params = HashMap::from([
+            ("pk".to_string(), "1111".to_string()),
+            ("sk".to_string(), "10".to_string()),
+        ]);
+
+        let created = processor
+            .put_item(&RequestContext::default(), put_payload(serde_json::json!({"status": {"S": "open"}})), &path_params)
+            .await
+            .unwrap();
+        assert_eq!(created["version"], ItemValue::number(1));
+        assert_eq!(created["segment"], ItemValue::number(10));
+
+        let update = serde_json::json!({"status": {"S": "closed"}, "version": {"N": "1"}});
+        let updated = processor.put_item(&RequestContext::default(), put_payload(update.clone()), &path_params).await.unwrap();
+        assert_eq!(updated["version"], ItemValue::number(2));
+
+        // Replaying the same update carries a stale version
+        let result = processor.put_item(&RequestContext::default(), put_payload(update), &path_params).await;
+        assert!(matches!(result, Err(DomainError::ConditionFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_put_item_uses_configured_table_and_key() {
+        let db = Arc::new(MockDatabase::new().with_table("orders", &["customer_id", "order_ref"]));
+        let database = DatabaseConfig {
+            table: "orders".to_string(),
+            partition_key: "customer_id".to_string(),
+            sort_key: "order_ref".to_string(),
+            sort_key_type: SortKeyType::String,
+        };
+        let processor = RequestProcessor::new(db.clone(), Arc::new(MockStorage::new()))
+            .with_config(database, StorageConfig::default());
+        let path_params = HashMap::from([
+            ("pk".to_string(), "c-7".to_string()),
+            ("sk".to_string(), "2024-001".to_string()),
+        ]);
+
+        let created = processor
+            .put_item(&RequestContext::default(), put_payload(serde_json::json!({"status": {"S": "open"}})), &path_params)
+            .await
+            .unwrap();
+        assert_eq!(created["order_ref"], ItemValue::from("2024-001"));
+        assert_eq!(db.call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_put_item_rejects_invalid_attribute() {
+        let processor = RequestProcessor::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
+        let path_params = HashMap::from([
+            ("pk".to_string(), "1111".to_string()),
+            ("sk".to_string(), "10".to_string()),
+        ]);
+
+        let payload = put_payload(serde_json::json!({"total": {"N": "abc"}, "note": {"S": 1}}));
+        let fields = invalid_fields(processor.put_item(&RequestContext::default(), payload, &path_params).await);
+        assert_eq!(fields.len(), 2);
+        assert!(fields[0].0.starts_with("data.note") && fields[1].0.starts_with("data.total"));
+
+        let fields = invalid_fields(processor.put_item(&RequestContext::default(), None, &path_params).await);
+        assert_eq!(fields, vec![("data".to_string(), "required".to_string())]);
+    }
+
+    fn item_path() -> HashMap<String, String> {
+        HashMap::from([
+            ("pk".to_string(), "1111".to_string()),
+            ("sk".to_string(), "10".to_string()),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_patch_item() {
+        let db = Arc::new(MockDatabase::new().with_table("demo-table", &["order_id", "segment"]));
+        let processor = RequestProcessor::new(db, Arc::new(MockStorage::new()));
+
+        let missing = processor
+            .patch_item(&RequestContext::default(), put_payload(serde_json::json!({"set": {"status": {"S": "closed"}}})), &item_path())
+            .await;
+        assert!(matches!(missing, Err(DomainError::NotFound(_))));
+
+        processor
+            .put_item(&RequestContext::default(), put_payload(serde_json::json!({"status": {"S": "open"}, "note": {"S": "x"}})), &item_path())
+            .await
+            .unwrap();
+
+        let patch = serde_json::json!({
+            "set": {"status": {"S": "closed"}},
+            "add": {"views": {"N": "2"}},
+            "remove": ["note"],
+            "version": 1
+        });
+        let patched = processor.patch_item(&RequestContext::default(), put_payload(patch.clone()), &item_path()).await.unwrap();
+        assert_eq!(patched["status"].as_str(), Some("closed"));
+        assert_eq!(patched["views"], ItemValue::number(2));
+        assert_eq!(patched["version"], ItemValue::number(2));
+        assert!(!patched.contains_key("note"));
+
+        let stale = processor.patch_item(&RequestContext::default(), put_payload(patch), &item_path()).await;
+        assert!(matches!(stale, Err(DomainError::ConditionFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_patch_item_rejects_unknown_fields() {
+        let processor = RequestProcessor::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
+        let patch = serde_json::json!({
+            "replace": {},
+            "set": {"version": {"N": "9"}},
+            "remove": ["note", "version"],
+            "version": "2"
         });
+        let fields = invalid_fields(processor.patch_item(&RequestContext::default(), put_payload(patch), &item_path()).await);
+        assert_eq!(
+            fields,
+            vec![
+                ("data.replace".to_string(), "unknown".to_string()),
+                ("data.remove[1]".to_string(), "reserved".to_string()),
+                ("data.set.version".to_string(), "reserved".to_string()),
+                ("data.version".to_string(), "type".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_item() {
+        let db = Arc::new(MockDatabase::new().with_table("demo-table", &["order_id", "segment"]));
+        let processor = RequestProcessor::new(db, Arc::new(MockStorage::new()));
+        processor.put_item(&RequestContext::default(), put_payload(serde_json::json!({})), &item_path()).await.unwrap();
+
+        let stale = HashMap::from([("version".to_string(), "5".to_string())]);
+        let result = processor.delete_item(&RequestContext::default(), &stale, &item_path()).await;
+        assert!(matches!(result, Err(DomainError::ConditionFailed(_))));
+
+        let deleted = processor.delete_item(&RequestContext::default(), &HashMap::new(), &item_path()).await.unwrap();
+        assert_eq!(deleted["version"], ItemValue::number(1));
+
+        let result = processor.delete_item(&RequestContext::default(), &HashMap::new(), &item_path()).await;
+        assert!(matches!(result, Err(DomainError::NotFound(_))));
+    }
+
+    fn key_path(key: &str) -> HashMap<String, String> {
+        HashMap::from([("key".to_string(), key.to_string())])
+    }
+
+    #[tokio::test]
+    async fn test_upload_url_signs_content_headers() {
+        let processor = RequestProcessor::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
+        let payload = put_payload(serde_json::json!({"content_type": "text/csv", "content_length": 10}));
+        let url = processor.upload_url(&RequestContext::default(), payload, &HashMap::new(), &key_path("reports/a.csv")).await.unwrap();
+        assert_eq!(url.method, PresignMethod::Put);
+        assert_eq!(url.expires_in, DEFAULT_PRESIGN_EXPIRY_SECS);
+        assert_eq!(url.headers["content-type"], "text/csv");
+        assert_eq!(url.headers["content-length"], "10");
+    }
+
+    #[tokio::test]
+    async fn test_upload_url_rejects_bad_requests() {
+        let processor = RequestProcessor::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
+
+        let too_large = put_payload(serde_json::json!({"content_length": u64::MAX, "content_type": "csv"}));
+        let fields = invalid_fields(processor.upload_url(&RequestContext::default(), too_large, &HashMap::new(), &key_path("a.bin")).await);
+        assert_eq!(
+            fields,
+            vec![
+                ("data.content_length".to_string(), "range".to_string()),
+                ("data.content_type".to_string(), "format".to_string()),
+            ]
+        );
+
+        let missing_length = put_payload(serde_json::json!({"content_type": "text/plain"}));
+        let fields = invalid_fields(processor.upload_url(&RequestContext::default(), missing_length, &HashMap::new(), &key_path("a.bin")).await);
+        assert_eq!(fields, vec![("data.content_length".to_string(), "required".to_string())]);
+
+        let payload = put_payload(serde_json::json!({"content_length": 1}));
+        let expiry = HashMap::from([("expires_in".to_string(), "604801".to_string())]);
+        let result = processor.upload_url(&RequestContext::default(), payload, &expiry, &key_path("a.bin")).await;
+        assert!(matches!(result, Err(DomainError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_operations_require_their_scope() {
+        let db = MockDatabase::new().with_table("demo-table", &["order_id", "segment"]);
+        let processor = RequestProcessor::new(Arc::new(db), Arc::new(MockStorage::new()));
+        let reader = RequestContext {
+            principal: Some(Principal {
+                subject: "user-1".to_string(),
+                scopes: vec![ITEMS_READ.to_string()],
+                ..Principal::default()
+            }),
+            ..RequestContext::default()
+        };
+
+        let result = processor.put_item(&reader, put_payload(serde_json::json!({})), &item_path()).await;
+        assert!(matches!(result, Err(DomainError::Forbidden(_))));
+        let pk = HashMap::from([("pk".to_string(), "1111".to_string())]);
+        assert!(processor.list_items(&reader, &HashMap::new(), &pk).await.is_ok());
+        let result = processor.download_object(&reader, &key_path("a.bin")).await;
+        assert!(matches!(result, Err(DomainError::Forbidden(_))));
+    }
+
+    #[tokio::test]
+    async fn test_upload_and_download_object() {
+        let processor = RequestProcessor::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
+        let bytes = vec![0x89, 0x50, 0x4e, 0x47, 0x00, 0xff];
 
-        let result = processor.process_request(payload, &HashMap::new(), &HashMap::new()).await;
-        assert!(result.is_ok());
+        let metadata = processor.upload_object(&RequestContext::default(), &key_path("img/a.png"), bytes.clone(), Some("image/png")).await.unwrap();
+        assert_eq!(metadata.size, 6);
+
+        let (metadata, body) = processor.download_object(&RequestContext::default(), &key_path("img/a.png")).await.unwrap();
+        assert_eq!(body, bytes);
+        assert_eq!(metadata.content_type.as_deref(), Some("image/png"));
+
+        processor.upload_object(&RequestContext::default(), &key_path("raw.bin"), Vec::new(), None).await.unwrap();
+        let (metadata, _) = processor.download_object(&RequestContext::default(), &key_path("raw.bin")).await.unwrap();
+        assert_eq!(metadata.content_type.as_deref(), Some(DEFAULT_CONTENT_TYPE));
+    }
+
+    #[tokio::test]
+    async fn test_download_object_rejects_large_objects() {
+        let storage = MockStorage::new().with_generated_object("demo-bucket", "big.bin", MAX_INLINE_OBJECT_BYTES + 1);
+        let processor = RequestProcessor::new(Arc::new(MockDatabase::new()), Arc::new(storage));
+        let result = processor.download_object(&RequestContext::default(), &key_path("big.bin")).await;
+        assert!(matches!(result, Err(DomainError::Validation(_))));
     }
 }
@@ -0,0 +1,803 @@
+use crate::application::api_keys::DEFAULT_CACHE_TTL_SECS;
+use crate::application::idempotency::{DEFAULT_LOCK_SECS, DEFAULT_TTL_SECS};
+use crate::application::secrets::{SecretsService, DEFAULT_CACHE_TTL_SECS as DEFAULT_SECRET_CACHE_TTL_SECS};
+use crate::domain::errors::{DomainError, DomainResult};
+use crate::domain::rate_limit::RateLimit;
+use crate::domain::secret::SecretReference;
+use crate::http::auth::DEFAULT_LEEWAY_SECS;
+use crate::http::cors::AllowedOrigin;
+use crate::infrastructure::s3::{MultipartConfig, MIN_PART_SIZE};
+use crate::metrics::{MAX_DIMENSIONS, RESERVED_DIMENSIONS};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable naming the optional configuration file
+pub const CONFIG_FILE_VAR: &str = "APP_CONFIG_FILE";
+/// Environment variable selecting the stage
+pub const STAGE_VAR: &str = "APP_ENV";
+/// Prefix of environment variables setting `credentials`, e.g.
+/// `CREDENTIAL_PAYMENTS_API_KEY` for `credentials.payments_api_key`
+pub const CREDENTIAL_VAR_PREFIX: &str = "CREDENTIAL_";
+
+/// How an environment variable's text is turned into a setting
+#[derive(Clone, Copy)]
+enum EnvValue {
+    Text,
+    Number,
+    Bool,
+    /// Comma-separated, empty entries dropped
+    List,
+    /// A number, or empty for none
+    OptionalNumber,
+    /// Comma-separated `<key>=<value>` entries, split at the last `=`
+    Pairs,
+}
+
+/// Environment variables and the setting each one overrides
+const ENV_VARS: &[(&str, &str, EnvValue)] = &[
+    ("AWS_REGION", "region", EnvValue::Text),
+    ("RUST_LOG", "log_level", EnvValue::Text),
+    ("DYNAMO_TABLE", "database.table", EnvValue::Text),
+    ("DYNAMO_PARTITION_KEY", "database.partition_key", EnvValue::Text),
+    ("DYNAMO_SORT_KEY", "database.sort_key", EnvValue::Text),
+    ("DYNAMO_SORT_KEY_TYPE", "database.sort_key_type", EnvValue::Text),
+    ("S3_BUCKET", "storage.bucket", EnvValue::Text),
+    ("MAX_UPLOAD_BYTES", "storage.max_upload_bytes", EnvValue::Number),
+    ("S3_MULTIPART_THRESHOLD_BYTES", "storage.multipart_threshold_bytes", EnvValue::Number),
+    ("S3_MULTIPART_PART_SIZE_BYTES", "storage.multipart_part_size_bytes", EnvValue::Number),
+    ("JWT_JWKS", "auth.jwks", EnvValue::Text),
+    ("JWT_JWKS_FILE", "auth.jwks_file", EnvValue::Text),
+    ("JWT_ISSUER", "auth.issuer", EnvValue::Text),
+    ("JWT_AUDIENCE", "auth.audience", EnvValue::List),
+    ("JWT_LEEWAY_SECS", "auth.leeway_secs", EnvValue::Number),
+    ("API_KEYS_TABLE", "api_keys.table", EnvValue::Text),
+    ("API_KEY_CACHE_TTL_SECS", "api_keys.cache_ttl_secs", EnvValue::Number),
+    ("CORS_ALLOWED_ORIGINS", "cors.allowed_origins", EnvValue::List),
+    ("CORS_ALLOWED_HEADERS", "cors.allowed_headers", EnvValue::List),
+    ("CORS_EXPOSED_HEADERS", "cors.exposed_headers", EnvValue::List),
+    ("CORS_ALLOW_CREDENTIALS", "cors.allow_credentials", EnvValue::Bool),
+    ("CORS_MAX_AGE", "cors.max_age", EnvValue::OptionalNumber),
+    ("RATE_LIMIT_TABLE", "rate_limit.table", EnvValue::Text),
+    ("RATE_LIMIT_DEFAULT", "rate_limit.default", EnvValue::Text),
+    ("RATE_LIMIT_ROUTES", "rate_limit.routes", EnvValue::Pairs),
+    ("IDEMPOTENCY_TABLE", "idempotency.table", EnvValue::Text),
+    ("IDEMPOTENCY_TTL_SECS", "idempotency.ttl_secs", EnvValue::Number),
+    ("IDEMPOTENCY_LOCK_SECS", "idempotency.lock_secs", EnvValue::Number),
+    ("SECRETS_PROVIDER", "secrets.provider", EnvValue::Text),
+    ("SECRETS_FILE", "secrets.file", EnvValue::Text),
+    ("SECRETS_CACHE_TTL_SECS", "secrets.cache_ttl_secs", EnvValue::Number),
+    ("METRICS_NAMESPACE", "metrics.namespace", EnvValue::Text),
+    ("METRICS_DIMENSIONS", "metrics.dimensions", EnvValue::Pairs),
+];
+
+/// Deployment stage, matching the contexts of `CargoLambda.toml`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    #[default]
+    Dev,
+    Staging,
+    Production,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 3] = [Stage::Dev, Stage::Staging, Stage::Production];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Dev => "dev",
+            Stage::Staging => "staging",
+            Stage::Production => "production",
+        }
+    }
+
+    pub fn parse(value: &str) -> DomainResult<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|stage| stage.as_str().eq_ignore_ascii_case(value.trim()))
+            .ok_or_else(|| invalid(format!("stage '{}' is not one of dev, staging, production", value)))
+    }
+}
+
+/// A setting kept out of logs: `Debug` prints `[REDACTED]` instead of the value.
+///
+/// It may hold a `secret://name#field` reference, replaced by the secret
+/// itself in `Config::resolve_secrets`.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+/// Type of the items table's sort key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum SortKeyType {
+    #[default]
+    #[serde(rename = "N")]
+    Number,
+    #[serde(rename = "S")]
+    String,
+}
+
+/// Items table used by `RequestProcessor`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub table: String,
+    pub partition_key: String,
+    pub sort_key: String,
+    pub sort_key_type: SortKeyType,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            table: "demo-table".to_string(),
+            partition_key: "order_id".to_string(),
+            sort_key: "segment".to_string(),
+            sort_key_type: SortKeyType::Number,
+        }
+    }
+}
+
+/// Bucket used by `RequestProcessor`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub bucket: String,
+    /// Largest upload a presigned URL is issued for
+    pub max_upload_bytes: u64,
+    /// Streamed uploads of at least this many bytes go to S3 in parts; at
+    /// most `multipart_part_size_bytes`, the most that is ever buffered
+    pub multipart_threshold_bytes: usize,
+    /// Size of every part but the last; S3 requires at least 5 MiB
+    pub multipart_part_size_bytes: usize,
+}
+
+impl StorageConfig {
+    pub fn multipart(&self) -> MultipartConfig {
+        MultipartConfig {
+            threshold: self.multipart_threshold_bytes,
+            part_size: self.multipart_part_size_bytes,
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        let multipart = MultipartConfig::default();
+        Self {
+            bucket: "demo-bucket".to_string(),
+            max_upload_bytes: 100 * 1024 * 1024,
+            multipart_threshold_bytes: multipart.threshold,
+            multipart_part_size_bytes: multipart.part_size,
+        }
+    }
+}
+
+/// JWT bearer authentication; off unless `jwks` or `jwks_file` is set
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// The JWKS document itself; redacted, as a misconfigured one may carry private key parameters
+    pub jwks: Option<Secret>,
+    /// Path to the JWKS document, used when `jwks` is not set
+    pub jwks_file: Option<String>,
+    pub issuer: String,
+    pub audience: Vec<String>,
+    pub leeway_secs: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            jwks: None,
+            jwks_file: None,
+            issuer: String::new(),
+            audience: Vec::new(),
+            leeway_secs: DEFAULT_LEEWAY_SECS,
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.jwks.is_some() || self.jwks_file.is_some()
+    }
+}
+
+/// `x-api-key` authentication; off unless `table` is set
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiKeysConfig {
+    pub table: Option<String>,
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for ApiKeysConfig {
+    fn default() -> Self {
+        Self {
+            table: None,
+            cache_ttl_secs: DEFAULT_CACHE_TTL_SECS as u64,
+        }
+    }
+}
+
+/// Cross-origin policy; entries are parsed by `CorsPolicy::from_config`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub exposed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age: Option<u64>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec!["*".to_string()],
+            allowed_headers: vec!["Content-Type".to_string(), "Authorization".to_string()],
+            exposed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: Some(600),
+        }
+    }
+}
+
+/// Per-client rate limits; off unless `table` is set
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub table: Option<String>,
+    pub default: Option<RateLimit>,
+    /// Limits by route key, e.g. `POST /api-keys`
+    pub routes: BTreeMap<String, RateLimit>,
+}
+
+/// `Idempotency-Key` handling; off unless `table` is set
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdempotencyConfig {
+    pub table: Option<String>,
+    pub ttl_secs: u64,
+    pub lock_secs: u64,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            table: None,
+            ttl_secs: DEFAULT_TTL_SECS as u64,
+            lock_secs: DEFAULT_LOCK_SECS as u64,
+        }
+    }
+}
+
+/// Every setting of the function, loaded once per cold start.
+///
+/// Layers, lowest first: built-in defaults, the file named by
+/// `APP_CONFIG_FILE`, that file's table for the active stage, then
+/// environment variables. `Debug` output redacts `Secret` values, so the
+/// whole struct can be logged.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub stage: Stage,
+    pub region: String,
+    pub log_level: String,
+    pub database: DatabaseConfig,
+    pub storage: StorageConfig,
+    pub auth: AuthConfig,
+    pub api_keys: ApiKeysConfig,
+    pub cors: CorsConfig,
+    pub rate_limit: RateLimitConfig,
+    pub idempotency: IdempotencyConfig,
+    pub secrets: SecretsConfig,
+    pub metrics: MetricsConfig,
+    /// Named credentials for downstream services, usually `secret://` references
+    pub credentials: BTreeMap<String, Secret>,
+}
+
+/// Where `secret://` references are looked up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecretsProvider {
+    SecretsManager,
+    Ssm,
+    /// A local JSON file, for running without AWS
+    File,
+}
+
+/// Secret lookups; references cannot be resolved unless `provider` is set
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecretsConfig {
+    pub provider: Option<SecretsProvider>,
+    /// Path of the JSON file read by the `file` provider
+    pub file: Option<String>,
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            file: None,
+            cache_ttl_secs: DEFAULT_SECRET_CACHE_TTL_SECS as u64,
+        }
+    }
+}
+
+/// CloudWatch metrics in Embedded Metric Format; off unless `namespace` is set
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub namespace: Option<String>,
+    /// Dimensions added to every metric, e.g. `Service = "orders-api"`
+    pub dimensions: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            stage: Stage::Dev,
+            region: "us-east-1".to_string(),
+            log_level: "info".to_string(),
+            database: DatabaseConfig::default(),
+            storage: StorageConfig::default(),
+            auth: AuthConfig::default(),
+            api_keys: ApiKeysConfig::default(),
+            cors: CorsConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            idempotency: IdempotencyConfig::default(),
+            secrets: SecretsConfig::default(),
+            metrics: MetricsConfig::default(),
+            credentials: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Configuration from the process environment and the file it names
+    pub fn load() -> DomainResult<Self> {
+        let env: HashMap<String, String> = std::env::vars().collect();
+        let file = match env.get(CONFIG_FILE_VAR) {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path)
+                    .map_err(|e| invalid(format!("failed to read '{}': {}", path, e)))?;
+                Some(parse_file(path, &contents)?)
+            }
+            None => None,
+        };
+        Self::resolve(&env, file)
+    }
+
+    /// Layer a parsed configuration file and environment variables over the
+    /// defaults, then validate the result.
+    ///
+    /// The stage comes from `APP_ENV`, else the file's `stage`, else `dev`.
+    pub fn resolve(env: &HashMap<String, String>, file: Option<Value>) -> DomainResult<Self> {
+        let mut base = match file {
+            Some(Value::Object(file)) => file,
+            Some(_) => return Err(invalid("the configuration file must hold a table")),
+            None => Map::new(),
+        };
+        let stage = match (env.get(STAGE_VAR), base.get("stage")) {
+            (Some(stage), _) => Stage::parse(stage)?,
+            (None, Some(Value::String(stage))) => Stage::parse(stage)?,
+            (None, Some(_)) => return Err(invalid("stage must be a string")),
+            (None, None) => Stage::default(),
+        };
+
+        let mut overrides = Vec::new();
+        for candidate in Stage::ALL {
+            if let Some(table) = base.remove(candidate.as_str()) {
+                if candidate == stage {
+                    overrides.push(table);
+                }
+            }
+        }
+        let mut merged = Value::Object(base);
+        for table in overrides {
+            if !table.is_object() {
+                return Err(invalid(format!("[{}] must be a table", stage.as_str())));
+            }
+            merge(&mut merged, table);
+        }
+        merge(&mut merged, env_overrides(env)?);
+        merge(&mut merged, Value::from(stage.as_str()).into_field("stage"));
+
+        let config: Config = serde_path_to_error::deserialize(merged).map_err(|e| invalid(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the settings that deserialize but cannot work, reporting all of them at once
+    pub fn validate(&self) -> DomainResult<()> {
+        let mut problems = Vec::new();
+        let mut require = |ok: bool, problem: &str| {
+            if !ok {
+                problems.push(problem.to_string());
+            }
+        };
+
+        require(!self.region.trim().is_empty(), "region must not be empty");
+        require(
+            valid_log_filter(&self.log_level),
+            "log_level must be a level (trace, debug, info, warn, error, off) or RUST_LOG directives such as info,aws_config=warn",
+        );
+        require(!self.database.table.is_empty(), "database.table must not be empty");
+        require(!self.database.partition_key.is_empty(), "database.partition_key must not be empty");
+        require(!self.database.sort_key.is_empty(), "database.sort_key must not be empty");
+        require(!self.storage.bucket.is_empty(), "storage.bucket must not be empty");
+        require(self.storage.max_upload_bytes > 0, "storage.max_upload_bytes must be positive");
+        require(self.storage.multipart_threshold_bytes > 0, "storage.multipart_threshold_bytes must be positive");
+        require(
+            self.storage.multipart_threshold_bytes <= self.storage.multipart_part_size_bytes,
+            "storage.multipart_threshold_bytes must not exceed storage.multipart_part_size_bytes",
+        );
+        require(
+            self.storage.multipart_part_size_bytes >= MIN_PART_SIZE,
+            &format!("storage.multipart_part_size_bytes must be at least {} (5 MiB)", MIN_PART_SIZE),
+        );
+        require(
+            !(self.cors.allow_credentials && self.cors.allowed_origins.iter().any(|origin| AllowedOrigin::parse(origin) == AllowedOrigin::Any)),
+            "cors.allowed_origins must list the trusted origins instead of '*' when cors.allow_credentials is true",
+        );
+        if self.auth.is_enabled() {
+            require(!self.auth.issuer.is_empty(), "auth.issuer must be set when a JWKS is configured");
+            require(!self.auth.audience.is_empty(), "auth.audience must be set when a JWKS is configured");
+        }
+        for (name, table) in [
+            ("api_keys.table", &self.api_keys.table),
+            ("rate_limit.table", &self.rate_limit.table),
+            ("idempotency.table", &self.idempotency.table),
+            ("metrics.namespace", &self.metrics.namespace),
+        ] {
+            require(table.as_deref() != Some(""), &format!("{} must not be empty", name));
+        }
+        require(self.idempotency.ttl_secs > 0, "idempotency.ttl_secs must be positive");
+        require(self.idempotency.lock_secs > 0, "idempotency.lock_secs must be positive");
+        require(
+            self.secrets.provider != Some(SecretsProvider::File) || self.secrets.file.is_some(),
+            "secrets.file must be set for the file provider",
+        );
+        // Each metric adds up to two dimensions of its own
+        require(
+            self.metrics.dimensions.len() + 2 <= MAX_DIMENSIONS,
+            &format!("metrics.dimensions may have at most {} entries", MAX_DIMENSIONS - 2),
+        );
+        for (name, value) in &self.metrics.dimensions {
+            require(
+                !name.is_empty() && !value.is_empty(),
+                "metrics.dimensions names and values must not be empty",
+            );
+            require(
+                !RESERVED_DIMENSIONS.contains(&name.as_str()),
+                &format!("metrics.dimensions cannot set '{}', which metrics set themselves", name),
+            );
+        }
+        for (name, secret) in self.secret_settings() {
+            match SecretReference::parse(secret.expose()) {
+                Ok(Some(_)) => require(
+                    self.secrets.provider.is_some(),
+                    &format!("{} references a secret but secrets.provider is not set", name),
+                ),
+                Ok(None) => {}
+                Err(e) => require(false, &format!("{}: {}", name, e.message())),
+            }
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(invalid(problems.join("; "))),
+        }
+    }
+
+    /// Replace every `secret://` reference in a `Secret` setting with the secret it names
+    pub async fn resolve_secrets(&mut self, secrets: &SecretsService) -> DomainResult<()> {
+        let secret_settings = self.auth.jwks.iter_mut().chain(self.credentials.values_mut());
+        for setting in secret_settings {
+            *setting = Secret::new(secrets.resolve(setting.expose()).await?);
+        }
+        Ok(())
+    }
+
+    /// `Secret` settings by their path, e.g. `credentials.payments_api_key`
+    fn secret_settings(&self) -> impl Iterator<Item = (String, &Secret)> {
+        let jwks = self.auth.jwks.iter().map(|jwks| ("auth.jwks".to_string(), jwks));
+        jwks.chain(self.credentials.iter().map(|(name, secret)| (format!("credentials.{}", name), secret)))
+    }
+
+    /// `log_level` as a tracing filter; `validate` guarantees it parses
+    pub fn log_filter(&self) -> EnvFilter {
+        EnvFilter::try_new(&self.log_level).unwrap_or_else(|_| EnvFilter::new("info"))
+    }
+}
+
+/// `RUST_LOG` syntax, but with a level in every directive: a bare word would
+/// otherwise be taken as a target name and silently enable everything for it
+fn valid_log_filter(filter: &str) -> bool {
+    let has_level = |directive: &str| {
+        let level = directive.rsplit_once('=').map_or(directive, |(_, level)| level);
+        level.trim().parse::<LevelFilter>().is_ok()
+    };
+    EnvFilter::try_new(filter).is_ok() && filter.split(',').filter(|d| !d.trim().is_empty()).all(has_level)
+}
+
+/// Parse a configuration file as TOML or JSON, by its extension
+pub fn parse_file(path: &str, contents: &str) -> DomainResult<Value> {
+    if path.ends_with(".toml") {
+        toml::from_str(contents).map_err(|e| invalid(format!("'{}' is not valid TOML: {}", path, e)))
+    } else if path.ends_with(".json") {
+        serde_json::from_str(contents).map_err(|e| invalid(format!("'{}' is not valid JSON: {}", path, e)))
+    } else {
+        Err(invalid(format!("'{}' must be a .toml or .json file", path)))
+    }
+}
+
+fn invalid(problem: impl fmt::Display) -> DomainError {
+    DomainError::Internal(format!("Invalid configuration: {}", problem))
+}
+
+/// The settings given by environment variables, nested like the file
+fn env_overrides(env: &HashMap<String, String>) -> DomainResult<Value> {
+    let mut overrides = Value::Object(Map::new());
+    for (name, path, kind) in ENV_VARS {
+        let Some(raw) = env.get(*name) else { continue };
+        let bad = || invalid(format!("{} '{}' is not valid", name, raw));
+        let list = || raw.split(',').map(str::trim).filter(|v| !v.is_empty());
+
+        let value = match kind {
+            EnvValue::Text => Value::from(raw.as_str()),
+            EnvValue::Number => Value::from(raw.trim().parse::<u64>().map_err(|_| bad())?),
+            EnvValue::Bool => match raw.trim().to_ascii_lowercase().as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => return Err(bad()),
+            },
+            EnvValue::List => list().map(Value::from).collect(),
+            EnvValue::OptionalNumber if raw.trim().is_empty() => Value::Null,
+            EnvValue::OptionalNumber => Value::from(raw.trim().parse::<u64>().map_err(|_| bad())?),
+            EnvValue::Pairs => {
+                let mut pairs = Map::new();
+                for entry in list() {
+                    let (key, value) = entry.rsplit_once('=').ok_or_else(bad)?;
+                    pairs.insert(key.trim().to_string(), Value::from(value.trim()));
+                }
+                Value::Object(pairs)
+            }
+        };
+        let nested = path.rsplit('.').fold(value, |value, field| value.into_field(field));
+        merge(&mut overrides, nested);
+    }
+    for (name, value) in env {
+        if let Some(credential) = name.strip_prefix(CREDENTIAL_VAR_PREFIX).filter(|c| !c.is_empty()) {
+            let nested = Value::from(value.as_str()).into_field(&credential.to_ascii_lowercase()).into_field("credentials");
+            merge(&mut overrides, nested);
+        }
+    }
+    Ok(overrides)
+}
+
+trait IntoField {
+    /// `{ field: self }`
+    fn into_field(self, field: &str) -> Value;
+}
+
+impl IntoField for Value {
+    fn into_field(self, field: &str) -> Value {
+        Value::Object(Map::from_iter([(field.to_string(), self)]))
+    }
+}
+
+/// Deep-merge `overlay` into `base`: tables merge key by key, anything else replaces
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    const FILE: &str = r#"
+region = "eu-west-1"
+
+[database]
+table = "orders"
+sort_key_type = "S"
+
+[rate_limit]
+table = "rate-limits"
+default = "100/60"
+
+[rate_limit.routes]
+"POST /api-keys" = "5/60"
+
+[production]
+log_level = "warn"
+
+[production.database]
+table = "orders-prod"
+"#;
+
+    #[test]
+    fn test_defaults_without_file_or_env() {
+        let config = Config::resolve(&HashMap::new(), None).unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(config.log_filter().max_level_hint(), Some(LevelFilter::INFO));
+    }
+
+    #[test]
+    fn test_stage_table_and_env_override_the_file() {
+        let file = parse_file("app.toml", FILE).unwrap();
+
+        let dev = Config::resolve(&HashMap::new(), Some(file.clone())).unwrap();
+        assert_eq!((dev.stage, dev.region.as_str(), dev.log_level.as_str()), (Stage::Dev, "eu-west-1", "info"));
+        assert_eq!(dev.database.table, "orders");
+        assert_eq!(dev.database.sort_key_type, SortKeyType::String);
+        // Untouched fields of a section keep their defaults
+        assert_eq!(dev.database.partition_key, "order_id");
+        assert_eq!(dev.rate_limit.routes["POST /api-keys"], RateLimit::new(5, 60));
+
+        let production = Config::resolve(
+            &env(&[("APP_ENV", "production"), ("S3_BUCKET", "uploads"), ("CORS_MAX_AGE", "")]),
+            Some(file.clone()),
+        )
+        .unwrap();
+        assert_eq!(production.stage, Stage::Production);
+        assert_eq!(production.database.table, "orders-prod");
+        assert_eq!(production.database.sort_key_type, SortKeyType::String);
+        assert_eq!(production.log_filter().max_level_hint(), Some(LevelFilter::WARN));
+        assert_eq!(production.storage.bucket, "uploads");
+        assert_eq!(production.cors.max_age, None);
+
+        let overridden = Config::resolve(&env(&[("APP_ENV", "production"), ("DYNAMO_TABLE", "orders-env")]), Some(file)).unwrap();
+        assert_eq!(overridden.database.table, "orders-env");
+    }
+
+    #[test]
+    fn test_env_values_are_typed() {
+        let config = Config::resolve(
+            &env(&[
+                ("JWT_JWKS", "{\"keys\":[]}"),
+                ("JWT_ISSUER", "https://issuer.example.com/"),
+                ("JWT_AUDIENCE", "orders-api, admin-console,"),
+                ("CORS_ALLOWED_ORIGINS", "https://shop.example.com"),
+                ("CORS_ALLOW_CREDENTIALS", "TRUE"),
+                ("RATE_LIMIT_ROUTES", "POST /api-keys=5/60, GET /items/{pk}=500/60"),
+                ("IDEMPOTENCY_TABLE", "idempotency"),
+                ("IDEMPOTENCY_TTL_SECS", "3600"),
+                ("METRICS_NAMESPACE", "Orders"),
+                ("METRICS_DIMENSIONS", "Service=orders-api, Stage=prod"),
+                ("S3_MULTIPART_THRESHOLD_BYTES", "6291456"),
+            ]),
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.auth.audience, ["orders-api", "admin-console"]);
+        assert!(config.cors.allow_credentials);
+        assert_eq!(config.rate_limit.routes.len(), 2);
+        assert_eq!(config.rate_limit.routes["GET /items/{pk}"], RateLimit::new(500, 60));
+        assert_eq!((config.idempotency.ttl_secs, config.idempotency.lock_secs), (3600, 60));
+        assert_eq!(config.metrics.namespace.as_deref(), Some("Orders"));
+        assert_eq!(config.metrics.dimensions["Stage"], "prod");
+        assert_eq!(config.storage.multipart(), MultipartConfig { threshold: 6 * 1024 * 1024, part_size: 8 * 1024 * 1024 });
+    }
+
+    #[test]
+    fn test_invalid_settings_fail() {
+        let error = |vars: &[(&str, &str)], file: Option<Value>| match Config::resolve(&env(vars), file) {
+            Err(DomainError::Internal(message)) => message,
+            other => panic!("expected an error, got {:?}", other),
+        };
+
+        assert!(error(&[("MAX_UPLOAD_BYTES", "10MB")], None).contains("MAX_UPLOAD_BYTES '10MB'"));
+        assert!(error(&[("APP_ENV", "qa")], None).contains("stage 'qa'"));
+        assert!(error(&[("RATE_LIMIT_DEFAULT", "100")], None).contains("rate_limit.default"));
+        assert!(error(&[("DYNAMO_SORT_KEY_TYPE", "B")], None).contains("database.sort_key_type"));
+        assert!(error(&[("CORS_ALLOW_CREDENTIALS", "true")], None).contains("cors.allowed_origins"));
+        assert!(error(&[("S3_MULTIPART_THRESHOLD_BYTES", "0")], None).contains("storage.multipart_threshold_bytes"));
+        assert!(error(&[("S3_MULTIPART_THRESHOLD_BYTES", "16777216")], None).contains("must not exceed"));
+        assert!(error(&[("S3_MULTIPART_PART_SIZE_BYTES", "1048576")], None).contains("storage.multipart_part_size_bytes"));
+        // Unknown keys are typos, not ignored
+        let typo = parse_file("app.json", r#"{"databse": {"table": "orders"}}"#).unwrap();
+        assert!(error(&[], Some(typo)).contains("databse"));
+
+        let message = error(&[("JWT_JWKS_FILE", "/var/task/jwks.json"), ("RUST_LOG", "verbose")], None);
+        assert!(message.contains("log_level"));
+        assert!(message.contains("auth.issuer"));
+        assert!(message.contains("auth.audience"));
+        let filter = Config::resolve(&env(&[("RUST_LOG", "info,aws_config=warn,mk_test_lambda=debug")]), None).unwrap();
+        assert_eq!(filter.log_filter().max_level_hint(), Some(LevelFilter::DEBUG));
+        assert!(error(&[("RUST_LOG", "info,aws_config")], None).contains("log_level"));
+
+        assert!(error(&[("METRICS_DIMENSIONS", "Route=all")], None).contains("'Route'"));
+        assert!(error(&[("METRICS_DIMENSIONS", "Service")], None).contains("METRICS_DIMENSIONS"));
+
+        assert!(error(&[("SECRETS_PROVIDER", "file")], None).contains("secrets.file"));
+        assert!(error(&[("SECRETS_PROVIDER", "vault")], None).contains("secrets.provider"));
+        assert!(error(&[("SECRETS_PROVIDER", "ssm"), ("CREDENTIAL_X", "secret://#f")], None).contains("credentials.x"));
+
+        assert!(parse_file("app.yaml", "").is_err());
+        assert!(parse_file("app.toml", "region = ").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_secret_references_resolve() {
+        use crate::domain::mocks::MockSecrets;
+        use std::sync::Arc;
+
+        let vars = [
+            ("JWT_JWKS", "secret:///orders/jwks"),
+            ("JWT_ISSUER", "iss"),
+            ("JWT_AUDIENCE", "aud"),
+            ("CREDENTIAL_PAYMENTS_API_KEY", "secret://payments#api_key"),
+            ("CREDENTIAL_REGION_HINT", "eu"),
+        ];
+        let message = match Config::resolve(&env(&vars), None) {
+            Err(DomainError::Internal(message)) => message,
+            other => panic!("expected an error, got {:?}", other),
+        };
+        assert!(message.contains("auth.jwks references a secret"));
+        assert!(message.contains("credentials.payments_api_key references a secret"));
+
+        let mut vars = vars.to_vec();
+        vars.push(("SECRETS_PROVIDER", "secrets-manager"));
+        let mut config = Config::resolve(&env(&vars), None).unwrap();
+        assert_eq!(config.secrets.provider, Some(SecretsProvider::SecretsManager));
+
+        let port = MockSecrets::new()
+            .with_secret("/orders/jwks", "{\"keys\":[]}")
+            .with_secret("payments", r#"{"api_key": "sk_live_1"}"#);
+        config.resolve_secrets(&SecretsService::new(Arc::new(port))).await.unwrap();
+        assert_eq!(config.auth.jwks.unwrap().expose(), "{\"keys\":[]}");
+        assert_eq!(config.credentials["payments_api_key"].expose(), "sk_live_1");
+        assert_eq!(config.credentials["region_hint"].expose(), "eu");
+    }
+
+    #[test]
+    fn test_debug_output_redacts_secrets() {
+        let config = Config::resolve(
+            &env(&[("JWT_JWKS", "{\"keys\":[{\"d\":\"private\"}]}"), ("JWT_ISSUER", "iss"), ("JWT_AUDIENCE", "aud")]),
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.auth.jwks.as_ref().unwrap().expose(), "{\"keys\":[{\"d\":\"private\"}]}");
+
+        let logged = format!("{:?}", config);
+        assert!(logged.contains("jwks: Some([REDACTED])"));
+        assert!(!logged.contains("private"));
+    }
+}
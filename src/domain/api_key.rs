@@ -0,0 +1,183 @@
+use super::auth::Principal;
+use super::errors::{DomainError, DomainResult};
+use super::item::{Item, ItemValue};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Prefix that makes API keys recognizable, e.g. in secret scanners
+pub const KEY_PREFIX: &str = "ak_";
+/// Partition key attribute of the API key table
+pub const KEY_ID_ATTRIBUTE: &str = "key_id";
+/// Key ids are this many lowercase hex characters
+pub const KEY_ID_LEN: usize = 16;
+
+/// An API key's metadata as stored in the database.
+///
+/// Only a SHA-256 hash of the key's secret is stored; the key itself is
+/// shown once, when it is issued.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApiKey {
+    pub key_id: String,
+    /// Who the key was issued to; becomes the principal's subject
+    pub owner: String,
+    pub scopes: Vec<String>,
+    pub disabled: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub secret_hash: String,
+}
+
+impl ApiKey {
+    /// Whether the key may be used at `now`
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        !self.disabled && self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+
+    /// Compare a presented secret with the stored hash, in constant time
+    pub fn verify_secret(&self, secret: &str) -> bool {
+        let presented = hash_secret(secret);
+        presented.len() == self.secret_hash.len()
+            && presented
+                .bytes()
+                .zip(self.secret_hash.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+
+    pub fn principal(&self) -> Principal {
+        let mut claims = serde_json::Map::new();
+        claims.insert("api_key_id".to_string(), self.key_id.clone().into());
+        Principal {
+            subject: self.owner.clone(),
+            scopes: self.scopes.clone(),
+            claims,
+        }
+    }
+
+    pub fn key(key_id: &str) -> Item {
+        Item::from([(KEY_ID_ATTRIBUTE.to_string(), ItemValue::from(key_id))])
+    }
+
+    pub fn to_item(&self) -> Item {
+        let mut item = Self::key(&self.key_id);
+        item.insert("owner".to_string(), self.owner.clone().into());
+        item.insert(
+            "scopes".to_string(),
+            // A list rather than a string set, which cannot be empty
+            ItemValue::List(self.scopes.iter().map(|s| s.clone().into()).collect()),
+        );
+        item.insert("disabled".to_string(), self.disabled.into());
+        item.insert("created_at".to_string(), self.created_at.timestamp().into());
+        if let Some(expires_at) = self.expires_at {
+            item.insert("expires_at".to_string(), expires_at.timestamp().into());
+        }
+        item.insert("secret_hash".to_string(), self.secret_hash.clone().into());
+        item
+    }
+
+    pub fn from_item(item: &Item) -> DomainResult<Self> {
+        let string = |name: &str| {
+            item.get(name)
+                .and_then(ItemValue::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| DomainError::Internal(format!("API key item has no '{}' string", name)))
+        };
+        let timestamp = |name: &str| {
+            item.get(name)
+                .and_then(ItemValue::as_number::<i64>)
+                .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        };
+        let scopes = match item.get("scopes") {
+            Some(ItemValue::List(values)) => values.iter().filter_map(ItemValue::as_str).map(str::to_string).collect(),
+            _ => Vec::new(),
+        };
+
+        Ok(Self {
+            key_id: string(KEY_ID_ATTRIBUTE)?,
+            owner: string("owner")?,
+            scopes,
+            disabled: item.get("disabled").and_then(ItemValue::as_bool).unwrap_or(false),
+            expires_at: timestamp("expires_at"),
+            created_at: timestamp("created_at").unwrap_or_default(),
+            secret_hash: string("secret_hash")?,
+        })
+    }
+}
+
+/// A newly issued key: the only time the full key is available
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IssuedApiKey {
+    /// `ak_<key_id>.<secret>`, to be sent in the `x-api-key` header
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+/// Split a presented `ak_<key_id>.<secret>` key into its id and secret.
+/// Ids that could not have been issued are rejected before any lookup.
+pub fn parse_key(key: &str) -> Option<(&str, &str)> {
+    let (key_id, secret) = key.strip_prefix(KEY_PREFIX)?.split_once('.')?;
+    let valid_id = key_id.len() == KEY_ID_LEN && key_id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    (valid_id && !secret.is_empty()).then_some((key_id, secret))
+}
+
+/// Assemble the key handed to the client
+pub fn format_key(key_id: &str, secret: &str) -> String {
+    format!("{}{}.{}", KEY_PREFIX, key_id, secret)
+}
+
+/// Hex SHA-256 of a key secret. Secrets are random, so no salt or stretching is needed.
+pub fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_key() -> ApiKey {
+        ApiKey {
+            key_id: "0f3a9c".to_string(),
+            owner: "billing-service".to_string(),
+            scopes: vec!["items:read".to_string()],
+            disabled: false,
+            expires_at: DateTime::from_timestamp(2_000_000_000, 0),
+            created_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            secret_hash: hash_secret("s3cret"),
+        }
+    }
+
+    #[test]
+    fn test_item_round_trip() {
+        let key = api_key();
+        assert_eq!(ApiKey::from_item(&key.to_item()).unwrap(), key);
+
+        let no_scopes = ApiKey { scopes: Vec::new(), expires_at: None, ..key };
+        assert_eq!(ApiKey::from_item(&no_scopes.to_item()).unwrap(), no_scopes);
+    }
+
+    #[test]
+    fn test_secret_and_activity_checks() {
+        let key = api_key();
+        assert!(key.verify_secret("s3cret"));
+        assert!(!key.verify_secret("s3cret2"));
+
+        assert!(key.is_active(DateTime::from_timestamp(1_999_999_999, 0).unwrap()));
+        assert!(!key.is_active(DateTime::from_timestamp(2_000_000_000, 0).unwrap()));
+        assert!(!ApiKey { disabled: true, ..key }.is_active(DateTime::from_timestamp(0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key(&format_key("0f3a9c0d1e2b3a4c", "abc")), Some(("0f3a9c0d1e2b3a4c", "abc")));
+        assert_eq!(parse_key("ak_0f3a9c0d1e2b3a4c"), None);
+        assert_eq!(parse_key("ak_0f3a9c0d1e2b3a4c."), None);
+        assert_eq!(parse_key("ak_.abc"), None);
+        assert_eq!(parse_key("ak_0f3a9c.abc"), None);
+        assert_eq!(parse_key("ak_0F3A9C0D1E2B3A4C.abc"), None);
+        assert_eq!(parse_key("ak_0f3a9c0d1e2b3a4cff.abc"), None);
+        assert_eq!(parse_key("sk_0f3a9c0d1e2b3a4c.abc"), None);
+    }
+}
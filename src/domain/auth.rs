@@ -0,0 +1,86 @@
+use super::errors::{DomainError, DomainResult};
+use serde_json::{Map, Value};
+
+/// An authenticated caller, carried to `RequestProcessor` in the `RequestContext`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Principal {
+    /// Who the caller is, e.g. the token's `sub` claim
+    pub subject: String,
+    /// Operations the caller was granted, e.g. `items:write`
+    pub scopes: Vec<String>,
+    /// Every verified claim, for checks beyond scopes
+    pub claims: Map<String, Value>,
+}
+
+impl Principal {
+    /// Principal from a verified JWT claim set.
+    ///
+    /// Scopes come from the space-separated OAuth `scope` claim, or from a
+    /// `scp` array as issued by some providers.
+    pub fn from_claims(claims: Map<String, Value>) -> DomainResult<Self> {
+        let subject = claims
+            .get("sub")
+            .and_then(Value::as_str)
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| DomainError::Unauthorized("Token has no subject".to_string()))?
+            .to_string();
+        let scopes = match (claims.get("scope"), claims.get("scp")) {
+            (Some(Value::String(scope)), _) => scope.split_whitespace().map(str::to_string).collect(),
+            (_, Some(Value::Array(scp))) => scp.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+            _ => Vec::new(),
+        };
+        Ok(Self { subject, scopes, claims })
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+/// Check that the caller may perform an operation guarded by `scope`.
+///
+/// `None` means authentication is disabled, in which case everything is allowed.
+pub fn authorize(principal: Option<&Principal>, scope: &str) -> DomainResult<()> {
+    match principal {
+        Some(principal) if !principal.has_scope(scope) => Err(DomainError::Forbidden(format!(
+            "Scope '{}' is required for this operation",
+            scope
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn claims(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_scopes_from_claims() {
+        let principal = Principal::from_claims(claims(json!({"sub": "user-1", "scope": "items:read items:write"}))).unwrap();
+        assert_eq!(principal.subject, "user-1");
+        assert!(principal.has_scope("items:write"));
+
+        let principal = Principal::from_claims(claims(json!({"sub": "user-1", "scp": ["objects:read"]}))).unwrap();
+        assert_eq!(principal.scopes, vec!["objects:read".to_string()]);
+
+        let missing = Principal::from_claims(claims(json!({"scope": "items:read"})));
+        assert!(matches!(missing, Err(DomainError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_authorize() {
+        let principal = Principal {
+            subject: "user-1".to_string(),
+            scopes: vec!["items:read".to_string()],
+            ..Principal::default()
+        };
+        assert!(authorize(Some(&principal), "items:read").is_ok());
+        assert!(matches!(authorize(Some(&principal), "items:write"), Err(DomainError::Forbidden(_))));
+        assert!(authorize(None, "items:write").is_ok());
+    }
+}
@@ -0,0 +1,87 @@
+use super::condition::Condition;
+use super::item::Item;
+use super::update::UpdateRequest;
+
+/// Most keys DynamoDB reads in one `BatchGetItem` call
+pub const BATCH_GET_LIMIT: usize = 100;
+/// Most requests DynamoDB accepts in one `BatchWriteItem` call
+pub const BATCH_WRITE_LIMIT: usize = 25;
+/// Most items DynamoDB accepts in one `TransactWriteItems` call
+pub const TRANSACT_WRITE_LIMIT: usize = 100;
+
+/// One unconditional write of a batch
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteRequest {
+    Put(Item),
+    /// Delete the item with this key
+    Delete(Item),
+}
+
+/// One write of an all-or-nothing transaction; items may target different tables
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransactItem {
+    Put {
+        table_name: String,
+        item: Item,
+        conditions: Vec<Condition>,
+    },
+    Update {
+        table_name: String,
+        request: UpdateRequest,
+    },
+    Delete {
+        table_name: String,
+        key: Item,
+        conditions: Vec<Condition>,
+    },
+    /// Check conditions on an item without writing it
+    ConditionCheck {
+        table_name: String,
+        key: Item,
+        conditions: Vec<Condition>,
+    },
+}
+
+impl TransactItem {
+    pub fn put(table_name: &str, item: Item) -> Self {
+        TransactItem::Put {
+            table_name: table_name.to_string(),
+            item,
+            conditions: Vec::new(),
+        }
+    }
+
+    pub fn update(table_name: &str, request: UpdateRequest) -> Self {
+        TransactItem::Update {
+            table_name: table_name.to_string(),
+            request,
+        }
+    }
+
+    pub fn delete(table_name: &str, key: Item) -> Self {
+        TransactItem::Delete {
+            table_name: table_name.to_string(),
+            key,
+            conditions: Vec::new(),
+        }
+    }
+
+    pub fn condition_check(table_name: &str, key: Item, condition: Condition) -> Self {
+        TransactItem::ConditionCheck {
+            table_name: table_name.to_string(),
+            key,
+            conditions: vec![condition],
+        }
+    }
+
+    /// Add a condition the stored item must satisfy
+    pub fn condition(mut self, condition: Condition) -> Self {
+        match &mut self {
+            TransactItem::Put { conditions, .. }
+            | TransactItem::Delete { conditions, .. }
+            | TransactItem::ConditionCheck { conditions, .. } => conditions.push(condition),
+            TransactItem::Update { request, .. } => request.conditions.push(condition),
+        }
+        self
+    }
+}
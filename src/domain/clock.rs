@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+
+/// Source of the current time, so expiry and TTL logic can be tested without waiting
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -0,0 +1,151 @@
+use super::errors::{DomainError, DomainResult};
+use super::item::{Item, ItemValue};
+use super::query::{Filter, FilterOp};
+use std::collections::HashMap;
+
+/// Condition the stored item must satisfy for a write to go through
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// Every filter must match the stored item (AND-combined); a missing item has no attributes
+    Match(Vec<Filter>),
+    /// Raw DynamoDB condition expression with its own `#name`/`:value` placeholders.
+    ///
+    /// Placeholders must not look like the generated ones (`#n0`, `:v0`).
+    Expression {
+        expression: String,
+        names: HashMap<String, String>,
+        values: Item,
+    },
+}
+
+impl Condition {
+    /// Only write when the stored item lacks `attribute`, e.g. the partition key for create-only puts
+    pub fn attribute_not_exists(attribute: &str) -> Self {
+        Condition::Match(vec![Filter::new(attribute, FilterOp::NotExists)])
+    }
+
+    pub fn attribute_exists(attribute: &str) -> Self {
+        Condition::Match(vec![Filter::new(attribute, FilterOp::Exists)])
+    }
+
+    pub fn attribute_equals(attribute: &str, value: impl Into<ItemValue>) -> Self {
+        Condition::Match(vec![Filter::new(attribute, FilterOp::Eq(value.into()))])
+    }
+
+    /// Only write when the stored `attribute` holds version `version`
+    pub fn version_equals(attribute: &str, version: u64) -> Self {
+        Condition::attribute_equals(attribute, ItemValue::number(version))
+    }
+
+    pub fn expression(expression: &str) -> Self {
+        Condition::Expression {
+            expression: expression.to_string(),
+            names: HashMap::new(),
+            values: Item::new(),
+        }
+    }
+
+    /// Bind a `#name` placeholder of a raw expression; ignored for `Match` conditions
+    pub fn name(mut self, placeholder: &str, attribute: &str) -> Self {
+        if let Condition::Expression { names, .. } = &mut self {
+            names.insert(placeholder.to_string(), attribute.to_string());
+        }
+        self
+    }
+
+    /// Bind a `:value` placeholder of a raw expression; ignored for `Match` conditions
+    pub fn value(mut self, placeholder: &str, value: impl Into<ItemValue>) -> Self {
+        if let Condition::Expression { values, .. } = &mut self {
+            values.insert(placeholder.to_string(), value.into());
+        }
+        self
+    }
+}
+
+/// Options for a conditional put
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PutOptions {
+    pub condition: Option<Condition>,
+    /// Numeric attribute used for optimistic locking.
+    ///
+    /// The version in the item being written is the one the caller last read
+    /// (absent for a new item); the put only succeeds if the stored item still
+    /// holds it, and the item is written with the version incremented.
+    pub version_attribute: Option<String>,
+}
+
+impl PutOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn condition(mut self, condition: Condition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    pub fn versioned(mut self, attribute: &str) -> Self {
+        self.version_attribute = Some(attribute.to_string());
+        self
+    }
+
+    /// Resolve the options into the conditions to check and the item to write
+    pub fn prepare(&self, mut item: Item) -> DomainResult<(Item, Vec<Condition>)> {
+        let mut conditions: Vec<Condition> = self.condition.iter().cloned().collect();
+
+        if let Some(attribute) = &self.version_attribute {
+            let next = match item.get(attribute) {
+                None => {
+                    conditions.push(Condition::attribute_not_exists(attribute));
+                    1
+                }
+                Some(value) => {
+                    let current = value.as_number::<u64>().ok_or_else(|| {
+                        DomainError::Validation(format!(
+                            "Version attribute '{}' must be a non-negative integer",
+                            attribute
+                        ))
+                    })?;
+                    conditions.push(Condition::version_equals(attribute, current));
+                    current + 1
+                }
+            };
+            item.insert(attribute.clone(), ItemValue::number(next));
+        }
+
+        Ok((item, conditions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versioned_new_item() {
+        let (item, conditions) = PutOptions::new().versioned("version").prepare(Item::new()).unwrap();
+        assert_eq!(item["version"], ItemValue::number(1));
+        assert_eq!(conditions, vec![Condition::attribute_not_exists("version")]);
+    }
+
+    #[test]
+    fn test_versioned_existing_item() {
+        let item = Item::from([("version".to_string(), ItemValue::number(4))]);
+        let options = PutOptions::new()
+            .condition(Condition::attribute_exists("order_id"))
+            .versioned("version");
+        let (item, conditions) = options.prepare(item).unwrap();
+        assert_eq!(item["version"], ItemValue::number(5));
+        assert_eq!(
+            conditions,
+            vec![Condition::attribute_exists("order_id"), Condition::version_equals("version", 4)]
+        );
+    }
+
+    #[test]
+    fn test_versioned_rejects_non_numeric_version() {
+        let item = Item::from([("version".to_string(), ItemValue::from("v1"))]);
+        let result = PutOptions::new().versioned("version").prepare(item);
+        assert!(matches!(result, Err(DomainError::Validation(_))));
+    }
+}
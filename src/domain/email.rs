@@ -0,0 +1,283 @@
+use super::errors::{DomainError, DomainResult};
+use super::validation::is_content_type;
+
+/// Most recipients (To, Cc and Bcc combined) SES accepts per message
+pub const MAX_RECIPIENTS: usize = 50;
+/// Largest message SES accepts, after MIME encoding
+pub const MAX_MESSAGE_BYTES: usize = 10 * 1024 * 1024;
+
+/// A file attached to a message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    pub fn new(filename: &str, content_type: &str, data: Vec<u8>) -> Self {
+        Self {
+            filename: filename.to_string(),
+            content_type: content_type.to_string(),
+            data,
+        }
+    }
+}
+
+/// Sender and recipients shared by every kind of message
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Envelope {
+    pub from: String,
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+    pub reply_to: Vec<String>,
+    /// SES configuration set used for event publishing (bounces, opens, ...)
+    pub configuration_set: Option<String>,
+}
+
+impl Envelope {
+    /// Every recipient, in To, Cc, Bcc order
+    pub fn recipients(&self) -> impl Iterator<Item = &String> {
+        self.to.iter().chain(&self.cc).chain(&self.bcc)
+    }
+
+    fn validate(&self) -> DomainResult<()> {
+        let count = self.recipients().count();
+        if count == 0 {
+            return Err(DomainError::Validation("Email needs at least one recipient".to_string()));
+        }
+        if count > MAX_RECIPIENTS {
+            return Err(DomainError::Validation(format!(
+                "Email has {} recipients, at most {} are allowed",
+                count, MAX_RECIPIENTS
+            )));
+        }
+        for address in std::iter::once(&self.from).chain(self.recipients()).chain(&self.reply_to) {
+            if !is_valid_address(address) {
+                return Err(DomainError::Validation(format!("Invalid email address '{}'", address)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A message with a text and/or HTML body and optional attachments
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EmailMessage {
+    pub envelope: Envelope,
+    pub subject: String,
+    pub text: Option<String>,
+    pub html: Option<String>,
+    pub attachments: Vec<Attachment>,
+}
+
+impl EmailMessage {
+    pub fn new(from: &str, subject: &str) -> Self {
+        Self {
+            envelope: Envelope {
+                from: from.to_string(),
+                ..Envelope::default()
+            },
+            subject: subject.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn to(mut self, address: &str) -> Self {
+        self.envelope.to.push(address.to_string());
+        self
+    }
+
+    pub fn cc(mut self, address: &str) -> Self {
+        self.envelope.cc.push(address.to_string());
+        self
+    }
+
+    pub fn bcc(mut self, address: &str) -> Self {
+        self.envelope.bcc.push(address.to_string());
+        self
+    }
+
+    pub fn reply_to(mut self, address: &str) -> Self {
+        self.envelope.reply_to.push(address.to_string());
+        self
+    }
+
+    pub fn configuration_set(mut self, name: &str) -> Self {
+        self.envelope.configuration_set = Some(name.to_string());
+        self
+    }
+
+    pub fn text(mut self, body: &str) -> Self {
+        self.text = Some(body.to_string());
+        self
+    }
+
+    pub fn html(mut self, body: &str) -> Self {
+        self.html = Some(body.to_string());
+        self
+    }
+
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// Reject messages SES would refuse, and header values that could inject headers
+    pub fn validate(&self) -> DomainResult<()> {
+        self.envelope.validate()?;
+        if self.text.is_none() && self.html.is_none() {
+            return Err(DomainError::Validation("Email needs a text or HTML body".to_string()));
+        }
+        if has_line_break(&self.subject) {
+            return Err(DomainError::Validation("Email subject must be a single line".to_string()));
+        }
+        for attachment in &self.attachments {
+            if attachment.filename.is_empty() || has_line_break(&attachment.filename) {
+                return Err(DomainError::Validation(format!(
+                    "Invalid attachment filename '{}'",
+                    attachment.filename.escape_debug()
+                )));
+            }
+            if !is_content_type(&attachment.content_type) {
+                return Err(DomainError::Validation(format!(
+                    "Invalid attachment content type '{}'",
+                    attachment.content_type.escape_debug()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A message rendered by SES from a stored template
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplatedEmail {
+    pub envelope: Envelope,
+    pub template: String,
+    /// Values substituted into the template's `{{placeholders}}`
+    pub template_data: serde_json::Value,
+}
+
+impl TemplatedEmail {
+    pub fn new(from: &str, template: &str, template_data: serde_json::Value) -> Self {
+        Self {
+            envelope: Envelope {
+                from: from.to_string(),
+                ..Envelope::default()
+            },
+            template: template.to_string(),
+            template_data,
+        }
+    }
+
+    pub fn to(mut self, address: &str) -> Self {
+        self.envelope.to.push(address.to_string());
+        self
+    }
+
+    pub fn cc(mut self, address: &str) -> Self {
+        self.envelope.cc.push(address.to_string());
+        self
+    }
+
+    pub fn bcc(mut self, address: &str) -> Self {
+        self.envelope.bcc.push(address.to_string());
+        self
+    }
+
+    pub fn reply_to(mut self, address: &str) -> Self {
+        self.envelope.reply_to.push(address.to_string());
+        self
+    }
+
+    pub fn configuration_set(mut self, name: &str) -> Self {
+        self.envelope.configuration_set = Some(name.to_string());
+        self
+    }
+
+    pub fn validate(&self) -> DomainResult<()> {
+        self.envelope.validate()?;
+        if self.template.is_empty() {
+            return Err(DomainError::Validation("Email template name must not be empty".to_string()));
+        }
+        if !self.template_data.is_object() {
+            return Err(DomainError::Validation("Email template data must be a JSON object".to_string()));
+        }
+        Ok(())
+    }
+}
+
+fn has_line_break(value: &str) -> bool {
+    value.contains(['\r', '\n'])
+}
+
+/// Loose `local@domain` check, optionally with a display name: `Shop <orders@example.com>`
+fn is_valid_address(address: &str) -> bool {
+    if has_line_break(address) {
+        return false;
+    }
+    let addr_spec = match (address.rfind('<'), address.strip_suffix('>')) {
+        (Some(start), Some(inner)) => &inner[start + 1..],
+        (None, None) => address,
+        _ => return false,
+    };
+    match addr_spec.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !addr_spec.contains(|c: char| c.is_whitespace() || c == '<' || c == '>')
+                && !domain.contains('@')
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_validation() {
+        assert!(is_valid_address("orders@example.com"));
+        assert!(is_valid_address("Shop <orders@example.com>"));
+        assert!(!is_valid_address("orders@localhost"));
+        assert!(!is_valid_address("orders.example.com"));
+        assert!(!is_valid_address("a@b.com\r\nBcc: victim@example.com"));
+        assert!(!is_valid_address("Shop <orders@example.com"));
+    }
+
+    #[test]
+    fn test_message_validation() {
+        let message = EmailMessage::new("shop@example.com", "Your order").to("buyer@example.com");
+        assert!(matches!(message.validate(), Err(DomainError::Validation(_))));
+
+        let message = message.text("Thanks!");
+        assert!(message.validate().is_ok());
+
+        let injected = EmailMessage { subject: "Hi\r\nBcc: x@example.com".into(), ..message.clone() };
+        assert!(matches!(injected.validate(), Err(DomainError::Validation(_))));
+
+        let bad_type = message.clone().attachment(Attachment::new("a.pdf", "pdf", vec![1]));
+        assert!(matches!(bad_type.validate(), Err(DomainError::Validation(_))));
+
+        let mut crowded = message;
+        for i in 0..MAX_RECIPIENTS {
+            crowded = crowded.bcc(&format!("buyer{}@example.com", i));
+        }
+        assert!(matches!(crowded.validate(), Err(DomainError::Validation(_))));
+    }
+
+    #[test]
+    fn test_templated_email_validation() {
+        let email = TemplatedEmail::new("shop@example.com", "OrderConfirmation", serde_json::json!({"order_id": "1111"}))
+            .to("buyer@example.com");
+        assert!(email.validate().is_ok());
+
+        let no_data = TemplatedEmail { template_data: serde_json::json!([1, 2]), ..email };
+        assert!(matches!(no_data.validate(), Err(DomainError::Validation(_))));
+    }
+}
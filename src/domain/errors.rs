@@ -0,0 +1,105 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Why one item of a canceled transaction failed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CancellationReason {
+    /// Position of the item in the transaction
+    pub index: usize,
+    /// Backend reason code, e.g. `ConditionalCheckFailed` or `TransactionConflict`
+    pub code: String,
+    pub message: Option<String>,
+}
+
+/// One rule a request field broke
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    /// Path of the field in the request body, e.g. `data.content_length`; empty for the body itself
+    pub field: String,
+    /// Rule that failed, e.g. `required`, `type`, `length`, `range` or `format`
+    pub rule: String,
+    pub message: String,
+}
+
+/// Errors surfaced by ports and application services.
+///
+/// Adapters classify their SDK errors into these variants so the HTTP layer
+/// can pick a status code without knowing which backend failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DomainError {
+    /// The requested resource does not exist
+    NotFound(String),
+    /// The request conflicts with the current state of the resource
+    Conflict(String),
+    /// A conditional write was rejected because its condition did not hold
+    ConditionFailed(String),
+    /// A transaction was canceled; only the items that caused it are listed
+    TransactionCanceled {
+        message: String,
+        reasons: Vec<CancellationReason>,
+    },
+    /// The request is malformed or violates a constraint
+    Validation(String),
+    /// The request body broke field rules; every violation is listed
+    InvalidInput {
+        message: String,
+        errors: Vec<FieldError>,
+    },
+    /// A downstream service or limiter rejected the call for capacity reasons
+    Throttled(String),
+    /// The caller is not authenticated
+    Unauthorized(String),
+    /// The caller is authenticated but may not perform this operation
+    Forbidden(String),
+    /// A downstream service failed or could not be reached
+    Upstream(String),
+    /// Unexpected failure inside this service
+    Internal(String),
+}
+
+impl DomainError {
+    /// Stable machine-readable code returned to clients
+    pub fn code(&self) -> &'static str {
+        match self {
+            DomainError::NotFound(_) => "NOT_FOUND",
+            DomainError::Conflict(_) => "CONFLICT",
+            DomainError::ConditionFailed(_) => "CONDITION_FAILED",
+            DomainError::TransactionCanceled { .. } => "TRANSACTION_CANCELED",
+            DomainError::Validation(_) => "VALIDATION_ERROR",
+            DomainError::InvalidInput { .. } => "INVALID_INPUT",
+            DomainError::Throttled(_) => "THROTTLED",
+            DomainError::Unauthorized(_) => "UNAUTHORIZED",
+            DomainError::Forbidden(_) => "FORBIDDEN",
+            DomainError::Upstream(_) => "UPSTREAM_ERROR",
+            DomainError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    /// Human-readable detail
+    pub fn message(&self) -> &str {
+        match self {
+            DomainError::NotFound(m)
+            | DomainError::Conflict(m)
+            | DomainError::ConditionFailed(m)
+            | DomainError::Validation(m)
+            | DomainError::Throttled(m)
+            | DomainError::Unauthorized(m)
+            | DomainError::Forbidden(m)
+            | DomainError::Upstream(m)
+            | DomainError::Internal(m)
+            | DomainError::TransactionCanceled { message: m, .. }
+            | DomainError::InvalidInput { message: m, .. } => m,
+        }
+    }
+}
+
+impl fmt::Display for DomainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
+
+impl std::error::Error for DomainError {}
+
+/// Result alias used by ports
+pub type DomainResult<T> = Result<T, DomainError>;
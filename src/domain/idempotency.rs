@@ -0,0 +1,189 @@
+use super::errors::{DomainError, DomainResult};
+use super::item::{Item, ItemValue};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+
+/// Partition key attribute of the idempotency table
+pub const IDEMPOTENCY_KEY_ATTRIBUTE: &str = "idempotency_key";
+/// Longest `Idempotency-Key` header accepted
+pub const MAX_KEY_LENGTH: usize = 255;
+
+/// Where the first request carrying a key has got to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdempotencyStatus {
+    InProgress,
+    Completed,
+}
+
+impl IdempotencyStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdempotencyStatus::InProgress => "IN_PROGRESS",
+            IdempotencyStatus::Completed => "COMPLETED",
+        }
+    }
+}
+
+/// A response as stored for replay
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub body: Vec<u8>,
+    /// Whether the body was text, so it is replayed as the same `Body` variant
+    pub is_text: bool,
+}
+
+/// The stored state of one idempotency key
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdempotencyRecord {
+    pub key: String,
+    /// `request_hash` of the first request sent with the key
+    pub request_hash: String,
+    pub status: IdempotencyStatus,
+    /// Set once the status is `Completed`
+    pub response: Option<StoredResponse>,
+    /// Epoch seconds after which an `InProgress` record is considered abandoned
+    pub locked_until: i64,
+    /// Epoch seconds after which the record is ignored, and deleted by DynamoDB TTL
+    pub expires_at: i64,
+}
+
+impl IdempotencyRecord {
+    pub fn key_item(key: &str) -> Item {
+        Item::from([(IDEMPOTENCY_KEY_ATTRIBUTE.to_string(), ItemValue::from(key))])
+    }
+
+    pub fn to_item(&self) -> Item {
+        let mut item = Self::key_item(&self.key);
+        item.insert("request_hash".to_string(), self.request_hash.clone().into());
+        item.insert("status".to_string(), self.status.as_str().into());
+        item.insert("locked_until".to_string(), self.locked_until.into());
+        item.insert("expires_at".to_string(), self.expires_at.into());
+        if let Some(response) = &self.response {
+            item.insert("response_status".to_string(), ItemValue::number(response.status));
+            item.insert(
+                "response_headers".to_string(),
+                ItemValue::Map(
+                    response
+                        .headers
+                        .iter()
+                        .map(|(name, value)| (name.clone(), value.clone().into()))
+                        .collect::<HashMap<_, _>>(),
+                ),
+            );
+            item.insert("response_body".to_string(), ItemValue::Binary(response.body.clone()));
+            item.insert("response_is_text".to_string(), response.is_text.into());
+        }
+        item
+    }
+
+    pub fn from_item(item: &Item) -> DomainResult<Self> {
+        let invalid = |name: &str| DomainError::Internal(format!("Idempotency record has no valid '{}'", name));
+        let string = |name: &str| item.get(name).and_then(ItemValue::as_str).map(str::to_string).ok_or_else(|| invalid(name));
+        let number = |name: &str| item.get(name).and_then(ItemValue::as_number::<i64>).ok_or_else(|| invalid(name));
+
+        let status = match string("status")?.as_str() {
+            "IN_PROGRESS" => IdempotencyStatus::InProgress,
+            "COMPLETED" => IdempotencyStatus::Completed,
+            _ => return Err(invalid("status")),
+        };
+        let response = match status {
+            IdempotencyStatus::InProgress => None,
+            IdempotencyStatus::Completed => Some(StoredResponse {
+                status: number("response_status")? as u16,
+                headers: match item.get("response_headers") {
+                    Some(ItemValue::Map(headers)) => headers
+                        .iter()
+                        .filter_map(|(name, value)| value.as_str().map(|v| (name.clone(), v.to_string())))
+                        .collect(),
+                    _ => BTreeMap::new(),
+                },
+                body: match item.get("response_body") {
+                    Some(ItemValue::Binary(body)) => body.clone(),
+                    _ => Vec::new(),
+                },
+                is_text: item.get("response_is_text").and_then(ItemValue::as_bool).unwrap_or(false),
+            }),
+        };
+
+        Ok(Self {
+            key: string(IDEMPOTENCY_KEY_ATTRIBUTE)?,
+            request_hash: string("request_hash")?,
+            status,
+            response,
+            locked_until: number("locked_until")?,
+            expires_at: number("expires_at")?,
+        })
+    }
+}
+
+/// Fingerprint of a request, compared when a key is reused: hex SHA-256 of
+/// the method, path, query string and body
+pub fn request_hash(method: &str, path: &str, query: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    for part in [method.as_bytes(), path.as_bytes(), query.as_bytes()] {
+        hasher.update(part);
+        // Separator, so `/a` + `b` and `/ab` + `` hash differently
+        hasher.update([0]);
+    }
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+/// Check an `Idempotency-Key` header value: 1 to 255 visible ASCII characters
+pub fn validate_key(key: &str) -> DomainResult<()> {
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH || !key.bytes().all(|b| b.is_ascii_graphic()) {
+        return Err(DomainError::Validation(format!(
+            "Idempotency-Key must be 1 to {} visible ASCII characters",
+            MAX_KEY_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_round_trip() {
+        let mut record = IdempotencyRecord {
+            key: "ip:203.0.113.7#order-42".to_string(),
+            request_hash: request_hash("POST", "/", "", b"{}"),
+            status: IdempotencyStatus::InProgress,
+            response: None,
+            locked_until: 1_700_000_060,
+            expires_at: 1_700_086_400,
+        };
+        assert_eq!(IdempotencyRecord::from_item(&record.to_item()).unwrap(), record);
+
+        record.status = IdempotencyStatus::Completed;
+        record.response = Some(StoredResponse {
+            status: 201,
+            headers: BTreeMap::from([("content-type".to_string(), "application/json".to_string())]),
+            body: b"{\"status\":\"success\"}".to_vec(),
+            is_text: true,
+        });
+        assert_eq!(IdempotencyRecord::from_item(&record.to_item()).unwrap(), record);
+    }
+
+    #[test]
+    fn test_request_hash_covers_method_path_query_and_body() {
+        let hash = request_hash("POST", "/upload-urls/a.csv", "", b"{}");
+        assert_eq!(hash, request_hash("POST", "/upload-urls/a.csv", "", b"{}"));
+        assert_ne!(hash, request_hash("POST", "/upload-urls/b.csv", "", b"{}"));
+        assert_ne!(hash, request_hash("POST", "/upload-urls/a.csv", "expires_in=60", b"{}"));
+        assert_ne!(hash, request_hash("POST", "/upload-urls/a.csv", "", b"{ }"));
+        assert_ne!(request_hash("POST", "/a", "", b"b"), request_hash("POST", "/ab", "", b""));
+        assert_ne!(request_hash("POST", "/a", "b", b""), request_hash("POST", "/a", "", b"b"));
+    }
+
+    #[test]
+    fn test_validate_key() {
+        assert!(validate_key("8e03978e-40d5-43e8-bc93-6894a57f9324").is_ok());
+        assert!(validate_key("").is_err());
+        assert!(validate_key("has space").is_err());
+        assert!(validate_key(&"k".repeat(MAX_KEY_LENGTH + 1)).is_err());
+    }
+}
@@ -0,0 +1,260 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A database item: attribute name to value
+pub type Item = HashMap<String, ItemValue>;
+
+/// An attribute value covering every DynamoDB attribute type.
+///
+/// Numbers are kept as their decimal string so no precision is lost, and
+/// sets stay distinct from lists. The serde representation is DynamoDB JSON
+/// (`{"S": "text"}`, `{"N": "1.50"}`, `{"SS": ["a", "b"]}`, ...) with binary
+/// data base64-encoded, so items survive a round trip through JSON unchanged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Repr", into = "Repr")]
+pub enum ItemValue {
+    String(String),
+    Number(String),
+    Binary(Vec<u8>),
+    Bool(bool),
+    Null,
+    Map(HashMap<String, ItemValue>),
+    List(Vec<ItemValue>),
+    StringSet(Vec<String>),
+    NumberSet(Vec<String>),
+    BinarySet(Vec<Vec<u8>>),
+}
+
+impl ItemValue {
+    /// Build a number value from anything that formats as a decimal number
+    pub fn number(n: impl ToString) -> Self {
+        ItemValue::Number(n.to_string())
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ItemValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Parse a number value into the requested numeric type
+    pub fn as_number<T: std::str::FromStr>(&self) -> Option<T> {
+        match self {
+            ItemValue::Number(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ItemValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for ItemValue {
+    fn from(value: &str) -> Self {
+        ItemValue::String(value.to_string())
+    }
+}
+
+impl From<String> for ItemValue {
+    fn from(value: String) -> Self {
+        ItemValue::String(value)
+    }
+}
+
+impl From<bool> for ItemValue {
+    fn from(value: bool) -> Self {
+        ItemValue::Bool(value)
+    }
+}
+
+impl From<i64> for ItemValue {
+    fn from(value: i64) -> Self {
+        ItemValue::number(value)
+    }
+}
+
+/// Check that `s` is a decimal number DynamoDB accepts (`-12`, `3.14`, `1E+10`)
+pub fn is_valid_number(s: &str) -> bool {
+    let s = s.strip_prefix(['-', '+']).unwrap_or(s);
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    let mantissa_ok = !(int_part.is_empty() && frac_part.is_empty()) && digits(int_part) && digits(frac_part);
+
+    let exponent_ok = match exponent {
+        Some(exp) => {
+            let exp = exp.strip_prefix(['-', '+']).unwrap_or(exp);
+            !exp.is_empty() && digits(exp)
+        }
+        None => true,
+    };
+
+    mantissa_ok && exponent_ok
+}
+
+/// DynamoDB JSON wire representation
+#[derive(Serialize, Deserialize)]
+enum Repr {
+    S(String),
+    N(String),
+    B(#[serde(with = "base64_bytes")] Vec<u8>),
+    #[serde(rename = "BOOL")]
+    Bool(bool),
+    #[serde(rename = "NULL")]
+    Null(bool),
+    M(HashMap<String, ItemValue>),
+    L(Vec<ItemValue>),
+    #[serde(rename = "SS")]
+    Ss(Vec<String>),
+    #[serde(rename = "NS")]
+    Ns(Vec<String>),
+    #[serde(rename = "BS")]
+    Bs(#[serde(with = "base64_bytes_list")] Vec<Vec<u8>>),
+}
+
+impl TryFrom<Repr> for ItemValue {
+    type Error = String;
+
+    fn try_from(repr: Repr) -> Result<Self, Self::Error> {
+        let check_number = |n: &String| {
+            if is_valid_number(n) {
+                Ok(())
+            } else {
+                Err(format!("invalid number '{}'", n))
+            }
+        };
+
+        Ok(match repr {
+            Repr::S(s) => ItemValue::String(s),
+            Repr::N(n) => {
+                check_number(&n)?;
+                ItemValue::Number(n)
+            }
+            Repr::B(b) => ItemValue::Binary(b),
+            Repr::Bool(b) => ItemValue::Bool(b),
+            Repr::Null(_) => ItemValue::Null,
+            Repr::M(m) => ItemValue::Map(m),
+            Repr::L(l) => ItemValue::List(l),
+            Repr::Ss(ss) => ItemValue::StringSet(ss),
+            Repr::Ns(ns) => {
+                ns.iter().try_for_each(check_number)?;
+                ItemValue::NumberSet(ns)
+            }
+            Repr::Bs(bs) => ItemValue::BinarySet(bs),
+        })
+    }
+}
+
+impl From<ItemValue> for Repr {
+    fn from(value: ItemValue) -> Self {
+        match value {
+            ItemValue::String(s) => Repr::S(s),
+            ItemValue::Number(n) => Repr::N(n),
+            ItemValue::Binary(b) => Repr::B(b),
+            ItemValue::Bool(b) => Repr::Bool(b),
+            ItemValue::Null => Repr::Null(true),
+            ItemValue::Map(m) => Repr::M(m),
+            ItemValue::List(l) => Repr::L(l),
+            ItemValue::StringSet(ss) => Repr::Ss(ss),
+            ItemValue::NumberSet(ns) => Repr::Ns(ns),
+            ItemValue::BinarySet(bs) => Repr::Bs(bs),
+        }
+    }
+}
+
+mod base64_bytes {
+    use super::{Engine, BASE64};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        BASE64.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+mod base64_bytes_list {
+    use super::{Engine, BASE64};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(list: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(list.iter().map(|bytes| BASE64.encode(bytes)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|encoded| BASE64.decode(encoded).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_representation() {
+        let value = ItemValue::Map(HashMap::from([
+            ("name".to_string(), ItemValue::from("widget")),
+            ("price".to_string(), ItemValue::Number("19.990000000000000001".to_string())),
+            ("blob".to_string(), ItemValue::Binary(vec![0, 1, 2])),
+            ("gone".to_string(), ItemValue::Null),
+            ("tags".to_string(), ItemValue::StringSet(vec!["a".into(), "b".into()])),
+        ]));
+
+        let encoded = serde_json::to_value(&value).unwrap();
+        assert_eq!(
+            encoded,
+            json!({"M": {
+                "name": {"S": "widget"},
+                "price": {"N": "19.990000000000000001"},
+                "blob": {"B": "AAEC"},
+                "gone": {"NULL": true},
+                "tags": {"SS": ["a", "b"]}
+            }})
+        );
+
+        let decoded: ItemValue = serde_json::from_value(encoded).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn test_sets_and_lists_stay_distinct() {
+        let list: ItemValue = serde_json::from_value(json!({"L": [{"S": "a"}]})).unwrap();
+        let set: ItemValue = serde_json::from_value(json!({"SS": ["a"]})).unwrap();
+        assert_eq!(list, ItemValue::List(vec![ItemValue::from("a")]));
+        assert_eq!(set, ItemValue::StringSet(vec!["a".to_string()]));
+    }
+
+    #[test]
+    fn test_rejects_invalid_numbers() {
+        assert!(serde_json::from_value::<ItemValue>(json!({"N": "12abc"})).is_err());
+        assert!(serde_json::from_value::<ItemValue>(json!({"NS": ["1", "NaN"]})).is_err());
+    }
+
+    #[test]
+    fn test_is_valid_number() {
+        for valid in ["0", "-12", "+3.5", ".5", "5.", "1E+10", "-2.5e-3"] {
+            assert!(is_valid_number(valid), "{} should be valid", valid);
+        }
+        for invalid in ["", ".", "-", "1e", "1.2.3", "inf", "NaN", "0x10"] {
+            assert!(!is_valid_number(invalid), "{} should be invalid", invalid);
+        }
+    }
+}
@@ -1,92 +1,1362 @@
 use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use std::collections::HashMap;
-use std::error::Error;
-use super::ports::{DatabasePort, StoragePort};
+use std::hash::{DefaultHasher, Hasher};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use super::batch::{TransactItem, WriteRequest, TRANSACT_WRITE_LIMIT};
+use super::clock::Clock;
+use super::condition::{Condition, PutOptions};
+use super::email::{EmailMessage, TemplatedEmail};
+use super::errors::{CancellationReason, DomainError, DomainResult};
+use super::item::{Item, ItemValue};
+use super::update::{ReturnValues, UpdateAction, UpdateRequest};
+use super::query::{decode_token, encode_token, Filter, FilterOp, Page, QueryRequest, ScanRequest, SortKeyCondition};
+use super::object::{
+    DeleteFailure, ListObjectsRequest, ObjectListing, ObjectMetadata, ObjectSummary, PresignMethod, PresignRequest,
+    PresignedUrl, PutObjectOptions,
+};
+use super::ports::{ByteReader, DatabasePort, EmailPort, SecretsPort, StoragePort};
 
-/// Mock implementation of DatabasePort for testing
+/// Mock implementation of DatabasePort for testing.
+///
+/// Items are stored in memory per table; each table needs a key schema
+/// (declared with `with_table`) so writes can replace existing items.
 pub struct MockDatabase {
-    pub items: HashMap<String, HashMap<String, String>>,
+    items: Mutex<HashMap<String, Item>>,
+    key_schemas: HashMap<String, Vec<String>>,
+    calls: AtomicUsize,
+}
+
+impl Default for MockDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MockDatabase {
     pub fn new() -> Self {
         Self {
-            items: HashMap::new(),
+            items: Mutex::new(HashMap::new()),
+            key_schemas: HashMap::new(),
+            calls: AtomicUsize::new(0),
         }
     }
 
-    pub fn with_item(mut self, table: &str, key: &str, value: HashMap<String, String>) -> Self {
-        let full_key = format!("{}::{}", table, key);
-        self.items.insert(full_key, value);
+    /// Number of port calls served by this instance
+    pub fn call_count(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    /// Declare a table and the attribute names forming its primary key
+    pub fn with_table(mut self, table: &str, key_attributes: &[&str]) -> Self {
+        self.key_schemas.insert(
+            table.to_string(),
+            key_attributes.iter().map(|k| k.to_string()).collect(),
+        );
+        self
+    }
+
+    /// Seed an item; the table must have been declared with `with_table`
+    pub fn with_item(self, table: &str, item: Item) -> Self {
+        let storage_key = self
+            .storage_key_for_item(table, &item)
+            .unwrap_or_else(|e| panic!("Cannot seed mock item: {}", e));
+        self.items.lock().unwrap().insert(storage_key, item);
         self
     }
+
+    /// Build the in-memory key from the key attributes, in name order
+    fn storage_key(table: &str, key: &Item) -> String {
+        let mut parts: Vec<(&String, &ItemValue)> = key.iter().collect();
+        parts.sort_by(|a, b| a.0.cmp(b.0));
+        let encoded = serde_json::to_string(&parts).unwrap_or_default();
+        format!("{}::{}", table, encoded)
+    }
+
+    /// Extract the primary key attributes of `item`
+    fn key_of(&self, table: &str, item: &Item) -> DomainResult<Item> {
+        let schema = self
+            .key_schemas
+            .get(table)
+            .ok_or_else(|| DomainError::Internal(format!("Table {} does not exist", table)))?;
+
+        let mut key = Item::new();
+        for name in schema {
+            let value = item
+                .get(name)
+                .ok_or_else(|| DomainError::Validation(format!("Missing key attribute '{}'", name)))?;
+            key.insert(name.clone(), value.clone());
+        }
+        Ok(key)
+    }
+
+    fn storage_key_for_item(&self, table: &str, item: &Item) -> DomainResult<String> {
+        Ok(Self::storage_key(table, &self.key_of(table, item)?))
+    }
+
+    /// All items of a table, ordered by storage key
+    fn table_items(&self, table: &str) -> Vec<(String, Item)> {
+        let prefix = format!("{}::", table);
+        let mut items: Vec<(String, Item)> = self
+            .items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(k, _)| k.starts_with(&prefix))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        items
+    }
+
+    /// Apply an update to `items`, returning the old and new images
+    fn update_locked(
+        &self,
+        items: &mut HashMap<String, Item>,
+        table: &str,
+        request: &UpdateRequest,
+    ) -> DomainResult<(Option<Item>, Item)> {
+        if request.actions.is_empty() {
+            return Err(DomainError::Validation("Update has no actions".to_string()));
+        }
+        let key = self.key_of(table, &request.key)?;
+        if let Some(action) = request.actions.iter().find(|a| key.contains_key(a.attribute())) {
+            return Err(DomainError::Validation(format!(
+                "Cannot update key attribute '{}'",
+                action.attribute()
+            )));
+        }
+        let storage_key = Self::storage_key(table, &key);
+
+        let old = items.get(&storage_key).cloned();
+        check_conditions(old.as_ref(), &request.conditions, &storage_key)?;
+
+        let mut new = old.clone().unwrap_or(key);
+        for action in &request.actions {
+            apply_update(&mut new, action)?;
+        }
+        items.insert(storage_key, new.clone());
+        Ok((old, new))
+    }
+
+    /// Apply one transaction item to `items`, returning the storage key it touched
+    fn transact_locked(&self, items: &mut HashMap<String, Item>, item: &TransactItem) -> DomainResult<String> {
+        match item {
+            TransactItem::Put { table_name, item, conditions } => {
+                let storage_key = self.storage_key_for_item(table_name, item)?;
+                check_conditions(items.get(&storage_key), conditions, &storage_key)?;
+                items.insert(storage_key.clone(), item.clone());
+                Ok(storage_key)
+            }
+            TransactItem::Update { table_name, request } => {
+                self.update_locked(items, table_name, request)?;
+                Ok(Self::storage_key(table_name, &request.key))
+            }
+            TransactItem::Delete { table_name, key, conditions } => {
+                let storage_key = Self::storage_key(table_name, key);
+                check_conditions(items.get(&storage_key), conditions, &storage_key)?;
+                items.remove(&storage_key);
+                Ok(storage_key)
+            }
+            TransactItem::ConditionCheck { table_name, key, conditions } => {
+                let storage_key = Self::storage_key(table_name, key);
+                check_conditions(items.get(&storage_key), conditions, &storage_key)?;
+                Ok(storage_key)
+            }
+        }
+    }
+
+    /// Apply start token, limit (items evaluated, as in DynamoDB) and filters
+    fn paginate(
+        &self,
+        table: &str,
+        items: Vec<(String, Item)>,
+        next_token: Option<&str>,
+        limit: Option<u32>,
+        filters: &[Filter],
+    ) -> DomainResult<Page> {
+        let start = match next_token {
+            Some(token) => {
+                let last_key = Self::storage_key(table, &decode_token(token)?);
+                items.iter().position(|(k, _)| *k == last_key).map_or(items.len(), |i| i + 1)
+            }
+            None => 0,
+        };
+
+        let remaining = &items[start.min(items.len())..];
+        let limit = limit.map_or(remaining.len(), |l| l as usize);
+        let evaluated = &remaining[..limit.min(remaining.len())];
+
+        let next_token = if evaluated.len() < remaining.len() {
+            match evaluated.last() {
+                Some((_, last)) => Some(encode_token(&self.key_of(table, last)?)),
+                None => None,
+            }
+        } else {
+            None
+        };
+
+        let items = evaluated
+            .iter()
+            .filter(|(_, item)| filters.iter().all(|f| matches_filter(item, f)))
+            .map(|(_, item)| item.clone())
+            .collect();
+
+        Ok(Page { items, next_token })
+    }
+}
+
+/// Order two values of the same type the way DynamoDB compares them
+fn compare_values(a: &ItemValue, b: &ItemValue) -> Option<std::cmp::Ordering> {
+    match (a, b) {
+        (ItemValue::String(a), ItemValue::String(b)) => Some(a.cmp(b)),
+        (ItemValue::Number(a), ItemValue::Number(b)) => {
+            a.parse::<f64>().ok()?.partial_cmp(&b.parse::<f64>().ok()?)
+        }
+        (ItemValue::Binary(a), ItemValue::Binary(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn matches_sort_key(value: Option<&ItemValue>, condition: &SortKeyCondition) -> bool {
+    use std::cmp::Ordering::*;
+    let Some(value) = value else { return false };
+    match condition {
+        SortKeyCondition::Eq(v) => compare_values(value, v) == Some(Equal),
+        SortKeyCondition::Lt(v) => compare_values(value, v) == Some(Less),
+        SortKeyCondition::Le(v) => matches!(compare_values(value, v), Some(Less | Equal)),
+        SortKeyCondition::Gt(v) => compare_values(value, v) == Some(Greater),
+        SortKeyCondition::Ge(v) => matches!(compare_values(value, v), Some(Greater | Equal)),
+        SortKeyCondition::Between(low, high) => {
+            matches!(compare_values(value, low), Some(Greater | Equal))
+                && matches!(compare_values(value, high), Some(Less | Equal))
+        }
+        SortKeyCondition::BeginsWith(prefix) => value.as_str().is_some_and(|s| s.starts_with(prefix.as_str())),
+    }
+}
+
+fn matches_filter(item: &Item, filter: &Filter) -> bool {
+    use std::cmp::Ordering::*;
+    let value = item.get(&filter.attribute);
+    match &filter.op {
+        FilterOp::Exists => value.is_some(),
+        FilterOp::NotExists => value.is_none(),
+        FilterOp::Ne(v) => value != Some(v),
+        FilterOp::Contains(v) => match (value, v) {
+            (Some(ItemValue::String(s)), ItemValue::String(needle)) => s.contains(needle.as_str()),
+            (Some(ItemValue::StringSet(set)), ItemValue::String(needle)) => set.contains(needle),
+            (Some(ItemValue::NumberSet(set)), ItemValue::Number(needle)) => set.contains(needle),
+            (Some(ItemValue::List(list)), needle) => list.contains(needle),
+            _ => false,
+        },
+        FilterOp::Eq(v) => value.is_some_and(|value| value == v || compare_values(value, v) == Some(Equal)),
+        FilterOp::Lt(v) => matches_sort_key(value, &SortKeyCondition::Lt(v.clone())),
+        FilterOp::Le(v) => matches_sort_key(value, &SortKeyCondition::Le(v.clone())),
+        FilterOp::Gt(v) => matches_sort_key(value, &SortKeyCondition::Gt(v.clone())),
+        FilterOp::Ge(v) => matches_sort_key(value, &SortKeyCondition::Ge(v.clone())),
+        FilterOp::Between(low, high) => {
+            matches_sort_key(value, &SortKeyCondition::Between(low.clone(), high.clone()))
+        }
+        FilterOp::BeginsWith(prefix) => matches_sort_key(value, &SortKeyCondition::BeginsWith(prefix.clone())),
+    }
+}
+
+/// Evaluate a write condition against the stored item (empty when missing)
+fn matches_condition(stored: &Item, condition: &Condition) -> DomainResult<bool> {
+    match condition {
+        Condition::Match(filters) => Ok(filters.iter().all(|f| matches_filter(stored, f))),
+        Condition::Expression { expression, .. } => Err(DomainError::Internal(format!(
+            "MockDatabase cannot evaluate raw condition expression '{}'",
+            expression
+        ))),
+    }
+}
+
+fn check_conditions(stored: Option<&Item>, conditions: &[Condition], storage_key: &str) -> DomainResult<()> {
+    let empty = Item::new();
+    let stored = stored.unwrap_or(&empty);
+    for condition in conditions {
+        if !matches_condition(stored, condition)? {
+            return Err(DomainError::ConditionFailed(format!("Condition failed for {}", storage_key)));
+        }
+    }
+    Ok(())
+}
+
+/// Add two decimal numbers, exactly for integers
+fn add_numbers(a: &str, b: &str) -> DomainResult<String> {
+    if let (Ok(a), Ok(b)) = (a.parse::<i128>(), b.parse::<i128>()) {
+        return Ok((a + b).to_string());
+    }
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => Ok((a + b).to_string()),
+        _ => Err(DomainError::Validation(format!("Cannot add '{}' and '{}'", a, b))),
+    }
+}
+
+/// Apply one update action to a top-level attribute, following DynamoDB's ADD/DELETE rules
+fn apply_update(item: &mut Item, action: &UpdateAction) -> DomainResult<()> {
+    fn union<T: Clone + PartialEq>(current: &mut Vec<T>, added: &[T]) {
+        for value in added {
+            if !current.contains(value) {
+                current.push(value.clone());
+            }
+        }
+    }
+    let type_mismatch = |attribute: &str| {
+        DomainError::Validation(format!("Operand type does not match attribute '{}'", attribute))
+    };
+
+    match action {
+        UpdateAction::Set(attribute, value) => {
+            item.insert(attribute.clone(), value.clone());
+        }
+        UpdateAction::SetIfNotExists(attribute, value) => {
+            item.entry(attribute.clone()).or_insert_with(|| value.clone());
+        }
+        UpdateAction::Remove(attribute) => {
+            item.remove(attribute);
+        }
+        UpdateAction::Add(attribute, value) => {
+            let new = match (item.get(attribute), value) {
+                (None, ItemValue::Number(_) | ItemValue::StringSet(_) | ItemValue::NumberSet(_) | ItemValue::BinarySet(_)) => {
+                    value.clone()
+                }
+                (Some(ItemValue::Number(a)), ItemValue::Number(b)) => ItemValue::Number(add_numbers(a, b)?),
+                (Some(ItemValue::StringSet(a)), ItemValue::StringSet(b)) => {
+                    let mut a = a.clone();
+                    union(&mut a, b);
+                    ItemValue::StringSet(a)
+                }
+                (Some(ItemValue::NumberSet(a)), ItemValue::NumberSet(b)) => {
+                    let mut a = a.clone();
+                    union(&mut a, b);
+                    ItemValue::NumberSet(a)
+                }
+                (Some(ItemValue::BinarySet(a)), ItemValue::BinarySet(b)) => {
+                    let mut a = a.clone();
+                    union(&mut a, b);
+                    ItemValue::BinarySet(a)
+                }
+                _ => return Err(type_mismatch(attribute)),
+            };
+            item.insert(attribute.clone(), new);
+        }
+        UpdateAction::DeleteFromSet(attribute, value) => {
+            let remaining = match (item.get(attribute), value) {
+                (None, _) => return Ok(()),
+                (Some(ItemValue::StringSet(a)), ItemValue::StringSet(b)) => {
+                    ItemValue::StringSet(a.iter().filter(|v| !b.contains(v)).cloned().collect())
+                }
+                (Some(ItemValue::NumberSet(a)), ItemValue::NumberSet(b)) => {
+                    ItemValue::NumberSet(a.iter().filter(|v| !b.contains(v)).cloned().collect())
+                }
+                (Some(ItemValue::BinarySet(a)), ItemValue::BinarySet(b)) => {
+                    ItemValue::BinarySet(a.iter().filter(|v| !b.contains(v)).cloned().collect())
+                }
+                _ => return Err(type_mismatch(attribute)),
+            };
+            // DynamoDB has no empty sets: removing every element removes the attribute
+            let empty = match &remaining {
+                ItemValue::StringSet(v) | ItemValue::NumberSet(v) => v.is_empty(),
+                ItemValue::BinarySet(v) => v.is_empty(),
+                _ => false,
+            };
+            if empty {
+                item.remove(attribute);
+            } else {
+                item.insert(attribute.clone(), remaining);
+            }
+        }
+    }
+    Ok(())
 }
 
 #[async_trait]
 impl DatabasePort for MockDatabase {
-    async fn get_item(&self, table_name: &str, key: HashMap<String, String>) -> Result<Option<HashMap<String, String>>, Box<dyn Error + Send + Sync>> {
-        let key_str = key.values().next().unwrap_or(&String::new()).clone();
-        let full_key = format!("{}::{}", table_name, key_str);
-        Ok(self.items.get(&full_key).cloned())
+    async fn get_item(&self, table_name: &str, key: Item) -> DomainResult<Option<Item>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let storage_key = Self::storage_key(table_name, &key);
+        Ok(self.items.lock().unwrap().get(&storage_key).cloned())
     }
 
-    async fn put_item(&self, _table_name: &str, _item: HashMap<String, String>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    async fn put_item_with(&self, table_name: &str, item: Item, options: PutOptions) -> DomainResult<Item> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let (item, conditions) = options.prepare(item)?;
+        let storage_key = self.storage_key_for_item(table_name, &item)?;
+
+        // Check and write under one lock, as DynamoDB does atomically
+        let mut items = self.items.lock().unwrap();
+        check_conditions(items.get(&storage_key), &conditions, &storage_key)?;
+        items.insert(storage_key, item.clone());
+        Ok(item)
+    }
+
+    async fn update_item(&self, table_name: &str, request: UpdateRequest) -> DomainResult<Option<Item>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let mut items = self.items.lock().unwrap();
+        let (old, new) = self.update_locked(&mut items, table_name, &request)?;
+
+        let updated = |image: &Item| -> Item {
+            request
+                .actions
+                .iter()
+                .filter_map(|a| image.get(a.attribute()).map(|v| (a.attribute().to_string(), v.clone())))
+                .collect()
+        };
+        Ok(match request.return_values {
+            ReturnValues::None => None,
+            ReturnValues::AllOld => old,
+            ReturnValues::UpdatedOld => old.as_ref().map(updated),
+            ReturnValues::AllNew => Some(new),
+            ReturnValues::UpdatedNew => Some(updated(&new)),
+        })
+    }
+
+    async fn delete_item(&self, table_name: &str, key: Item, conditions: Vec<Condition>) -> DomainResult<Option<Item>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let storage_key = Self::storage_key(table_name, &key);
+
+        let mut items = self.items.lock().unwrap();
+        check_conditions(items.get(&storage_key), &conditions, &storage_key)?;
+        Ok(items.remove(&storage_key))
+    }
+
+    async fn batch_get(&self, table_name: &str, keys: Vec<Item>) -> DomainResult<Vec<Item>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let items = self.items.lock().unwrap();
+        Ok(keys
+            .iter()
+            .filter_map(|key| items.get(&Self::storage_key(table_name, key)).cloned())
+            .collect())
+    }
+
+    async fn batch_write(&self, table_name: &str, requests: Vec<WriteRequest>) -> DomainResult<()> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let mut items = self.items.lock().unwrap();
+        for request in requests {
+            match request {
+                WriteRequest::Put(item) => {
+                    let storage_key = self.storage_key_for_item(table_name, &item)?;
+                    items.insert(storage_key, item);
+                }
+                WriteRequest::Delete(key) => {
+                    items.remove(&Self::storage_key(table_name, &key));
+                }
+            }
+        }
         Ok(())
     }
+
+    async fn transact_write(&self, transact_items: Vec<TransactItem>) -> DomainResult<()> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if transact_items.is_empty() || transact_items.len() > TRANSACT_WRITE_LIMIT {
+            return Err(DomainError::Validation(format!(
+                "A transaction needs between 1 and {} items, got {}",
+                TRANSACT_WRITE_LIMIT,
+                transact_items.len()
+            )));
+        }
+
+        // Apply to a copy and only commit it if every item succeeded
+        let mut items = self.items.lock().unwrap();
+        let mut staged = items.clone();
+        let mut touched = Vec::new();
+        let mut reasons = Vec::new();
+        for (index, item) in transact_items.iter().enumerate() {
+            match self.transact_locked(&mut staged, item) {
+                Ok(storage_key) => {
+                    if touched.contains(&storage_key) {
+                        return Err(DomainError::Validation(
+                            "Transaction cannot include multiple operations on one item".to_string(),
+                        ));
+                    }
+                    touched.push(storage_key);
+                }
+                Err(DomainError::ConditionFailed(message)) => reasons.push(CancellationReason {
+                    index,
+                    code: "ConditionalCheckFailed".to_string(),
+                    message: Some(message),
+                }),
+                Err(e) => return Err(e),
+            }
+        }
+
+        if !reasons.is_empty() {
+            return Err(DomainError::TransactionCanceled {
+                message: "Transaction canceled".to_string(),
+                reasons,
+            });
+        }
+        *items = staged;
+        Ok(())
+    }
+
+    async fn query(&self, table_name: &str, request: QueryRequest) -> DomainResult<Page> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let (pk_name, pk_value) = &request.partition_key;
+
+        // Order by the queried sort key, falling back to the table's sort key
+        let sort_key_name = request
+            .sort_key
+            .as_ref()
+            .map(|(name, _)| name.clone())
+            .or_else(|| self.key_schemas.get(table_name).and_then(|schema| schema.get(1).cloned()));
+
+        let mut items: Vec<(String, Item)> = self
+            .table_items(table_name)
+            .into_iter()
+            .filter(|(_, item)| item.get(pk_name) == Some(pk_value))
+            .filter(|(_, item)| match &request.sort_key {
+                Some((name, condition)) => matches_sort_key(item.get(name), condition),
+                None => true,
+            })
+            .collect();
+
+        if let Some(name) = sort_key_name {
+            items.sort_by(|a, b| {
+                match (a.1.get(&name), b.1.get(&name)) {
+                    (Some(x), Some(y)) => compare_values(x, y).unwrap_or(std::cmp::Ordering::Equal),
+                    _ => std::cmp::Ordering::Equal,
+                }
+            });
+        }
+        if !request.scan_forward {
+            items.reverse();
+        }
+
+        self.paginate(table_name, items, request.next_token.as_deref(), request.limit, &request.filters)
+    }
+
+    async fn scan(&self, table_name: &str, request: ScanRequest) -> DomainResult<Page> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let items = self
+            .table_items(table_name)
+            .into_iter()
+            .filter(|(storage_key, _)| match request.segment {
+                Some(segment) => {
+                    let hash = storage_key.bytes().fold(0u32, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u32));
+                    hash % segment.total_segments.max(1) == segment.segment
+                }
+                None => true,
+            })
+            .collect();
+
+        self.paginate(table_name, items, request.next_token.as_deref(), request.limit, &request.filters)
+    }
+}
+
+/// Size and checksum of an object written through `put_object_stream`; the bytes are not kept
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamedObject {
+    pub size: u64,
+    pub checksum: u64,
+}
+
+/// Buffer size used by the mock when consuming streams
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Read a stream to the end through a fixed-size buffer, returning its size and checksum
+pub async fn stream_checksum<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<StreamedObject> {
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = vec![0u8; STREAM_BUFFER_SIZE];
+    let mut size = 0u64;
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.write(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok(StreamedObject {
+        size,
+        checksum: hasher.finish(),
+    })
+}
+
+/// Deterministic byte source of a given size that never holds more than one pattern block
+struct PatternReader {
+    pattern: Vec<u8>,
+    position: u64,
+    size: u64,
+}
+
+impl PatternReader {
+    const PERIOD: usize = 251;
+
+    fn new(size: u64) -> Self {
+        Self {
+            pattern: (0..STREAM_BUFFER_SIZE + Self::PERIOD).map(|i| (i % Self::PERIOD) as u8).collect(),
+            position: 0,
+            size,
+        }
+    }
 }
 
-/// Mock implementation of StoragePort for testing
+impl AsyncRead for PatternReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let remaining = (self.size - self.position).min(STREAM_BUFFER_SIZE as u64) as usize;
+        let len = remaining.min(buf.remaining());
+        let offset = (self.position % Self::PERIOD as u64) as usize;
+        buf.put_slice(&self.pattern[offset..offset + len]);
+        self.position += len as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Content of a mock object
+enum MockContent {
+    Bytes(Vec<u8>),
+    /// Pattern bytes produced on demand, see `with_generated_object`
+    Generated(u64),
+    /// Written through `put_object_stream`; only the size and checksum are kept
+    Streamed(StreamedObject),
+}
+
+struct MockObject {
+    content: MockContent,
+    content_type: Option<String>,
+    metadata: HashMap<String, String>,
+    last_modified: chrono::DateTime<chrono::Utc>,
+}
+
+impl MockObject {
+    fn new(content: MockContent) -> Self {
+        Self {
+            content,
+            content_type: None,
+            metadata: HashMap::new(),
+            last_modified: chrono::Utc::now(),
+        }
+    }
+
+    fn size(&self) -> u64 {
+        match &self.content {
+            MockContent::Bytes(data) => data.len() as u64,
+            MockContent::Generated(size) => *size,
+            MockContent::Streamed(object) => object.size,
+        }
+    }
+
+    fn etag(&self) -> Option<String> {
+        let checksum = match &self.content {
+            MockContent::Bytes(data) => {
+                let mut hasher = DefaultHasher::new();
+                hasher.write(data);
+                hasher.finish()
+            }
+            MockContent::Generated(_) => return None,
+            MockContent::Streamed(object) => object.checksum,
+        };
+        Some(format!("\"{:016x}\"", checksum))
+    }
+}
+
+/// Mock implementation of StoragePort for testing.
+///
+/// Objects are keyed by `bucket::key`; listings are ordered by key as in S3.
 pub struct MockStorage {
-    pub objects: HashMap<String, Vec<u8>>,
+    objects: Mutex<HashMap<String, MockObject>>,
+    calls: AtomicUsize,
+}
+
+impl Default for MockStorage {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MockStorage {
     pub fn new() -> Self {
         Self {
-            objects: HashMap::new(),
+            objects: Mutex::new(HashMap::new()),
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of port calls served by this instance
+    pub fn call_count(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    fn full_key(bucket: &str, key: &str) -> String {
+        format!("{}::{}", bucket, key)
+    }
+
+    fn insert(&self, bucket: &str, key: &str, object: MockObject) {
+        self.objects.lock().unwrap().insert(Self::full_key(bucket, key), object);
+    }
+
+    pub fn with_object(self, bucket: &str, key: &str, data: Vec<u8>) -> Self {
+        self.insert(bucket, key, MockObject::new(MockContent::Bytes(data)));
+        self
+    }
+
+    /// Set the content type and user metadata of an object seeded earlier
+    pub fn with_object_metadata(self, bucket: &str, key: &str, content_type: &str, metadata: &[(&str, &str)]) -> Self {
+        {
+            let mut objects = self.objects.lock().unwrap();
+            let object = objects
+                .get_mut(&Self::full_key(bucket, key))
+                .unwrap_or_else(|| panic!("Cannot set metadata of missing mock object {}/{}", bucket, key));
+            object.content_type = Some(content_type.to_string());
+            object.metadata = metadata.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
         }
+        self
     }
 
-    pub fn with_object(mut self, bucket: &str, key: &str, data: Vec<u8>) -> Self {
-        let full_key = format!("{}::{}", bucket, key);
-        self.objects.insert(full_key, data);
+    /// Declare an object of `size` bytes whose content is generated while it is streamed
+    pub fn with_generated_object(self, bucket: &str, key: &str, size: u64) -> Self {
+        self.insert(bucket, key, MockObject::new(MockContent::Generated(size)));
         self
     }
+
+    /// Size and checksum of an object written with `put_object_stream`
+    pub fn streamed_object(&self, bucket: &str, key: &str) -> Option<StreamedObject> {
+        match self.objects.lock().unwrap().get(&Self::full_key(bucket, key)) {
+            Some(MockObject { content: MockContent::Streamed(object), .. }) => Some(*object),
+            _ => None,
+        }
+    }
+
+    fn not_found(bucket: &str, key: &str) -> DomainError {
+        DomainError::NotFound(format!("Object {}/{} not found", bucket, key))
+    }
 }
 
 #[async_trait]
 impl StoragePort for MockStorage {
-    async fn get_object(&self, bucket: &str, key: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
-        let full_key = format!("{}::{}", bucket, key);
-        self.objects
-            .get(&full_key)
-            .cloned()
-            .ok_or_else(|| "Object not found".into())
+    async fn get_object(&self, bucket: &str, key: &str) -> DomainResult<Vec<u8>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let objects = self.objects.lock().unwrap();
+        match objects.get(&Self::full_key(bucket, key)).map(|o| &o.content) {
+            Some(MockContent::Bytes(data)) => Ok(data.clone()),
+            Some(MockContent::Generated(_)) | Some(MockContent::Streamed(_)) => Err(DomainError::Internal(format!(
+                "Mock object {}/{} is only available as a stream",
+                bucket, key
+            ))),
+            None => Err(Self::not_found(bucket, key)),
+        }
     }
 
-    async fn put_object(&self, _bucket: &str, _key: &str, _body: Vec<u8>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    async fn put_object_with(&self, bucket: &str, key: &str, body: Vec<u8>, options: PutObjectOptions) -> DomainResult<()> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let mut object = MockObject::new(MockContent::Bytes(body));
+        object.content_type = options.content_type;
+        self.insert(bucket, key, object);
         Ok(())
     }
+
+    async fn get_object_stream(&self, bucket: &str, key: &str) -> DomainResult<ByteReader> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let objects = self.objects.lock().unwrap();
+        match objects.get(&Self::full_key(bucket, key)).map(|o| &o.content) {
+            Some(MockContent::Bytes(data)) => Ok(Box::pin(std::io::Cursor::new(data.clone()))),
+            Some(MockContent::Generated(size)) => Ok(Box::pin(PatternReader::new(*size))),
+            Some(MockContent::Streamed(_)) => Err(DomainError::Internal(format!(
+                "Mock object {}/{} was streamed in and its bytes were not kept",
+                bucket, key
+            ))),
+            None => Err(Self::not_found(bucket, key)),
+        }
+    }
+
+    async fn put_object_stream(&self, bucket: &str, key: &str, mut body: ByteReader) -> DomainResult<()> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let object = stream_checksum(&mut body)
+            .await
+            .map_err(|e| DomainError::Upstream(format!("Failed to read upload body: {}", e)))?;
+        self.insert(bucket, key, MockObject::new(MockContent::Streamed(object)));
+        Ok(())
+    }
+
+    async fn head_object(&self, bucket: &str, key: &str) -> DomainResult<Option<ObjectMetadata>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let objects = self.objects.lock().unwrap();
+        Ok(objects.get(&Self::full_key(bucket, key)).map(|object| ObjectMetadata {
+            key: key.to_string(),
+            size: object.size(),
+            etag: object.etag(),
+            content_type: object.content_type.clone(),
+            last_modified: Some(object.last_modified),
+            metadata: object.metadata.clone(),
+        }))
+    }
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> DomainResult<()> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        self.objects.lock().unwrap().remove(&Self::full_key(bucket, key));
+        Ok(())
+    }
+
+    async fn delete_objects(&self, bucket: &str, keys: Vec<String>) -> DomainResult<Vec<DeleteFailure>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let mut objects = self.objects.lock().unwrap();
+        for key in keys {
+            objects.remove(&Self::full_key(bucket, &key));
+        }
+        Ok(Vec::new())
+    }
+
+    async fn list_objects(&self, bucket: &str, request: ListObjectsRequest) -> DomainResult<ObjectListing> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let bucket_prefix = Self::full_key(bucket, "");
+        let prefix = request.prefix.unwrap_or_default();
+        let start_after = match request.next_token {
+            Some(token) => Some(
+                URL_SAFE_NO_PAD
+                    .decode(&token)
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .ok_or_else(|| DomainError::Validation("Invalid next_token".to_string()))?,
+            ),
+            None => None,
+        };
+
+        let objects = self.objects.lock().unwrap();
+        let mut keys: Vec<(&str, &MockObject)> = objects
+            .iter()
+            .filter_map(|(full_key, object)| full_key.strip_prefix(&bucket_prefix).map(|key| (key, object)))
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .collect();
+        keys.sort_by(|a, b| a.0.cmp(b.0));
+
+        // Entries are objects or rolled-up prefixes, in key order
+        let mut entries: Vec<(String, Option<&MockObject>)> = Vec::new();
+        for (key, object) in keys {
+            let rolled_up = request.delimiter.as_deref().and_then(|delimiter| {
+                key[prefix.len()..]
+                    .find(delimiter)
+                    .map(|i| key[..prefix.len() + i + delimiter.len()].to_string())
+            });
+            match rolled_up {
+                Some(common) => {
+                    if entries.last().map(|(k, _)| k) != Some(&common) {
+                        entries.push((common, None));
+                    }
+                }
+                None => entries.push((key.to_string(), Some(object))),
+            }
+        }
+        if let Some(start_after) = &start_after {
+            entries.retain(|(key, _)| key > start_after);
+        }
+
+        let max_keys = request.max_keys.unwrap_or(1000) as usize;
+        // As in S3, `max_keys(0)` is an empty page with nothing to continue from
+        let next_token = (max_keys > 0 && entries.len() > max_keys)
+            .then(|| URL_SAFE_NO_PAD.encode(&entries[max_keys - 1].0));
+        entries.truncate(max_keys);
+
+        let mut listing = ObjectListing {
+            next_token,
+            ..ObjectListing::default()
+        };
+        for (key, object) in entries {
+            match object {
+                Some(object) => listing.objects.push(ObjectSummary {
+                    size: object.size(),
+                    etag: object.etag(),
+                    last_modified: Some(object.last_modified),
+                    key,
+                }),
+                None => listing.common_prefixes.push(key),
+            }
+        }
+        Ok(listing)
+    }
+
+    async fn presign(&self, bucket: &str, key: &str, request: PresignRequest) -> DomainResult<PresignedUrl> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        request.validate()?;
+        let method = match request.method {
+            PresignMethod::Get => "GET",
+            PresignMethod::Put => "PUT",
+        };
+        Ok(PresignedUrl {
+            url: format!(
+                "https://{}.mock-s3.local/{}?method={}&expires_in={}",
+                bucket, key, method, request.expires_in_secs
+            ),
+            method: request.method,
+            headers: request.signed_headers(),
+            expires_in: request.expires_in_secs,
+        })
+    }
+}
+
+/// A message accepted by `MockEmail`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SentEmail {
+    Message(EmailMessage),
+    Templated(TemplatedEmail),
+}
+
+/// Mock implementation of EmailPort that records every message it accepts
+#[derive(Default)]
+pub struct MockEmail {
+    sent: Mutex<Vec<SentEmail>>,
+}
+
+impl MockEmail {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Messages sent so far, oldest first
+    pub fn sent(&self) -> Vec<SentEmail> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    fn record(&self, email: SentEmail) -> String {
+        let mut sent = self.sent.lock().unwrap();
+        sent.push(email);
+        format!("mock-message-{}", sent.len())
+    }
+}
+
+#[async_trait]
+impl EmailPort for MockEmail {
+    async fn send_email(&self, message: EmailMessage) -> DomainResult<String> {
+        message.validate()?;
+        Ok(self.record(SentEmail::Message(message)))
+    }
+
+    async fn send_templated_email(&self, email: TemplatedEmail) -> DomainResult<String> {
+        email.validate()?;
+        Ok(self.record(SentEmail::Templated(email)))
+    }
+}
+
+/// Mock implementation of SecretsPort over an in-memory map
+#[derive(Default)]
+pub struct MockSecrets {
+    secrets: Mutex<HashMap<String, String>>,
+    calls: AtomicUsize,
+}
+
+impl MockSecrets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_secret(self, name: &str, value: &str) -> Self {
+        self.set_secret(name, value);
+        self
+    }
+
+    /// Replace a secret's value, as a rotation would
+    pub fn set_secret(&self, name: &str, value: &str) {
+        self.secrets.lock().unwrap().insert(name.to_string(), value.to_string());
+    }
+
+    /// Number of `get_secret` calls so far
+    pub fn call_count(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl SecretsPort for MockSecrets {
+    async fn get_secret(&self, name: &str) -> DomainResult<String> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        self.secrets
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| DomainError::NotFound(format!("Secret '{}' does not exist", name)))
+    }
+}
+
+/// Clock that only moves when told to
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl MockClock {
+    pub fn at(now: DateTime<Utc>) -> Self {
+        Self { now: Mutex::new(now) }
+    }
+
+    pub fn advance(&self, by: chrono::Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::at(DateTime::from_timestamp(1_700_000_000, 0).unwrap_or_default())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn order_item() -> Item {
+        HashMap::from([
+            ("order_id".to_string(), ItemValue::from("1111")),
+            ("segment".to_string(), ItemValue::number(10)),
+            ("name".to_string(), ItemValue::from("test")),
+        ])
+    }
+
+    fn order_key() -> Item {
+        HashMap::from([
+            ("order_id".to_string(), ItemValue::from("1111")),
+            ("segment".to_string(), ItemValue::number(10)),
+        ])
+    }
+
     #[tokio::test]
     async fn test_mock_database() {
-        let mut item = HashMap::new();
-        item.insert("name".to_string(), "test".to_string());
+        let db = MockDatabase::new()
+            .with_table("test-table", &["order_id", "segment"])
+            .with_item("test-table", order_item());
+
+        let result = db.get_item("test-table", order_key()).await.unwrap();
+        assert!(result.is_some());
+        assert_eq!(result.unwrap().get("name").unwrap().as_str(), Some("test"));
+    }
+
+    #[tokio::test]
+    async fn test_mock_database_round_trips_every_type() {
+        let db = MockDatabase::new().with_table("test-table", &["order_id", "segment"]);
+
+        let mut item = order_item();
+        item.insert("total".to_string(), ItemValue::Number("1234567890.123456789012345".to_string()));
+        item.insert("paid".to_string(), ItemValue::Bool(true));
+        item.insert("notes".to_string(), ItemValue::Null);
+        item.insert("receipt".to_string(), ItemValue::Binary(vec![0, 159, 146, 150]));
+        item.insert("tags".to_string(), ItemValue::StringSet(vec!["new".to_string(), "vip".to_string()]));
+        item.insert("tag_history".to_string(), ItemValue::List(vec![ItemValue::from("new"), ItemValue::from("new")]));
+        item.insert("quantities".to_string(), ItemValue::NumberSet(vec!["1".to_string(), "2.50".to_string()]));
+        item.insert("signatures".to_string(), ItemValue::BinarySet(vec![vec![1, 2], vec![3]]));
+        item.insert(
+            "address".to_string(),
+            ItemValue::Map(HashMap::from([
+                ("city".to_string(), ItemValue::from("Madrid")),
+                ("floor".to_string(), ItemValue::number(3)),
+            ])),
+        );
 
+        db.put_item("test-table", item.clone()).await.unwrap();
+        let stored = db.get_item("test-table", order_key()).await.unwrap().unwrap();
+        assert_eq!(stored, item);
+        assert_eq!(stored["total"].as_number::<f64>(), Some(1234567890.1234568));
+
+        // The JSON representation is lossless as well
+        let json = serde_json::to_string(&stored).unwrap();
+        let decoded: Item = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, item);
+    }
+
+    #[tokio::test]
+    async fn test_mock_database_put_replaces_item() {
         let db = MockDatabase::new()
-            .with_item("test-table", "test-key", item.clone());
+            .with_table("test-table", &["order_id", "segment"])
+            .with_item("test-table", order_item());
 
-        let mut key = HashMap::new();
-        key.insert("id".to_string(), "test-key".to_string());
+        let mut updated = order_key();
+        updated.insert("name".to_string(), ItemValue::from("updated"));
+        db.put_item("test-table", updated).await.unwrap();
 
-        let result = db.get_item("test-table", key).await.unwrap();
-        assert!(result.is_some());
-        assert_eq!(result.unwrap().get("name").unwrap(), "test");
+        let result = db.get_item("test-table", order_key()).await.unwrap().unwrap();
+        assert_eq!(result["name"].as_str(), Some("updated"));
+    }
+
+    #[tokio::test]
+    async fn test_mock_database_put_requires_key_attributes() {
+        let db = MockDatabase::new().with_table("test-table", &["order_id", "segment"]);
+        let mut item = order_item();
+        item.remove("segment");
+
+        let result = db.put_item("test-table", item).await;
+        assert!(matches!(result, Err(DomainError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_mock_conditional_put() {
+        let db = MockDatabase::new()
+            .with_table("test-table", &["order_id", "segment"])
+            .with_item("test-table", order_item());
+
+        let create_only = PutOptions::new().condition(Condition::attribute_not_exists("order_id"));
+        let result = db.put_item_with("test-table", order_item(), create_only.clone()).await;
+        assert!(matches!(result, Err(DomainError::ConditionFailed(_))));
+
+        let matching = PutOptions::new().condition(Condition::attribute_equals("name", "test"));
+        assert!(db.put_item_with("test-table", order_item(), matching).await.is_ok());
+
+        let mut other = order_item();
+        other.insert("segment".to_string(), ItemValue::number(20));
+        assert!(db.put_item_with("test-table", other, create_only).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_mock_optimistic_locking_rejects_lost_update() {
+        let db = MockDatabase::new().with_table("test-table", &["order_id", "segment"]);
+        let options = PutOptions::new().versioned("version");
+
+        let created = db.put_item_with("test-table", order_item(), options.clone()).await.unwrap();
+        assert_eq!(created["version"], ItemValue::number(1));
+
+        // Two writers read version 1; only the first update wins
+        let mut first = created.clone();
+        first.insert("name".to_string(), ItemValue::from("first"));
+        let mut second = created.clone();
+        second.insert("name".to_string(), ItemValue::from("second"));
+
+        let written = db.put_item_with("test-table", first, options.clone()).await.unwrap();
+        assert_eq!(written["version"], ItemValue::number(2));
+        let result = db.put_item_with("test-table", second, options.clone()).await;
+        assert!(matches!(result, Err(DomainError::ConditionFailed(_))));
+
+        // Creating again without a version fails because the item exists
+        let result = db.put_item_with("test-table", order_item(), options).await;
+        assert!(matches!(result, Err(DomainError::ConditionFailed(_))));
+
+        let stored = db.get_item("test-table", order_key()).await.unwrap().unwrap();
+        assert_eq!(stored["name"].as_str(), Some("first"));
+    }
+
+    #[tokio::test]
+    async fn test_mock_update_item() {
+        let db = MockDatabase::new()
+            .with_table("test-table", &["order_id", "segment"])
+            .with_item("test-table", order_item());
+
+        let request = UpdateRequest::new(order_key())
+            .set("status", "shipped")
+            .remove("name")
+            .increment("views", 1)
+            .add("tags", ItemValue::StringSet(vec!["vip".to_string()]))
+            .returning(ReturnValues::AllNew);
+        let new = db.update_item("test-table", request).await.unwrap().unwrap();
+        assert_eq!(new["status"].as_str(), Some("shipped"));
+        assert!(!new.contains_key("name"));
+        assert_eq!(new["views"], ItemValue::number(1));
+
+        let request = UpdateRequest::new(order_key())
+            .increment("views", 41)
+            .add("tags", ItemValue::StringSet(vec!["vip".to_string(), "new".to_string()]))
+            .returning(ReturnValues::UpdatedOld);
+        let old = db.update_item("test-table", request).await.unwrap().unwrap();
+        assert_eq!(old.len(), 2);
+        assert_eq!(old["views"], ItemValue::number(1));
+
+        let stored = db.get_item("test-table", order_key()).await.unwrap().unwrap();
+        assert_eq!(stored["views"], ItemValue::number(42));
+        assert_eq!(stored["tags"], ItemValue::StringSet(vec!["vip".to_string(), "new".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn test_mock_update_item_version_and_key_checks() {
+        let db = MockDatabase::new().with_table("test-table", &["order_id", "segment"]);
+
+        // Updating a missing item creates it
+        let request = UpdateRequest::new(order_key()).set("version", ItemValue::number(1));
+        assert_eq!(db.update_item("test-table", request).await.unwrap(), None);
+
+        let stale = UpdateRequest::new(order_key()).set("name", "x").expect_version("version", 7);
+        let result = db.update_item("test-table", stale).await;
+        assert!(matches!(result, Err(DomainError::ConditionFailed(_))));
+
+        let current = UpdateRequest::new(order_key())
+            .set("name", "x")
+            .expect_version("version", 1)
+            .returning(ReturnValues::UpdatedNew);
+        let updated = db.update_item("test-table", current).await.unwrap().unwrap();
+        assert_eq!(updated["version"], ItemValue::number(2));
+
+        let key_change = UpdateRequest::new(order_key()).set("segment", ItemValue::number(11));
+        let result = db.update_item("test-table", key_change).await;
+        assert!(matches!(result, Err(DomainError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_increment_counter_sets_expiry_once() {
+        let db = MockDatabase::new().with_table("test-table", &["order_id", "segment"]);
+
+        assert_eq!(db.increment_counter("test-table", order_key(), "hits", 1, Some(("ttl", 100))).await.unwrap(), 1);
+        assert_eq!(db.increment_counter("test-table", order_key(), "hits", 2, Some(("ttl", 200))).await.unwrap(), 3);
+
+        let stored = db.get_item("test-table", order_key()).await.unwrap().unwrap();
+        assert_eq!(stored["ttl"], ItemValue::number(100));
+    }
+
+    #[tokio::test]
+    async fn test_mock_delete_item() {
+        let db = MockDatabase::new()
+            .with_table("test-table", &["order_id", "segment"])
+            .with_item("test-table", order_item());
+
+        let guarded = vec![Condition::attribute_equals("name", "other")];
+        let result = db.delete_item("test-table", order_key(), guarded).await;
+        assert!(matches!(result, Err(DomainError::ConditionFailed(_))));
+
+        let deleted = db.delete_item("test-table", order_key(), Vec::new()).await.unwrap();
+        assert_eq!(deleted, Some(order_item()));
+        assert_eq!(db.delete_item("test-table", order_key(), Vec::new()).await.unwrap(), None);
+        assert!(db.get_item("test-table", order_key()).await.unwrap().is_none());
+    }
+
+    fn order(segment: i64) -> Item {
+        HashMap::from([
+            ("order_id".to_string(), ItemValue::from("1111")),
+            ("segment".to_string(), ItemValue::number(segment)),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_mock_batch_write_and_get() {
+        let db = MockDatabase::new().with_table("test-table", &["order_id", "segment"]);
+
+        let puts = (1..=30).map(|segment| WriteRequest::Put(order(segment))).collect();
+        db.batch_write("test-table", puts).await.unwrap();
+        db.batch_write("test-table", vec![WriteRequest::Delete(order(30))]).await.unwrap();
+
+        let keys = (1..=40).map(order).collect();
+        let found = db.batch_get("test-table", keys).await.unwrap();
+        assert_eq!(found.len(), 29);
+    }
+
+    #[tokio::test]
+    async fn test_mock_transaction_is_all_or_nothing() {
+        let db = MockDatabase::new()
+            .with_table("test-table", &["order_id", "segment"])
+            .with_item("test-table", order_item());
+
+        let transaction = vec![
+            TransactItem::put("test-table", order(20)),
+            TransactItem::update("test-table", UpdateRequest::new(order_key()).increment("views", 1)),
+            TransactItem::put("test-table", order(10)).condition(Condition::attribute_not_exists("order_id")),
+            TransactItem::condition_check("test-table", order(99), Condition::attribute_exists("order_id")),
+        ];
+        match db.transact_write(transaction).await {
+            Err(DomainError::TransactionCanceled { reasons, .. }) => {
+                let indexes: Vec<usize> = reasons.iter().map(|r| r.index).collect();
+                assert_eq!(indexes, vec![2, 3]);
+                assert!(reasons.iter().all(|r| r.code == "ConditionalCheckFailed"));
+            }
+            other => panic!("expected a canceled transaction, got {:?}", other),
+        }
+
+        // Nothing from the canceled transaction was written
+        assert!(db.get_item("test-table", order(20)).await.unwrap().is_none());
+        assert!(!db.get_item("test-table", order_key()).await.unwrap().unwrap().contains_key("views"));
+
+        let transaction = vec![
+            TransactItem::put("test-table", order(20)),
+            TransactItem::delete("test-table", order_key()).condition(Condition::attribute_equals("name", "test")),
+        ];
+        db.transact_write(transaction).await.unwrap();
+        assert!(db.get_item("test-table", order(20)).await.unwrap().is_some());
+        assert!(db.get_item("test-table", order_key()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_mock_transaction_rejects_duplicate_items() {
+        let db = MockDatabase::new().with_table("test-table", &["order_id", "segment"]);
+        let transaction = vec![
+            TransactItem::put("test-table", order(1)),
+            TransactItem::delete("test-table", order(1)),
+        ];
+        let result = db.transact_write(transaction).await;
+        assert!(matches!(result, Err(DomainError::Validation(_))));
+    }
+
+    fn orders_db() -> MockDatabase {
+        let mut db = MockDatabase::new().with_table("orders", &["order_id", "segment"]);
+        for (order_id, segment, status) in [
+            ("1111", 10, "open"),
+            ("1111", 20, "closed"),
+            ("1111", 30, "open"),
+            ("1111", 40, "open"),
+            ("2222", 10, "open"),
+        ] {
+            db = db.with_item(
+                "orders",
+                HashMap::from([
+                    ("order_id".to_string(), ItemValue::from(order_id)),
+                    ("segment".to_string(), ItemValue::number(segment)),
+                    ("status".to_string(), ItemValue::from(status)),
+                ]),
+            );
+        }
+        db
+    }
+
+    fn segments(page: &Page) -> Vec<i64> {
+        page.items.iter().map(|item| item["segment"].as_number().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_mock_query_paginates_partition() {
+        let db = orders_db();
+
+        let first = db.query("orders", QueryRequest::new("order_id", "1111").limit(3)).await.unwrap();
+        assert_eq!(segments(&first), vec![10, 20, 30]);
+        assert!(first.next_token.is_some());
+
+        let second = db
+            .query("orders", QueryRequest::new("order_id", "1111").limit(3).starting_after(first.next_token))
+            .await
+            .unwrap();
+        assert_eq!(segments(&second), vec![40]);
+        assert!(second.next_token.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_mock_query_sort_key_filter_and_order() {
+        let db = orders_db();
+
+        let request = QueryRequest::new("order_id", "1111")
+            .sort_key("segment", SortKeyCondition::Between(ItemValue::number(15), ItemValue::number(40)))
+            .filter(Filter::new("status", FilterOp::Eq(ItemValue::from("open"))))
+            .descending();
+        let page = db.query("orders", request).await.unwrap();
+        assert_eq!(segments(&page), vec![40, 30]);
+    }
+
+    #[tokio::test]
+    async fn test_mock_parallel_scan_covers_every_item_once() {
+        let db = orders_db();
+
+        let mut seen = Vec::new();
+        for segment in 0..3 {
+            let mut next_token = None;
+            loop {
+                let request = ScanRequest::new().segment(segment, 3).limit(1).starting_after(next_token);
+                let page = db.scan("orders", request).await.unwrap();
+                seen.extend(page.items.into_iter().map(|item| MockDatabase::storage_key("orders", &item)));
+                next_token = page.next_token;
+                if next_token.is_none() {
+                    break;
+                }
+            }
+        }
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), 5);
     }
 
     #[tokio::test]
@@ -99,10 +1369,129 @@ mod tests {
         assert_eq!(result, data);
     }
 
+    #[tokio::test]
+    async fn test_mock_storage_streams_large_object_with_bounded_memory() {
+        const SIZE: u64 = 300 * 1024 * 1024;
+        let storage = MockStorage::new().with_generated_object("test-bucket", "large.bin", SIZE);
+
+        // The generated source and the consuming side each hold one 64 KiB buffer,
+        // so the 300 MiB body is never materialized
+        let reader = storage.get_object_stream("test-bucket", "large.bin").await.unwrap();
+        storage.put_object_stream("test-bucket", "copy.bin", reader).await.unwrap();
+
+        let copied = storage.streamed_object("test-bucket", "copy.bin").unwrap();
+        let mut original = storage.get_object_stream("test-bucket", "large.bin").await.unwrap();
+        assert_eq!(copied.size, SIZE);
+        assert_eq!(copied, stream_checksum(&mut original).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_mock_storage_streams_small_object() {
+        let storage = MockStorage::new().with_object("test-bucket", "test-key", b"test data".to_vec());
+        let mut reader = storage.get_object_stream("test-bucket", "test-key").await.unwrap();
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await.unwrap();
+        assert_eq!(data, b"test data");
+
+        let result = storage.get_object_stream("test-bucket", "missing-key").await;
+        assert!(matches!(result, Err(DomainError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_mock_storage_head_and_delete() {
+        let storage = MockStorage::new()
+            .with_object("test-bucket", "test-key", b"test data".to_vec())
+            .with_object_metadata("test-bucket", "test-key", "text/plain", &[("owner", "orders")]);
+
+        let metadata = storage.head_object("test-bucket", "test-key").await.unwrap().unwrap();
+        assert_eq!(metadata.size, 9);
+        assert_eq!(metadata.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(metadata.metadata["owner"], "orders");
+        assert!(metadata.etag.is_some());
+
+        storage.delete_object("test-bucket", "test-key").await.unwrap();
+        assert!(storage.head_object("test-bucket", "test-key").await.unwrap().is_none());
+        // Deleting again succeeds, as in S3
+        storage.delete_object("test-bucket", "test-key").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mock_storage_list_with_prefix_and_delimiter() {
+        let mut storage = MockStorage::new();
+        for key in ["photos/2024/a.jpg", "photos/2024/b.jpg", "photos/2025/c.jpg", "photos/index.html", "readme.txt"] {
+            storage = storage.with_object("test-bucket", key, key.as_bytes().to_vec());
+        }
+
+        let request = ListObjectsRequest::new().prefix("photos/").delimiter("/");
+        let listing = storage.list_objects("test-bucket", request).await.unwrap();
+        assert_eq!(listing.common_prefixes, vec!["photos/2024/", "photos/2025/"]);
+        let keys: Vec<&str> = listing.objects.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, vec!["photos/index.html"]);
+
+        let first = storage.list_objects("test-bucket", ListObjectsRequest::new().max_keys(3)).await.unwrap();
+        assert_eq!(first.objects.len(), 3);
+        let second = storage
+            .list_objects("test-bucket", ListObjectsRequest::new().max_keys(3).starting_after(first.next_token))
+            .await
+            .unwrap();
+        let keys: Vec<&str> = second.objects.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, vec!["photos/index.html", "readme.txt"]);
+        assert!(second.next_token.is_none());
+
+        let failures = storage
+            .delete_objects("test-bucket", vec!["readme.txt".to_string(), "photos/2025/c.jpg".to_string()])
+            .await
+            .unwrap();
+        assert!(failures.is_empty());
+        let remaining = storage.list_objects("test-bucket", ListObjectsRequest::new()).await.unwrap();
+        assert_eq!(remaining.objects.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_mock_storage_list_with_zero_max_keys() {
+        let storage = MockStorage::new().with_object("test-bucket", "readme.txt", b"hi".to_vec());
+        let listing = storage.list_objects("test-bucket", ListObjectsRequest::new().max_keys(0)).await.unwrap();
+        assert_eq!(listing, ObjectListing::default());
+    }
+
+    #[tokio::test]
+    async fn test_mock_storage_presigns_deterministic_urls() {
+        let storage = MockStorage::new();
+        let request = PresignRequest::put(900).content_type("image/png").content_length(1024);
+        let upload = storage.presign("test-bucket", "photos/a.png", request.clone()).await.unwrap();
+        assert_eq!(upload.url, "https://test-bucket.mock-s3.local/photos/a.png?method=PUT&expires_in=900");
+        assert_eq!(upload.headers["content-type"], "image/png");
+        assert_eq!(upload.headers["content-length"], "1024");
+        assert_eq!(storage.presign("test-bucket", "photos/a.png", request).await.unwrap(), upload);
+
+        let download = storage.presign("test-bucket", "photos/a.png", PresignRequest::get(60)).await.unwrap();
+        assert_eq!(download.method, PresignMethod::Get);
+        assert!(download.headers.is_empty());
+
+        let result = storage.presign("test-bucket", "photos/a.png", PresignRequest::get(0)).await;
+        assert!(matches!(result, Err(DomainError::Validation(_))));
+    }
+
     #[tokio::test]
     async fn test_mock_storage_not_found() {
         let storage = MockStorage::new();
         let result = storage.get_object("test-bucket", "missing-key").await;
-        assert!(result.is_err());
+        assert!(matches!(result, Err(DomainError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_mock_email_records_messages() {
+        let email = MockEmail::new();
+        let message = EmailMessage::new("shop@example.com", "Your order").to("buyer@example.com").text("Thanks!");
+        assert_eq!(email.send_email(message.clone()).await.unwrap(), "mock-message-1");
+
+        let templated = TemplatedEmail::new("shop@example.com", "OrderShipped", serde_json::json!({"carrier": "UPS"}))
+            .to("buyer@example.com");
+        assert_eq!(email.send_templated_email(templated.clone()).await.unwrap(), "mock-message-2");
+
+        let invalid = EmailMessage::new("shop@example.com", "No body").to("buyer@example.com");
+        assert!(matches!(email.send_email(invalid).await, Err(DomainError::Validation(_))));
+
+        assert_eq!(email.sent(), vec![SentEmail::Message(message), SentEmail::Templated(templated)]);
     }
 }
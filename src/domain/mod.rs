@@ -1,5 +1,21 @@
+pub mod api_key;
+pub mod auth;
+pub mod batch;
+pub mod clock;
+pub mod condition;
+pub mod email;
+pub mod errors;
+pub mod idempotency;
+pub mod item;
 pub mod models;
+pub mod object;
 pub mod ports;
+pub mod query;
+pub mod rate_limit;
+pub mod request;
+pub mod secret;
+pub mod update;
+pub mod validation;
 
 #[cfg(test)]
 pub mod mocks;
@@ -1,6 +1,10 @@
+use super::validation::{Validate, Validator};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Longest `message` accepted in a request body
+pub const MAX_MESSAGE_LENGTH: usize = 4096;
+
 /// Request payload structure
 #[derive(Deserialize, Debug, Clone)]
 pub struct RequestPayload {
@@ -8,11 +12,49 @@ pub struct RequestPayload {
     pub data: Option<HashMap<String, serde_json::Value>>,
 }
 
+impl Validate for RequestPayload {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(message) = &self.message {
+            v.length("message", message, 0, MAX_MESSAGE_LENGTH);
+        }
+    }
+}
+
 /// Response payload structure
 #[derive(Serialize, Debug, Clone)]
 pub struct ResponsePayload {
     pub status: String,
     pub message: String,
     pub data: Option<HashMap<String, serde_json::Value>>,
+    /// Machine-readable error code, only present on error responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    /// Continuation token for paginated results, only present while more pages remain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_token: Option<String>,
     pub timestamp: String,
 }
+
+impl ResponsePayload {
+    /// Successful response with a message and no data
+    pub fn success(message: impl Into<String>) -> Self {
+        Self {
+            status: "success".to_string(),
+            message: message.into(),
+            data: None,
+            error_code: None,
+            next_token: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn with_data(mut self, data: HashMap<String, serde_json::Value>) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    pub fn with_next_token(mut self, next_token: Option<String>) -> Self {
+        self.next_token = next_token;
+        self
+    }
+}
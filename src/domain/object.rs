@@ -0,0 +1,184 @@
+use super::errors::{DomainError, DomainResult};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Most keys S3 deletes in one `DeleteObjects` call
+pub const DELETE_OBJECTS_LIMIT: usize = 1000;
+
+/// Object metadata returned by a HEAD request
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ObjectMetadata {
+    pub key: String,
+    pub size: u64,
+    pub etag: Option<String>,
+    pub content_type: Option<String>,
+    pub last_modified: Option<DateTime<Utc>>,
+    /// User-defined `x-amz-meta-*` metadata, keyed without the prefix
+    pub metadata: HashMap<String, String>,
+}
+
+/// Options for writing an object
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PutObjectOptions {
+    /// Stored with the object and returned as its `Content-Type` on download
+    pub content_type: Option<String>,
+}
+
+impl PutObjectOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self
+    }
+}
+
+/// One object of a listing
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ObjectSummary {
+    pub key: String,
+    pub size: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// List objects, optionally grouped into "directories" by a delimiter
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ListObjectsRequest {
+    pub prefix: Option<String>,
+    pub delimiter: Option<String>,
+    pub max_keys: Option<u32>,
+    pub next_token: Option<String>,
+}
+
+impl ListObjectsRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.to_string());
+        self
+    }
+
+    pub fn delimiter(mut self, delimiter: &str) -> Self {
+        self.delimiter = Some(delimiter.to_string());
+        self
+    }
+
+    pub fn max_keys(mut self, max_keys: u32) -> Self {
+        self.max_keys = Some(max_keys);
+        self
+    }
+
+    pub fn starting_after(mut self, next_token: Option<String>) -> Self {
+        self.next_token = next_token;
+        self
+    }
+}
+
+/// A page of a listing; `next_token` is present while more keys remain
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ObjectListing {
+    pub objects: Vec<ObjectSummary>,
+    /// Key prefixes rolled up by the delimiter, e.g. `photos/2024/`
+    pub common_prefixes: Vec<String>,
+    pub next_token: Option<String>,
+}
+
+/// A key `delete_objects` could not delete
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeleteFailure {
+    pub key: String,
+    pub code: String,
+    pub message: Option<String>,
+}
+
+/// HTTP method a presigned URL is valid for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PresignMethod {
+    /// Download the object
+    Get,
+    /// Upload the object
+    Put,
+}
+
+/// Longest expiry S3 accepts for a presigned URL
+pub const MAX_PRESIGN_EXPIRY_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// What a presigned URL allows its holder to do
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresignRequest {
+    pub method: PresignMethod,
+    pub expires_in_secs: u64,
+    /// Content type the upload must declare; signed into PUT URLs
+    pub content_type: Option<String>,
+    /// Exact body size the upload must declare; signed into PUT URLs
+    pub content_length: Option<u64>,
+}
+
+impl PresignRequest {
+    pub fn get(expires_in_secs: u64) -> Self {
+        Self {
+            method: PresignMethod::Get,
+            expires_in_secs,
+            content_type: None,
+            content_length: None,
+        }
+    }
+
+    pub fn put(expires_in_secs: u64) -> Self {
+        Self {
+            method: PresignMethod::Put,
+            ..Self::get(expires_in_secs)
+        }
+    }
+
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self
+    }
+
+    pub fn content_length(mut self, content_length: u64) -> Self {
+        self.content_length = Some(content_length);
+        self
+    }
+
+    /// Reject expiries S3 would refuse to sign
+    pub fn validate(&self) -> DomainResult<()> {
+        if self.expires_in_secs == 0 || self.expires_in_secs > MAX_PRESIGN_EXPIRY_SECS {
+            return Err(DomainError::Validation(format!(
+                "expires_in must be between 1 and {} seconds",
+                MAX_PRESIGN_EXPIRY_SECS
+            )));
+        }
+        Ok(())
+    }
+
+    /// Headers the client must send for the signature to match
+    pub fn signed_headers(&self) -> HashMap<String, String> {
+        let mut headers = HashMap::new();
+        if self.method == PresignMethod::Put {
+            if let Some(content_type) = &self.content_type {
+                headers.insert("content-type".to_string(), content_type.clone());
+            }
+            if let Some(content_length) = self.content_length {
+                headers.insert("content-length".to_string(), content_length.to_string());
+            }
+        }
+        headers
+    }
+}
+
+/// A presigned URL and the headers the client must send with it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PresignedUrl {
+    pub url: String,
+    pub method: PresignMethod,
+    pub headers: HashMap<String, String>,
+    pub expires_in: u64,
+}
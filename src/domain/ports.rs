@@ -1,17 +1,110 @@
 use async_trait::async_trait;
-use std::collections::HashMap;
-use std::error::Error;
+use std::pin::Pin;
+use tokio::io::AsyncRead;
+use super::batch::{TransactItem, WriteRequest};
+use super::condition::{Condition, PutOptions};
+use super::email::{EmailMessage, TemplatedEmail};
+use super::errors::{DomainError, DomainResult};
+use super::item::{Item, ItemValue};
+use super::object::{
+    DeleteFailure, ListObjectsRequest, ObjectListing, ObjectMetadata, PresignRequest, PresignedUrl, PutObjectOptions,
+};
+use super::query::{Page, QueryRequest, ScanRequest};
+use super::update::{ReturnValues, UpdateRequest};
 
 /// Port for database operations
 #[async_trait]
 pub trait DatabasePort: Send + Sync {
-    async fn get_item(&self, table_name: &str, key: HashMap<String, String>) -> Result<Option<HashMap<String, String>>, Box<dyn Error + Send + Sync>>;
-    async fn put_item(&self, table_name: &str, item: HashMap<String, String>) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn get_item(&self, table_name: &str, key: Item) -> DomainResult<Option<Item>>;
+    async fn put_item(&self, table_name: &str, item: Item) -> DomainResult<()> {
+        self.put_item_with(table_name, item, PutOptions::default()).await.map(|_| ())
+    }
+    /// Write an item if its conditions hold, returning the item as written.
+    ///
+    /// A failed condition (including a stale version) is `DomainError::ConditionFailed`.
+    async fn put_item_with(&self, table_name: &str, item: Item, options: PutOptions) -> DomainResult<Item>;
+    /// Partially update an item (creating it if missing), returning the image chosen by `return_values`
+    async fn update_item(&self, table_name: &str, request: UpdateRequest) -> DomainResult<Option<Item>>;
+    /// Atomically add `by` to a number attribute and return the new total.
+    ///
+    /// A missing item or attribute counts from zero. `expires_at` (an attribute
+    /// name and epoch seconds) is only written when the item is created, so
+    /// DynamoDB TTL can remove the counter later.
+    async fn increment_counter(
+        &self,
+        table_name: &str,
+        key: Item,
+        attribute: &str,
+        by: i64,
+        expires_at: Option<(&str, i64)>,
+    ) -> DomainResult<i64> {
+        let mut request = UpdateRequest::new(key)
+            .increment(attribute, by)
+            .returning(ReturnValues::UpdatedNew);
+        if let Some((ttl_attribute, epoch_secs)) = expires_at {
+            request = request.set_if_not_exists(ttl_attribute, epoch_secs);
+        }
+        self.update_item(table_name, request)
+            .await?
+            .as_ref()
+            .and_then(|item| item.get(attribute))
+            .and_then(ItemValue::as_number::<i64>)
+            .ok_or_else(|| DomainError::Internal(format!("Counter '{}' did not come back as a number", attribute)))
+    }
+    /// Delete an item if every condition holds, returning the deleted item if there was one
+    async fn delete_item(&self, table_name: &str, key: Item, conditions: Vec<Condition>) -> DomainResult<Option<Item>>;
+    /// Read many items by key; missing keys are skipped and order is not preserved
+    async fn batch_get(&self, table_name: &str, keys: Vec<Item>) -> DomainResult<Vec<Item>>;
+    /// Apply many unconditional puts and deletes; each write succeeds or fails on its own
+    async fn batch_write(&self, table_name: &str, requests: Vec<WriteRequest>) -> DomainResult<()>;
+    /// Apply writes all-or-nothing; a failure is `TransactionCanceled` with the reason per item
+    async fn transact_write(&self, items: Vec<TransactItem>) -> DomainResult<()>;
+    /// Read one page of items sharing a partition key
+    async fn query(&self, table_name: &str, request: QueryRequest) -> DomainResult<Page>;
+    /// Read one page of a table scan (or of one scan segment)
+    async fn scan(&self, table_name: &str, request: ScanRequest) -> DomainResult<Page>;
 }
 
+/// Async byte source for streamed object bodies
+pub type ByteReader = Pin<Box<dyn AsyncRead + Send>>;
+
 /// Port for storage operations
 #[async_trait]
 pub trait StoragePort: Send + Sync {
-    async fn get_object(&self, bucket: &str, key: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
-    async fn put_object(&self, bucket: &str, key: &str, body: Vec<u8>) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn get_object(&self, bucket: &str, key: &str) -> DomainResult<Vec<u8>>;
+    async fn put_object(&self, bucket: &str, key: &str, body: Vec<u8>) -> DomainResult<()> {
+        self.put_object_with(bucket, key, body, PutObjectOptions::default()).await
+    }
+    /// Write an object along with its content type
+    async fn put_object_with(&self, bucket: &str, key: &str, body: Vec<u8>, options: PutObjectOptions) -> DomainResult<()>;
+    /// Read an object as a stream, without buffering the whole body
+    async fn get_object_stream(&self, bucket: &str, key: &str) -> DomainResult<ByteReader>;
+    /// Write an object from a stream, buffering at most one upload part at a time
+    async fn put_object_stream(&self, bucket: &str, key: &str, body: ByteReader) -> DomainResult<()>;
+    /// Object metadata without its body, or `None` if the object does not exist
+    async fn head_object(&self, bucket: &str, key: &str) -> DomainResult<Option<ObjectMetadata>>;
+    /// Delete an object; deleting a missing object succeeds
+    async fn delete_object(&self, bucket: &str, key: &str) -> DomainResult<()>;
+    /// Delete many objects, returning the keys that could not be deleted
+    async fn delete_objects(&self, bucket: &str, keys: Vec<String>) -> DomainResult<Vec<DeleteFailure>>;
+    /// List one page of objects
+    async fn list_objects(&self, bucket: &str, request: ListObjectsRequest) -> DomainResult<ObjectListing>;
+    /// URL that lets a client download or upload an object directly, without going through this function
+    async fn presign(&self, bucket: &str, key: &str, request: PresignRequest) -> DomainResult<PresignedUrl>;
+}
+
+/// Port for sending email
+#[async_trait]
+pub trait EmailPort: Send + Sync {
+    /// Send a message, returning the provider's message id
+    async fn send_email(&self, message: EmailMessage) -> DomainResult<String>;
+    /// Send a message rendered from a stored template, returning the provider's message id
+    async fn send_templated_email(&self, email: TemplatedEmail) -> DomainResult<String>;
+}
+
+/// Port for reading secrets such as API keys and signing keys
+#[async_trait]
+pub trait SecretsPort: Send + Sync {
+    /// Current value of the secret called `name`; a missing secret is `NotFound`
+    async fn get_secret(&self, name: &str) -> DomainResult<String>;
 }
@@ -0,0 +1,201 @@
+use super::errors::{DomainError, DomainResult};
+use super::item::{Item, ItemValue};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+/// Condition on the sort key of a query
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortKeyCondition {
+    Eq(ItemValue),
+    Lt(ItemValue),
+    Le(ItemValue),
+    Gt(ItemValue),
+    Ge(ItemValue),
+    Between(ItemValue, ItemValue),
+    BeginsWith(String),
+}
+
+/// Comparison applied by a filter
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterOp {
+    Eq(ItemValue),
+    Ne(ItemValue),
+    Lt(ItemValue),
+    Le(ItemValue),
+    Gt(ItemValue),
+    Ge(ItemValue),
+    Between(ItemValue, ItemValue),
+    BeginsWith(String),
+    Contains(ItemValue),
+    Exists,
+    NotExists,
+}
+
+/// Filter on a non-key attribute, applied after items are read.
+/// Multiple filters are combined with AND.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub attribute: String,
+    pub op: FilterOp,
+}
+
+impl Filter {
+    pub fn new(attribute: &str, op: FilterOp) -> Self {
+        Self {
+            attribute: attribute.to_string(),
+            op,
+        }
+    }
+}
+
+/// Query items sharing a partition key
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryRequest {
+    pub index_name: Option<String>,
+    pub partition_key: (String, ItemValue),
+    pub sort_key: Option<(String, SortKeyCondition)>,
+    pub filters: Vec<Filter>,
+    pub limit: Option<u32>,
+    /// Ascending sort-key order when true (the default), descending otherwise
+    pub scan_forward: bool,
+    pub next_token: Option<String>,
+}
+
+impl QueryRequest {
+    pub fn new(partition_key_name: &str, partition_key_value: impl Into<ItemValue>) -> Self {
+        Self {
+            index_name: None,
+            partition_key: (partition_key_name.to_string(), partition_key_value.into()),
+            sort_key: None,
+            filters: Vec::new(),
+            limit: None,
+            scan_forward: true,
+            next_token: None,
+        }
+    }
+
+    pub fn index(mut self, index_name: &str) -> Self {
+        self.index_name = Some(index_name.to_string());
+        self
+    }
+
+    pub fn sort_key(mut self, name: &str, condition: SortKeyCondition) -> Self {
+        self.sort_key = Some((name.to_string(), condition));
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn descending(mut self) -> Self {
+        self.scan_forward = false;
+        self
+    }
+
+    pub fn starting_after(mut self, next_token: Option<String>) -> Self {
+        self.next_token = next_token;
+        self
+    }
+}
+
+/// One segment of a parallel scan
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScanSegment {
+    pub segment: u32,
+    pub total_segments: u32,
+}
+
+/// Scan a whole table or index, optionally as one segment of a parallel scan
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ScanRequest {
+    pub index_name: Option<String>,
+    pub filters: Vec<Filter>,
+    pub limit: Option<u32>,
+    pub segment: Option<ScanSegment>,
+    pub next_token: Option<String>,
+}
+
+impl ScanRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn index(mut self, index_name: &str) -> Self {
+        self.index_name = Some(index_name.to_string());
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Scan only `segment` out of `total_segments`; run one call per segment concurrently
+    pub fn segment(mut self, segment: u32, total_segments: u32) -> Self {
+        self.segment = Some(ScanSegment {
+            segment,
+            total_segments,
+        });
+        self
+    }
+
+    pub fn starting_after(mut self, next_token: Option<String>) -> Self {
+        self.next_token = next_token;
+        self
+    }
+}
+
+/// A page of results; `next_token` is present while more items remain
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Page {
+    pub items: Vec<Item>,
+    pub next_token: Option<String>,
+}
+
+/// Encode the last evaluated key as an opaque continuation token
+pub fn encode_token(last_key: &Item) -> String {
+    let json = serde_json::to_vec(last_key).unwrap_or_default();
+    URL_SAFE_NO_PAD.encode(json)
+}
+
+/// Decode a continuation token produced by `encode_token`
+pub fn decode_token(token: &str) -> DomainResult<Item> {
+    let invalid = || DomainError::Validation("Invalid next_token".to_string());
+    let json = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid())?;
+    serde_json::from_slice(&json).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_token_round_trip() {
+        let key: Item = HashMap::from([
+            ("order_id".to_string(), ItemValue::from("1111")),
+            ("segment".to_string(), ItemValue::number(10)),
+        ]);
+        let token = encode_token(&key);
+        assert!(!token.contains('='));
+        assert_eq!(decode_token(&token).unwrap(), key);
+    }
+
+    #[test]
+    fn test_invalid_token() {
+        assert!(matches!(decode_token("not a token!"), Err(DomainError::Validation(_))));
+        assert!(matches!(decode_token("e30x"), Err(DomainError::Validation(_))));
+    }
+}
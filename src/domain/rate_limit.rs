@@ -0,0 +1,129 @@
+use super::errors::{DomainError, DomainResult};
+use serde::{Deserialize, Deserializer};
+
+/// Requests a client may make per window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub requests: u64,
+    pub window_secs: u64,
+}
+
+impl RateLimit {
+    pub fn new(requests: u64, window_secs: u64) -> Self {
+        Self { requests, window_secs }
+    }
+
+    /// Parse `<requests>/<seconds>`, e.g. `100/60`
+    pub fn parse(value: &str) -> DomainResult<Self> {
+        let invalid = || DomainError::Internal(format!("Invalid rate limit '{}', expected <requests>/<seconds>", value));
+        let (requests, window_secs) = value.trim().split_once('/').ok_or_else(invalid)?;
+        let requests: u64 = requests.trim().parse().map_err(|_| invalid())?;
+        let window_secs: u64 = window_secs.trim().parse().map_err(|_| invalid())?;
+        if requests == 0 || window_secs == 0 {
+            return Err(invalid());
+        }
+        Ok(Self::new(requests, window_secs))
+    }
+
+    /// Evaluate a request against the sliding window approximation.
+    ///
+    /// `previous` and `current` are the request counts of the previous and
+    /// current fixed windows, the latter including this request, and `elapsed`
+    /// is how far into the current window it arrived. The previous window's
+    /// count is weighted by how much of it still overlaps the sliding window.
+    pub fn evaluate(&self, previous: u64, current: u64, elapsed: u64) -> RateLimitStatus {
+        let window = self.window_secs as f64;
+        let remaining_secs = self.window_secs.saturating_sub(elapsed).max(1);
+        let estimate = previous as f64 * (remaining_secs as f64 / window) + current as f64;
+        let limited = estimate > self.requests as f64;
+
+        RateLimitStatus {
+            limit: self.requests,
+            remaining: (self.requests as f64 - estimate).max(0.0).floor() as u64,
+            reset_after: remaining_secs,
+            retry_after: limited.then(|| self.retry_after(previous, current, elapsed)),
+        }
+    }
+
+    /// Seconds until one more request would fit under the limit
+    fn retry_after(&self, previous: u64, current: u64, elapsed: u64) -> u64 {
+        let window = self.window_secs as f64;
+        let budget = self.requests as f64 - 1.0;
+        let wait = if current as f64 <= budget && previous > 0 {
+            // Wait within this window for the previous window's share to decay
+            let weight = (budget - current as f64) / previous as f64;
+            window * (1.0 - weight) - elapsed as f64
+        } else {
+            // This window is spent: once it ends, its own count has to decay
+            let weight = budget / current.max(1) as f64;
+            (window - elapsed as f64) + window * (1.0 - weight)
+        };
+        (wait.ceil() as u64).max(1)
+    }
+}
+
+/// Deserialized from the `<requests>/<seconds>` form accepted by `parse`
+impl<'de> Deserialize<'de> for RateLimit {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Self::parse(&value).map_err(|e| serde::de::Error::custom(e.message()))
+    }
+}
+
+/// Outcome of a rate limit check, reported in the `X-RateLimit-*` headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Seconds until the current window ends
+    pub reset_after: u64,
+    /// Seconds to wait before retrying; set only when the request is rejected
+    pub retry_after: Option<u64>,
+}
+
+impl RateLimitStatus {
+    pub fn is_limited(&self) -> bool {
+        self.retry_after.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(RateLimit::parse("100/60").unwrap(), RateLimit::new(100, 60));
+        assert_eq!(RateLimit::parse(" 5 / 1 ").unwrap(), RateLimit::new(5, 1));
+        for invalid in ["100", "0/60", "100/0", "a/60", "100/1m"] {
+            assert!(matches!(RateLimit::parse(invalid), Err(DomainError::Internal(_))), "{}", invalid);
+        }
+        assert_eq!(serde_json::from_str::<RateLimit>("\"5/1\"").unwrap(), RateLimit::new(5, 1));
+        assert!(serde_json::from_str::<RateLimit>("\"5\"").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_weights_previous_window() {
+        let limit = RateLimit::new(10, 60);
+
+        let status = limit.evaluate(0, 4, 0);
+        assert_eq!((status.remaining, status.reset_after, status.retry_after), (6, 60, None));
+        assert_eq!(limit.evaluate(0, 10, 30).remaining, 0);
+        assert!(!limit.evaluate(0, 10, 30).is_limited());
+
+        // Half of the previous window still overlaps: 10 * 0.5 + 6 = 11
+        assert!(limit.evaluate(10, 6, 30).is_limited());
+        // A quarter: 10 * 0.25 + 6 = 8.5
+        assert_eq!(limit.evaluate(10, 6, 45).remaining, 1);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let limit = RateLimit::new(10, 60);
+
+        // 10 * (60 - 30 - t) / 60 + 6 + 1 <= 10 once t >= 12
+        assert_eq!(limit.evaluate(10, 6, 30).retry_after, Some(12));
+        // Spent window: 30 s until it ends, then 11 * (60 - t) / 60 + 1 <= 10 once t >= 10.9
+        assert_eq!(limit.evaluate(0, 11, 30).retry_after, Some(41));
+    }
+}
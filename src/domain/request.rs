@@ -0,0 +1,127 @@
+use super::auth::Principal;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Headers left out of `RequestContext::headers`: they carry credentials,
+/// which are verified before the processor runs
+pub const CREDENTIAL_HEADERS: &[&str] = &["authorization", "x-api-key", "cookie", "proxy-authorization"];
+
+/// The AWS service that delivered the request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EventSource {
+    /// API Gateway REST API (payload format 1.0)
+    RestApi,
+    /// API Gateway HTTP API (payload format 2.0)
+    HttpApi,
+    /// Lambda Function URL, which shares the HTTP API payload format
+    FunctionUrl,
+    /// Application Load Balancer target group
+    Alb,
+    /// API Gateway WebSocket API
+    WebSocket,
+    /// No request context, e.g. a locally built request
+    #[default]
+    Unknown,
+}
+
+/// What an API Gateway authorizer established about the caller before the
+/// request reached this function
+#[derive(Debug, Clone, PartialEq)]
+pub enum Authorizer {
+    /// HTTP API JWT authorizer or REST API Cognito user pool authorizer
+    Jwt {
+        claims: Map<String, Value>,
+        scopes: Vec<String>,
+    },
+    /// IAM (SigV4) authorization
+    Iam {
+        user_arn: Option<String>,
+        account_id: Option<String>,
+        caller_id: Option<String>,
+        access_key: Option<String>,
+    },
+    /// Lambda authorizer: the principal id and the context it returned
+    Lambda {
+        principal_id: Option<String>,
+        context: Map<String, Value>,
+    },
+}
+
+/// Everything known about a request besides its parameters and body, passed
+/// to `RequestProcessor` with each request.
+///
+/// Fields an event source does not provide are `None`, e.g. the stage of an
+/// ALB request.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RequestContext {
+    /// API Gateway or Function URL request id, else the Lambda invocation's
+    pub request_id: Option<String>,
+    pub source: EventSource,
+    pub source_ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub stage: Option<String>,
+    /// API Gateway route, e.g. `GET /items/{pk}` or `$default`
+    pub route_key: Option<String>,
+    pub authorizer: Option<Authorizer>,
+    /// Request headers by lowercase name, repeated values joined with `, `;
+    /// `CREDENTIAL_HEADERS` are never included
+    pub headers: HashMap<String, String>,
+    /// The caller verified by this function, when authentication is enabled
+    pub principal: Option<Principal>,
+}
+
+impl RequestContext {
+    /// Header value by case-insensitive name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    /// Who the request is attributed to for rate limits and idempotency keys:
+    /// its API key, else the verified subject, else the source IP
+    pub fn client_key(&self) -> Option<String> {
+        if let Some(principal) = &self.principal {
+            return Some(match principal.claims.get("api_key_id").and_then(Value::as_str) {
+                Some(key_id) => format!("key:{}", key_id),
+                None => format!("sub:{}", principal.subject),
+            });
+        }
+        self.source_ip.as_ref().map(|ip| format!("ip:{}", ip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_lookup_ignores_case() {
+        let request = RequestContext {
+            headers: HashMap::from([("x-correlation-id".to_string(), "abc".to_string())]),
+            ..RequestContext::default()
+        };
+        assert_eq!(request.header("X-Correlation-Id"), Some("abc"));
+        assert_eq!(request.header("accept"), None);
+        assert_eq!(request.source, EventSource::Unknown);
+    }
+
+    #[test]
+    fn test_client_key_prefers_api_key_then_subject() {
+        let mut request = RequestContext {
+            source_ip: Some("203.0.113.7".to_string()),
+            ..RequestContext::default()
+        };
+        assert_eq!(request.client_key().as_deref(), Some("ip:203.0.113.7"));
+
+        request.principal = Some(Principal {
+            subject: "user-1".to_string(),
+            ..Principal::default()
+        });
+        assert_eq!(request.client_key().as_deref(), Some("sub:user-1"));
+
+        let key_owner = request.principal.as_mut().unwrap();
+        key_owner.claims.insert("api_key_id".to_string(), "0f3a9c".into());
+        assert_eq!(request.client_key().as_deref(), Some("key:0f3a9c"));
+
+        assert_eq!(RequestContext::default().client_key(), None);
+    }
+}
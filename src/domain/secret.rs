@@ -0,0 +1,95 @@
+use super::errors::{DomainError, DomainResult};
+use serde_json::Value;
+
+/// Prefix of a configuration value that names a secret instead of holding it
+pub const SECRET_SCHEME: &str = "secret://";
+
+/// A `secret://<name>[#<field>]` reference.
+///
+/// Without a field the whole secret value is used; with one, the secret
+/// must be a JSON object (as Secrets Manager stores key/value secrets) and
+/// the field's value is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretReference {
+    pub name: String,
+    pub field: Option<String>,
+}
+
+impl SecretReference {
+    /// `None` when `value` is not a reference; `Err` when it is a malformed one
+    pub fn parse(value: &str) -> DomainResult<Option<Self>> {
+        let Some(reference) = value.strip_prefix(SECRET_SCHEME) else {
+            return Ok(None);
+        };
+        let (name, field) = match reference.split_once('#') {
+            Some((name, field)) => (name, Some(field)),
+            None => (reference, None),
+        };
+        if name.is_empty() || field == Some("") {
+            return Err(DomainError::Internal(format!(
+                "Invalid secret reference '{}', expected secret://<name>[#<field>]",
+                value
+            )));
+        }
+        Ok(Some(Self {
+            name: name.to_string(),
+            field: field.map(str::to_string),
+        }))
+    }
+
+    /// The part of a secret's value this reference points at
+    pub fn select(&self, secret: &str) -> DomainResult<String> {
+        let Some(field) = &self.field else {
+            return Ok(secret.to_string());
+        };
+        let missing = || DomainError::Internal(format!("Secret '{}' has no field '{}'", self.name, field));
+        let fields: serde_json::Map<String, Value> = serde_json::from_str(secret)
+            .map_err(|_| DomainError::Internal(format!("Secret '{}' is not a JSON object", self.name)))?;
+        match fields.get(field).ok_or_else(missing)? {
+            Value::String(value) => Ok(value.clone()),
+            Value::Null => Err(missing()),
+            // Numbers, booleans and nested documents are used as their JSON text
+            other => Ok(other.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(SecretReference::parse("orders-table").unwrap(), None);
+        assert_eq!(
+            SecretReference::parse("secret://prod/payments#api_key").unwrap(),
+            Some(SecretReference {
+                name: "prod/payments".to_string(),
+                field: Some("api_key".to_string()),
+            })
+        );
+        let parameter = SecretReference::parse("secret:///orders/jwks").unwrap().unwrap();
+        assert_eq!((parameter.name.as_str(), parameter.field), ("/orders/jwks", None));
+
+        for invalid in ["secret://", "secret://#field", "secret://name#"] {
+            assert!(SecretReference::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_select_field() {
+        let secret = r#"{"api_key": "sk_live_1", "port": 5432, "unset": null}"#;
+        let reference = |field: &str| SecretReference {
+            name: "db".to_string(),
+            field: Some(field.to_string()),
+        };
+        assert_eq!(reference("api_key").select(secret).unwrap(), "sk_live_1");
+        assert_eq!(reference("port").select(secret).unwrap(), "5432");
+        assert!(reference("unset").select(secret).is_err());
+        assert!(reference("missing").select(secret).is_err());
+        assert!(reference("api_key").select("plain text").is_err());
+
+        let whole = SecretReference { name: "db".to_string(), field: None };
+        assert_eq!(whole.select("plain text").unwrap(), "plain text");
+    }
+}
@@ -0,0 +1,110 @@
+use super::condition::Condition;
+use super::item::{Item, ItemValue};
+
+/// One clause of an update expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateAction {
+    /// Replace an attribute (`SET a = :v`)
+    Set(String, ItemValue),
+    /// Set an attribute only if it is missing (`SET a = if_not_exists(a, :v)`)
+    SetIfNotExists(String, ItemValue),
+    /// Remove an attribute (`REMOVE a`)
+    Remove(String),
+    /// Add to a number, or union into a set (`ADD a :v`); a missing attribute starts from the value
+    Add(String, ItemValue),
+    /// Remove elements from a set (`DELETE a :v`)
+    DeleteFromSet(String, ItemValue),
+}
+
+impl UpdateAction {
+    pub fn attribute(&self) -> &str {
+        match self {
+            UpdateAction::Set(a, _)
+            | UpdateAction::SetIfNotExists(a, _)
+            | UpdateAction::Remove(a)
+            | UpdateAction::Add(a, _)
+            | UpdateAction::DeleteFromSet(a, _) => a,
+        }
+    }
+}
+
+/// Which item image an update or delete returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReturnValues {
+    #[default]
+    None,
+    /// The whole item before the write
+    AllOld,
+    /// Only the updated attributes, before the write
+    UpdatedOld,
+    /// The whole item after the write
+    AllNew,
+    /// Only the updated attributes, after the write
+    UpdatedNew,
+}
+
+/// Partial update of one item; the item is created if it does not exist
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateRequest {
+    pub key: Item,
+    pub actions: Vec<UpdateAction>,
+    pub conditions: Vec<Condition>,
+    pub return_values: ReturnValues,
+}
+
+impl UpdateRequest {
+    pub fn new(key: Item) -> Self {
+        Self {
+            key,
+            actions: Vec::new(),
+            conditions: Vec::new(),
+            return_values: ReturnValues::None,
+        }
+    }
+
+    pub fn set(mut self, attribute: &str, value: impl Into<ItemValue>) -> Self {
+        self.actions.push(UpdateAction::Set(attribute.to_string(), value.into()));
+        self
+    }
+
+    pub fn set_if_not_exists(mut self, attribute: &str, value: impl Into<ItemValue>) -> Self {
+        self.actions.push(UpdateAction::SetIfNotExists(attribute.to_string(), value.into()));
+        self
+    }
+
+    pub fn remove(mut self, attribute: &str) -> Self {
+        self.actions.push(UpdateAction::Remove(attribute.to_string()));
+        self
+    }
+
+    pub fn add(mut self, attribute: &str, value: impl Into<ItemValue>) -> Self {
+        self.actions.push(UpdateAction::Add(attribute.to_string(), value.into()));
+        self
+    }
+
+    pub fn delete_from_set(mut self, attribute: &str, value: impl Into<ItemValue>) -> Self {
+        self.actions.push(UpdateAction::DeleteFromSet(attribute.to_string(), value.into()));
+        self
+    }
+
+    /// Atomically add `by` (possibly negative) to a numeric counter
+    pub fn increment(self, attribute: &str, by: i64) -> Self {
+        self.add(attribute, ItemValue::number(by))
+    }
+
+    pub fn condition(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Optimistic locking: only apply if the stored version is `expected`, and bump it
+    pub fn expect_version(self, attribute: &str, expected: u64) -> Self {
+        self.condition(Condition::version_equals(attribute, expected))
+            .increment(attribute, 1)
+    }
+
+    pub fn returning(mut self, return_values: ReturnValues) -> Self {
+        self.return_values = return_values;
+        self
+    }
+}
@@ -0,0 +1,326 @@
+use super::errors::{DomainError, DomainResult, FieldError};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::fmt::Display;
+
+/// Field rules of a request model.
+///
+/// Implementations record every rule they break instead of stopping at the
+/// first, so a client can fix all of its mistakes in one round trip.
+pub trait Validate {
+    fn validate(&self, v: &mut Validator);
+
+    /// Check the rules, failing with `InvalidInput` listing every violation
+    fn validated(self) -> DomainResult<Self>
+    where
+        Self: Sized,
+    {
+        let mut v = Validator::new();
+        self.validate(&mut v);
+        v.finish()?;
+        Ok(self)
+    }
+}
+
+/// Collects rule violations, each under its field path.
+///
+/// Rule methods return whether the value passed (or the converted value), so
+/// dependent checks can be skipped once a field is known to be invalid.
+#[derive(Debug, Default)]
+pub struct Validator {
+    prefix: String,
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validator whose field paths start with `prefix`, e.g. `data`
+    pub fn at(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn path(&self, field: &str) -> String {
+        match (self.prefix.is_empty(), field.is_empty()) {
+            (true, _) => field.to_string(),
+            (false, true) => self.prefix.clone(),
+            (false, false) => format!("{}.{}", self.prefix, field),
+        }
+    }
+
+    /// Record a violation of `rule` by `field`
+    pub fn add(&mut self, field: &str, rule: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: self.path(field),
+            rule: rule.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Validate the fields of a nested object under `field`
+    pub fn nested<T>(&mut self, field: &str, check: impl FnOnce(&mut Validator) -> T) -> T {
+        let mut inner = Validator::at(&self.path(field));
+        let result = check(&mut inner);
+        self.errors.append(&mut inner.errors);
+        result
+    }
+
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+
+    /// `Ok` if no rule was broken, otherwise `InvalidInput` with every violation
+    pub fn finish(self) -> DomainResult<()> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        Err(DomainError::InvalidInput {
+            message: format!("Request has {} invalid field(s)", self.errors.len()),
+            errors: self.errors,
+        })
+    }
+
+    pub fn required<T>(&mut self, field: &str, value: Option<T>) -> Option<T> {
+        if value.is_none() {
+            self.add(field, "required", format!("{} is required", self.path(field)));
+        }
+        value
+    }
+
+    /// Length in characters, inclusive bounds
+    pub fn length(&mut self, field: &str, value: &str, min: usize, max: usize) -> bool {
+        let length = value.chars().count();
+        let valid = (min..=max).contains(&length);
+        if !valid {
+            self.add(
+                field,
+                "length",
+                format!("{} must be {} to {} characters long", self.path(field), min, max),
+            );
+        }
+        valid
+    }
+
+    /// Inclusive bounds
+    pub fn range<T: PartialOrd + Display>(&mut self, field: &str, value: T, min: T, max: T) -> bool {
+        let valid = value >= min && value <= max;
+        if !valid {
+            self.add(field, "range", format!("{} must be between {} and {}", self.path(field), min, max));
+        }
+        valid
+    }
+
+    /// `valid` is the outcome of a format check; `expected` describes the format
+    pub fn format(&mut self, field: &str, valid: bool, expected: &str) -> bool {
+        if !valid {
+            self.add(field, "format", format!("{} must be {}", self.path(field), expected));
+        }
+        valid
+    }
+
+    pub fn one_of(&mut self, field: &str, value: &str, allowed: &[&str]) -> bool {
+        let valid = allowed.contains(&value);
+        if !valid {
+            self.add(
+                field,
+                "one_of",
+                format!("{} must be one of: {}", self.path(field), allowed.join(", ")),
+            );
+        }
+        valid
+    }
+
+    /// Record an `unknown` violation for every key not in `allowed`
+    pub fn known_fields<'a>(&mut self, keys: impl IntoIterator<Item = &'a String>, allowed: &[&str]) {
+        let mut unknown: Vec<&String> = keys.into_iter().filter(|k| !allowed.contains(&k.as_str())).collect();
+        unknown.sort();
+        for key in unknown {
+            self.add(key, "unknown", format!("Unknown field; expected one of: {}", allowed.join(", ")));
+        }
+    }
+
+    pub fn string<'a>(&mut self, field: &str, value: &'a Value) -> Option<&'a str> {
+        let string = value.as_str();
+        if string.is_none() {
+            self.type_error(field, "a string");
+        }
+        string
+    }
+
+    pub fn unsigned(&mut self, field: &str, value: &Value) -> Option<u64> {
+        let number = value.as_u64();
+        if number.is_none() {
+            self.type_error(field, "a non-negative integer");
+        }
+        number
+    }
+
+    pub fn array<'a>(&mut self, field: &str, value: &'a Value) -> Option<&'a Vec<Value>> {
+        let array = value.as_array();
+        if array.is_none() {
+            self.type_error(field, "an array");
+        }
+        array
+    }
+
+    pub fn object<'a>(&mut self, field: &str, value: &'a Value) -> Option<&'a Map<String, Value>> {
+        let object = value.as_object();
+        if object.is_none() {
+            self.type_error(field, "an object");
+        }
+        object
+    }
+
+    /// Deserialize a value, reporting a mismatch at the innermost failing path
+    pub fn deserialize<T: DeserializeOwned>(&mut self, field: &str, value: &Value) -> Option<T> {
+        match serde_path_to_error::deserialize(value) {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                let inner = e.path().to_string();
+                let path = match inner.as_str() {
+                    "." => field.to_string(),
+                    index if index.starts_with('[') => format!("{}{}", field, index),
+                    _ => format!("{}.{}", field, inner),
+                };
+                self.add(&path, "type", e.into_inner().to_string());
+                None
+            }
+        }
+    }
+
+    fn type_error(&mut self, field: &str, expected: &str) {
+        self.add(field, "type", format!("{} must be {}", self.path(field), expected));
+    }
+}
+
+/// Parse a JSON request body.
+///
+/// Syntax errors are reported against the body itself (with line and column),
+/// type mismatches against the path of the offending field.
+pub fn parse_json<T: DeserializeOwned>(body: &[u8]) -> DomainResult<T> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+        let path = e.path().to_string();
+        let inner = e.into_inner();
+        match inner.classify() {
+            serde_json::error::Category::Data if path != "." => invalid_body(&path, "type", inner),
+            serde_json::error::Category::Data => invalid_body("", "type", inner),
+            _ => invalid_body("", "json", inner),
+        }
+    })?;
+    // Reject trailing characters after the JSON value
+    deserializer.end().map_err(|e| invalid_body("", "json", e))?;
+    Ok(value)
+}
+
+fn invalid_body(field: &str, rule: &str, error: serde_json::Error) -> DomainError {
+    DomainError::InvalidInput {
+        message: "Request body is invalid".to_string(),
+        errors: vec![FieldError {
+            field: field.to_string(),
+            rule: rule.to_string(),
+            message: error.to_string(),
+        }],
+    }
+}
+
+/// `type/subtype` media type, without parameters
+pub fn is_content_type(value: &str) -> bool {
+    match value.split_once('/') {
+        Some((kind, subtype)) => {
+            let token = |s: &str| {
+                !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || "!#$&^_.+-".contains(c))
+            };
+            token(kind) && token(subtype)
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    fn fields(err: DomainError) -> Vec<(String, String)> {
+        match err {
+            DomainError::InvalidInput { errors, .. } => errors.into_iter().map(|e| (e.field, e.rule)).collect(),
+            other => panic!("expected InvalidInput, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validator_collects_every_violation() {
+        let data = serde_json::json!({"name": "", "size": "big", "kind": "zip", "extra": 1});
+        let data = data.as_object().unwrap();
+
+        let mut v = Validator::at("data");
+        v.known_fields(data.keys(), &["name", "size", "kind", "owner"]);
+        if let Some(name) = data.get("name").and_then(|n| v.string("name", n)) {
+            v.length("name", name, 1, 64);
+        }
+        if let Some(size) = data.get("size") {
+            v.unsigned("size", size);
+        }
+        if let Some(kind) = data.get("kind").and_then(|k| v.string("kind", k)) {
+            v.one_of("kind", kind, &["pdf", "png"]);
+        }
+        v.required("owner", data.get("owner"));
+
+        assert_eq!(
+            fields(v.finish().unwrap_err()),
+            vec![
+                ("data.extra".to_string(), "unknown".to_string()),
+                ("data.name".to_string(), "length".to_string()),
+                ("data.size".to_string(), "type".to_string()),
+                ("data.kind".to_string(), "one_of".to_string()),
+                ("data.owner".to_string(), "required".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_and_deserialize_paths() {
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Line {
+            sku: String,
+            quantity: u32,
+        }
+
+        let mut v = Validator::new();
+        v.nested("data", |v| v.deserialize::<Vec<Line>>("lines", &serde_json::json!([{"sku": "a", "quantity": -1}])));
+        assert_eq!(fields(v.finish().unwrap_err()), vec![("data.lines[0].quantity".to_string(), "type".to_string())]);
+        assert!(Validator::new().finish().is_ok());
+    }
+
+    #[test]
+    fn test_parse_json_reports_syntax_and_type_errors() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Body {
+            message: Option<String>,
+        }
+
+        assert!(parse_json::<Body>(br#"{"message": "hi"}"#).is_ok());
+        assert_eq!(fields(parse_json::<Body>(b"{\"message\": ").unwrap_err()), vec![(String::new(), "json".to_string())]);
+        assert_eq!(fields(parse_json::<Body>(b"{} {}").unwrap_err()), vec![(String::new(), "json".to_string())]);
+        assert_eq!(
+            fields(parse_json::<Body>(br#"{"message": 5}"#).unwrap_err()),
+            vec![("message".to_string(), "type".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_is_content_type() {
+        assert!(is_content_type("image/png"));
+        assert!(is_content_type("application/vnd.api+json"));
+        assert!(!is_content_type("png"));
+        assert!(!is_content_type("text/plain\r\nX-Injected: 1"));
+    }
+}
@@ -0,0 +1,244 @@
+use crate::config::AuthConfig;
+use crate::domain::auth::Principal;
+use crate::domain::errors::{DomainError, DomainResult};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, PublicKeyUse};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use lambda_http::http::HeaderMap;
+use serde_json::{Map, Value};
+
+/// Clock skew tolerated on `exp` and `nbf`, in seconds
+pub const DEFAULT_LEEWAY_SECS: u64 = 60;
+
+/// A JWKS entry usable for verification
+struct VerificationKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+/// Bearer token authentication with RS256/ES256 JWTs.
+///
+/// The JWKS document is parsed once, when the verifier is built at cold
+/// start, and its keys are kept for every later invocation.
+pub struct JwtAuth {
+    keys: Vec<VerificationKey>,
+    issuer: String,
+    audience: Vec<String>,
+    leeway: u64,
+}
+
+impl JwtAuth {
+    /// Verifier trusting the RS256 and ES256 signing keys of a JWKS document.
+    ///
+    /// Keys for other algorithms or for encryption are ignored; a document
+    /// without any usable key is rejected.
+    pub fn new(jwks: &str, issuer: &str, audience: &[&str]) -> DomainResult<Self> {
+        let jwks: JwkSet =
+            serde_json::from_str(jwks).map_err(|e| DomainError::Internal(format!("Invalid JWKS document: {}", e)))?;
+        let keys: Vec<VerificationKey> = jwks.keys.iter().filter_map(verification_key).collect();
+        if keys.is_empty() {
+            return Err(DomainError::Internal("JWKS document has no RS256 or ES256 signing key".to_string()));
+        }
+        if issuer.is_empty() || audience.is_empty() {
+            return Err(DomainError::Internal("JWT issuer and audience must be set".to_string()));
+        }
+
+        Ok(Self {
+            keys,
+            issuer: issuer.to_string(),
+            audience: audience.iter().map(|a| a.to_string()).collect(),
+            leeway: DEFAULT_LEEWAY_SECS,
+        })
+    }
+
+    pub fn with_leeway(mut self, leeway_secs: u64) -> Self {
+        self.leeway = leeway_secs;
+        self
+    }
+
+    /// Verifier from `config.jwks` (the document itself) or `config.jwks_file`
+    /// (a path to it), with its issuer, audience and leeway.
+    ///
+    /// `None` when no JWKS is configured, which disables authentication.
+    pub fn from_config(config: &AuthConfig) -> DomainResult<Option<Self>> {
+        let jwks = match (&config.jwks, &config.jwks_file) {
+            (Some(jwks), _) => jwks.expose().to_string(),
+            (None, Some(path)) => std::fs::read_to_string(path)
+                .map_err(|e| DomainError::Internal(format!("Failed to read JWKS file '{}': {}", path, e)))?,
+            (None, None) => return Ok(None),
+        };
+        let audience: Vec<&str> = config.audience.iter().map(String::as_str).collect();
+        Ok(Some(Self::new(&jwks, &config.issuer, &audience)?.with_leeway(config.leeway_secs)))
+    }
+
+    /// Verify the request's `Authorization: Bearer` token: signature, issuer,
+    /// audience, expiry and not-before
+    pub fn authenticate(&self, headers: &HeaderMap) -> DomainResult<Principal> {
+        let token = bearer_token(headers)?;
+        let header = decode_header(token).map_err(|_| unauthorized("Malformed bearer token"))?;
+
+        let key = match &header.kid {
+            Some(kid) => self.keys.iter().find(|k| k.kid.as_deref() == Some(kid.as_str())),
+            // Without a `kid`, only an unambiguous JWKS can be used
+            None if self.keys.len() == 1 => self.keys.first(),
+            None => None,
+        }
+        .ok_or_else(|| unauthorized("Token signing key is not trusted"))?;
+        // Pinning the algorithm to the key prevents algorithm substitution
+        if header.alg != key.algorithm {
+            return Err(unauthorized("Token algorithm does not match its signing key"));
+        }
+
+        let mut validation = Validation::new(key.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&self.audience);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        validation.validate_nbf = true;
+        validation.leeway = self.leeway;
+
+        let claims = decode::<Map<String, Value>>(token, &key.key, &validation).map_err(|e| {
+            unauthorized(match e.kind() {
+                ErrorKind::ExpiredSignature => "Token has expired",
+                ErrorKind::ImmatureSignature => "Token is not valid yet",
+                ErrorKind::InvalidIssuer => "Token issuer is not trusted",
+                ErrorKind::InvalidAudience => "Token audience is not accepted",
+                ErrorKind::MissingRequiredClaim(_) => "Token is missing a required claim",
+                _ => "Invalid bearer token",
+            })
+        })?;
+        Principal::from_claims(claims.claims)
+    }
+}
+
+fn unauthorized(message: &str) -> DomainError {
+    DomainError::Unauthorized(message.to_string())
+}
+
+fn bearer_token(headers: &HeaderMap) -> DomainResult<&str> {
+    let value = headers
+        .get("authorization")
+        .ok_or_else(|| unauthorized("Missing bearer token"))?
+        .to_str()
+        .map_err(|_| unauthorized("Malformed Authorization header"))?;
+    match value.split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") && !token.trim().is_empty() => Ok(token.trim()),
+        _ => Err(unauthorized("Authorization header must use the Bearer scheme")),
+    }
+}
+
+fn verification_key(jwk: &Jwk) -> Option<VerificationKey> {
+    if matches!(jwk.common.public_key_use, Some(PublicKeyUse::Encryption)) {
+        return None;
+    }
+    let algorithm = match (&jwk.common.key_algorithm, &jwk.algorithm) {
+        (Some(KeyAlgorithm::RS256), AlgorithmParameters::RSA(_)) => Algorithm::RS256,
+        (Some(KeyAlgorithm::ES256), AlgorithmParameters::EllipticCurve(params))
+            if params.curve == EllipticCurve::P256 =>
+        {
+            Algorithm::ES256
+        }
+        (None, AlgorithmParameters::RSA(_)) => Algorithm::RS256,
+        (None, AlgorithmParameters::EllipticCurve(params)) if params.curve == EllipticCurve::P256 => Algorithm::ES256,
+        _ => return None,
+    };
+    Some(VerificationKey {
+        kid: jwk.common.key_id.clone(),
+        algorithm,
+        key: DecodingKey::from_jwk(jwk).ok()?,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    pub(crate) const JWKS: &str = include_str!("../../tests/fixtures/jwt/jwks.json");
+    const RS256_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt/rs256-private.pem");
+    const ES256_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt/es256-private.pem");
+    pub(crate) const ISSUER: &str = "https://auth.example.com/";
+    pub(crate) const AUDIENCE: &str = "orders-api";
+
+    /// RS256 token for the fixture key, with `claims` merged over valid defaults
+    pub(crate) fn token(claims: Value) -> String {
+        sign(Algorithm::RS256, "rsa-1", claims)
+    }
+
+    fn sign(algorithm: Algorithm, kid: &str, claims: Value) -> String {
+        let mut body = json!({
+            "sub": "user-1",
+            "iss": ISSUER,
+            "aud": AUDIENCE,
+            "exp": chrono::Utc::now().timestamp() + 3600,
+        });
+        body.as_object_mut().unwrap().extend(claims.as_object().cloned().unwrap_or_default());
+        let key = match algorithm {
+            Algorithm::ES256 => EncodingKey::from_ec_pem(ES256_KEY).unwrap(),
+            _ => EncodingKey::from_rsa_pem(RS256_KEY).unwrap(),
+        };
+        let mut header = Header::new(algorithm);
+        header.kid = Some(kid.to_string());
+        encode(&header, &body, &key).unwrap()
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", format!("Bearer {}", token).parse().unwrap());
+        headers
+    }
+
+    fn auth() -> JwtAuth {
+        JwtAuth::new(JWKS, ISSUER, &[AUDIENCE]).unwrap()
+    }
+
+    fn rejection(headers: &HeaderMap) -> String {
+        match auth().authenticate(headers) {
+            Err(DomainError::Unauthorized(message)) => message,
+            other => panic!("expected Unauthorized, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_accepts_rs256_and_es256_tokens() {
+        let principal = auth().authenticate(&bearer(&token(json!({"scope": "items:read"})))).unwrap();
+        assert_eq!(principal.subject, "user-1");
+        assert!(principal.has_scope("items:read"));
+
+        let es256 = sign(Algorithm::ES256, "ec-1", json!({"sub": "user-2"}));
+        assert_eq!(auth().authenticate(&bearer(&es256)).unwrap().subject, "user-2");
+    }
+
+    #[test]
+    fn test_rejects_invalid_claims() {
+        let expired = token(json!({"exp": chrono::Utc::now().timestamp() - 3600}));
+        assert_eq!(rejection(&bearer(&expired)), "Token has expired");
+        assert_eq!(rejection(&bearer(&token(json!({"iss": "https://evil.com/"})))), "Token issuer is not trusted");
+        assert_eq!(rejection(&bearer(&token(json!({"aud": "other-api"})))), "Token audience is not accepted");
+    }
+
+    #[test]
+    fn test_rejects_untrusted_keys_and_headers() {
+        assert_eq!(rejection(&HeaderMap::new()), "Missing bearer token");
+        assert_eq!(rejection(&bearer("not-a-token")), "Malformed bearer token");
+
+        let unknown_kid = sign(Algorithm::RS256, "rsa-2", json!({}));
+        assert_eq!(rejection(&bearer(&unknown_kid)), "Token signing key is not trusted");
+
+        // The EC key's kid on an RSA-signed token
+        let mismatched = sign(Algorithm::RS256, "ec-1", json!({}));
+        assert_eq!(rejection(&bearer(&mismatched)), "Token algorithm does not match its signing key");
+
+        let mut basic = HeaderMap::new();
+        basic.insert("authorization", "Basic dXNlcjpwYXNz".parse().unwrap());
+        assert_eq!(rejection(&basic), "Authorization header must use the Bearer scheme");
+    }
+
+    #[test]
+    fn test_jwks_without_signing_keys_is_rejected() {
+        let hmac_only = r#"{"keys": [{"kty": "oct", "k": "c2VjcmV0", "alg": "HS256"}]}"#;
+        assert!(matches!(JwtAuth::new(hmac_only, ISSUER, &[AUDIENCE]), Err(DomainError::Internal(_))));
+        assert!(matches!(JwtAuth::new(JWKS, ISSUER, &[]), Err(DomainError::Internal(_))));
+    }
+}
@@ -0,0 +1,323 @@
+use crate::domain::request::{Authorizer, EventSource, RequestContext, CREDENTIAL_HEADERS};
+use lambda_http::aws_lambda_events::apigw::{ApiGatewayRequestAuthorizer, ApiGatewayRequestIdentity};
+use lambda_http::http::HeaderMap;
+use lambda_http::request::RequestContext as EventContext;
+use lambda_http::{Request, RequestExt};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Authorizer fields API Gateway adds itself rather than taking from the
+/// Lambda authorizer's context
+const AUTHORIZER_METADATA: &[&str] = &["principalId", "integrationLatency"];
+
+/// The processor's view of a request, read from the event's request context.
+///
+/// REST, HTTP and WebSocket APIs and Function URLs report the caller's IP and
+/// user agent in the context; for ALB events, which do not, they are taken
+/// from the `X-Forwarded-For` and `User-Agent` headers. The principal is left
+/// for the handler to set once the caller is authenticated.
+pub fn request_context(event: &Request) -> RequestContext {
+    let mut context = match event.request_context_ref() {
+        Some(EventContext::ApiGatewayV1(c)) => RequestContext {
+            request_id: c.request_id.clone(),
+            source: EventSource::RestApi,
+            source_ip: c.identity.source_ip.clone(),
+            user_agent: c.identity.user_agent.clone(),
+            stage: c.stage.clone(),
+            route_key: c.resource_path.as_ref().map(|path| format!("{} {}", c.http_method, path)),
+            authorizer: rest_authorizer(&c.authorizer, &c.identity),
+            ..RequestContext::default()
+        },
+        Some(EventContext::ApiGatewayV2(c)) => RequestContext {
+            request_id: c.request_id.clone(),
+            source: match &c.domain_name {
+                Some(domain) if domain.contains(".lambda-url.") => EventSource::FunctionUrl,
+                _ => EventSource::HttpApi,
+            },
+            source_ip: c.http.source_ip.clone(),
+            user_agent: c.http.user_agent.clone(),
+            stage: c.stage.clone(),
+            route_key: c.route_key.clone(),
+            authorizer: c.authorizer.as_ref().and_then(http_authorizer),
+            ..RequestContext::default()
+        },
+        Some(EventContext::WebSocket(c)) => RequestContext {
+            request_id: c.request_id.clone(),
+            source: EventSource::WebSocket,
+            source_ip: c.identity.source_ip.clone(),
+            user_agent: c.identity.user_agent.clone(),
+            stage: c.stage.clone(),
+            route_key: c.route_key.clone(),
+            authorizer: rest_authorizer(&c.authorizer, &c.identity),
+            ..RequestContext::default()
+        },
+        Some(EventContext::Alb(_)) => RequestContext {
+            source: EventSource::Alb,
+            ..RequestContext::default()
+        },
+        None => RequestContext::default(),
+    };
+
+    context.headers = header_map(event.headers());
+    if context.request_id.is_none() {
+        context.request_id = event
+            .lambda_context_ref()
+            .map(|lambda| lambda.request_id.clone())
+            .filter(|id| !id.is_empty());
+    }
+    if context.source_ip.is_none() {
+        // ALB appends the address it saw to whatever chain the client sent,
+        // so only the right-most entry can be trusted
+        context.source_ip = context
+            .header("x-forwarded-for")
+            .and_then(|chain| chain.rsplit(',').next())
+            .map(|ip| ip.trim().to_string())
+            .filter(|ip| !ip.is_empty());
+    }
+    if context.user_agent.is_none() {
+        context.user_agent = context.header("user-agent").map(str::to_string);
+    }
+    context
+}
+
+/// The path routes are matched on. lambda_http prefixes API Gateway paths
+/// with the stage (`/dev/items/1111`) unless it is `$default`; routes never
+/// include it.
+pub fn route_path(event: &Request) -> &str {
+    let path = event.uri().path();
+    let stage = match event.request_context_ref() {
+        Some(EventContext::ApiGatewayV1(c)) => c.stage.as_deref(),
+        Some(EventContext::ApiGatewayV2(c)) => c.stage.as_deref(),
+        Some(EventContext::WebSocket(c)) => c.stage.as_deref(),
+        _ => None,
+    };
+    let Some(stage) = stage.filter(|stage| !stage.is_empty() && *stage != "$default") else {
+        return path;
+    };
+    match path.strip_prefix('/').and_then(|rest| rest.strip_prefix(stage)) {
+        Some("") => "/",
+        Some(rest) if rest.starts_with('/') => rest,
+        _ => path,
+    }
+}
+
+fn header_map(headers: &HeaderMap) -> HashMap<String, String> {
+    let mut map: HashMap<String, String> = HashMap::new();
+    for (name, value) in headers {
+        let (name, Ok(value)) = (name.as_str(), value.to_str()) else {
+            continue;
+        };
+        if CREDENTIAL_HEADERS.contains(&name) {
+            continue;
+        }
+        map.entry(name.to_string())
+            .and_modify(|joined| {
+                joined.push_str(", ");
+                joined.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+    map
+}
+
+/// REST and WebSocket APIs flatten the authorizer's output into one map: a
+/// Cognito user pool authorizer's claims sit under `claims`, a Lambda
+/// authorizer's context next to its `principalId`. IAM callers only show in
+/// the identity.
+fn rest_authorizer(authorizer: &ApiGatewayRequestAuthorizer, identity: &ApiGatewayRequestIdentity) -> Option<Authorizer> {
+    if let Some(Value::Object(claims)) = authorizer.fields.get("claims") {
+        return Some(Authorizer::Jwt {
+            scopes: scopes(claims.get("scope")),
+            claims: claims.clone(),
+        });
+    }
+    if !authorizer.fields.is_empty() {
+        return Some(lambda_authorizer(&authorizer.fields));
+    }
+    if identity.user_arn.is_some() || identity.access_key.is_some() {
+        return Some(Authorizer::Iam {
+            user_arn: identity.user_arn.clone(),
+            account_id: identity.account_id.clone(),
+            caller_id: identity.caller.clone(),
+            access_key: identity.access_key.clone(),
+        });
+    }
+    None
+}
+
+/// HTTP APIs report each authorizer type under its own key
+fn http_authorizer(authorizer: &ApiGatewayRequestAuthorizer) -> Option<Authorizer> {
+    if let Some(jwt) = &authorizer.jwt {
+        let claims: Map<String, Value> = jwt.claims.iter().map(|(k, v)| (k.clone(), v.clone().into())).collect();
+        return Some(Authorizer::Jwt {
+            scopes: jwt.scopes.clone().unwrap_or_else(|| scopes(claims.get("scope"))),
+            claims,
+        });
+    }
+    if let Some(iam) = &authorizer.iam {
+        return Some(Authorizer::Iam {
+            user_arn: iam.user_arn.clone(),
+            account_id: iam.account_id.clone(),
+            caller_id: iam.caller_id.clone(),
+            access_key: iam.access_key.clone(),
+        });
+    }
+    (!authorizer.fields.is_empty()).then(|| lambda_authorizer(&authorizer.fields))
+}
+
+fn lambda_authorizer(fields: &HashMap<String, Value>) -> Authorizer {
+    Authorizer::Lambda {
+        principal_id: fields.get("principalId").and_then(Value::as_str).map(str::to_string),
+        context: fields
+            .iter()
+            .filter(|(name, _)| !AUTHORIZER_METADATA.contains(&name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+    }
+}
+
+/// Scopes from a space-separated OAuth `scope` claim
+fn scopes(scope: Option<&Value>) -> Vec<String> {
+    scope
+        .and_then(Value::as_str)
+        .map(|scope| scope.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(value: Value) -> Request {
+        lambda_http::request::from_str(&value.to_string()).expect("Failed to parse event")
+    }
+
+    #[test]
+    fn test_rest_api_event_with_cognito_authorizer() {
+        let context = request_context(&event(json!({
+            "resource": "/items/{pk}",
+            "path": "/items/1111",
+            "httpMethod": "GET",
+            "headers": {"Host": "api.example.com", "Authorization": "Bearer abc", "Accept": "application/json"},
+            "multiValueHeaders": {
+                "Host": ["api.example.com"],
+                "Authorization": ["Bearer abc"],
+                "Accept": ["application/json"]
+            },
+            "requestContext": {
+                "resourcePath": "/items/{pk}",
+                "httpMethod": "GET",
+                "stage": "prod",
+                "requestId": "c6af9ac6-7b61-11e6-9a41-93e8deadbeef",
+                "identity": {"sourceIp": "203.0.113.7", "userAgent": "curl/8.4.0"},
+                "authorizer": {"claims": {"sub": "user-1", "scope": "items:read items:write"}}
+            },
+            "body": null,
+            "isBase64Encoded": false
+        })));
+
+        assert_eq!(context.source, EventSource::RestApi);
+        assert_eq!(context.request_id.as_deref(), Some("c6af9ac6-7b61-11e6-9a41-93e8deadbeef"));
+        assert_eq!(context.source_ip.as_deref(), Some("203.0.113.7"));
+        assert_eq!(context.user_agent.as_deref(), Some("curl/8.4.0"));
+        assert_eq!(context.stage.as_deref(), Some("prod"));
+        assert_eq!(context.route_key.as_deref(), Some("GET /items/{pk}"));
+        match &context.authorizer {
+            Some(Authorizer::Jwt { claims, scopes }) => {
+                assert_eq!(claims["sub"], "user-1");
+                assert_eq!(scopes, &["items:read".to_string(), "items:write".to_string()]);
+            }
+            other => panic!("expected a JWT authorizer, got {:?}", other),
+        }
+        assert_eq!(context.header("accept"), Some("application/json"));
+        assert_eq!(context.header("authorization"), None);
+    }
+
+    #[test]
+    fn test_http_api_event_with_lambda_authorizer() {
+        let request = event(json!({
+            "version": "2.0",
+            "routeKey": "PUT /items/{pk}/{sk}",
+            "rawPath": "/dev/items/1111/10",
+            "rawQueryString": "",
+            "headers": {"host": "abc123.execute-api.eu-west-1.amazonaws.com", "x-api-key": "ak_1.secret"},
+            "requestContext": {
+                "apiId": "abc123",
+                "domainName": "abc123.execute-api.eu-west-1.amazonaws.com",
+                "http": {
+                    "method": "PUT",
+                    "path": "/dev/items/1111/10",
+                    "protocol": "HTTP/1.1",
+                    "sourceIp": "198.51.100.20",
+                    "userAgent": "orders-client/2.1"
+                },
+                "requestId": "JKJaXmPLvHcESHA=",
+                "routeKey": "PUT /items/{pk}/{sk}",
+                "stage": "dev",
+                "authorizer": {"lambda": {"tenant": "acme", "tier": 2}}
+            },
+            "isBase64Encoded": false
+        }));
+        let context = request_context(&request);
+
+        assert_eq!(context.source, EventSource::HttpApi);
+        assert_eq!(route_path(&request), "/items/1111/10");
+        assert_eq!(context.route_key.as_deref(), Some("PUT /items/{pk}/{sk}"));
+        assert_eq!(context.source_ip.as_deref(), Some("198.51.100.20"));
+        assert_eq!(
+            context.authorizer,
+            Some(Authorizer::Lambda {
+                principal_id: None,
+                context: json!({"tenant": "acme", "tier": 2}).as_object().cloned().unwrap(),
+            })
+        );
+        assert!(!context.headers.contains_key("x-api-key"));
+    }
+
+    #[test]
+    fn test_function_url_and_alb_events() {
+        let context = request_context(&event(json!({
+            "version": "2.0",
+            "routeKey": "$default",
+            "rawPath": "/",
+            "rawQueryString": "",
+            "headers": {"host": "id.lambda-url.eu-west-2.on.aws"},
+            "requestContext": {
+                "domainName": "id.lambda-url.eu-west-2.on.aws",
+                "http": {"method": "GET", "path": "/", "protocol": "HTTP/1.1", "sourceIp": "65.78.31.245", "userAgent": "curl/7.68.0"},
+                "requestId": "MIZRNhJtIAMEMDw=",
+                "routeKey": "$default",
+                "stage": "$default",
+                "authorizer": {"iam": {"accountId": "123456789012", "userArn": "arn:aws:iam::123456789012:user/ci"}}
+            },
+            "isBase64Encoded": false
+        })));
+        assert_eq!(context.source, EventSource::FunctionUrl);
+        assert_eq!(context.request_id.as_deref(), Some("MIZRNhJtIAMEMDw="));
+        assert!(matches!(
+            context.authorizer,
+            Some(Authorizer::Iam { user_arn: Some(ref arn), .. }) if arn == "arn:aws:iam::123456789012:user/ci"
+        ));
+
+        let context = request_context(&event(json!({
+            "requestContext": {"elb": {"targetGroupArn": "arn:aws:elasticloadbalancing:eu-west-1:123456789012:targetgroup/orders/6d0ecf831eec9f09"}},
+            "httpMethod": "GET",
+            "path": "/items/1111",
+            "queryStringParameters": {},
+            "headers": {
+                "host": "orders.example.com",
+                "user-agent": "Mozilla/5.0",
+                "x-forwarded-for": "10.0.0.12, 203.0.113.250, 192.0.2.44"
+            },
+            "body": "",
+            "isBase64Encoded": false
+        })));
+        assert_eq!(context.source, EventSource::Alb);
+        // The entries before ALB's own are whatever the client claimed
+        assert_eq!(context.source_ip.as_deref(), Some("192.0.2.44"));
+        assert_eq!(context.user_agent.as_deref(), Some("Mozilla/5.0"));
+        assert_eq!(context.stage, None);
+        assert_eq!(context.authorizer, None);
+    }
+}
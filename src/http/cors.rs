@@ -0,0 +1,218 @@
+use crate::config::CorsConfig;
+use lambda_http::http::header::{HeaderMap, HeaderName, HeaderValue};
+use lambda_http::http::Method;
+
+/// An entry of the allowed-origin list
+#[derive(Debug, Clone, PartialEq)]
+pub enum AllowedOrigin {
+    /// `*`: every origin
+    Any,
+    /// One origin, compared exactly, e.g. `https://shop.example.com`
+    Exact(String),
+    /// One `*` wildcard standing for a host label run or port, e.g. `https://*.example.com`
+    Pattern { prefix: String, suffix: String },
+}
+
+impl AllowedOrigin {
+    pub fn parse(entry: &str) -> Self {
+        let entry = entry.trim().trim_end_matches('/');
+        match entry.split_once('*') {
+            None => AllowedOrigin::Exact(entry.to_string()),
+            Some(("", "")) => AllowedOrigin::Any,
+            Some((prefix, suffix)) => AllowedOrigin::Pattern {
+                prefix: prefix.to_string(),
+                suffix: suffix.to_string(),
+            },
+        }
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            AllowedOrigin::Any => true,
+            AllowedOrigin::Exact(allowed) => allowed.eq_ignore_ascii_case(origin),
+            AllowedOrigin::Pattern { prefix, suffix } => {
+                let origin = origin.to_ascii_lowercase();
+                origin.len() > prefix.len() + suffix.len()
+                    && origin.starts_with(&prefix.to_ascii_lowercase())
+                    && origin.ends_with(&suffix.to_ascii_lowercase())
+                    // The wildcard may not swallow a scheme or path
+                    && origin[prefix.len()..origin.len() - suffix.len()]
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+            }
+        }
+    }
+}
+
+/// Cross-origin policy applied to every response.
+///
+/// Built once per cold start, usually with `from_config`. The default allows any
+/// origin without credentials.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsPolicy {
+    pub allowed_origins: Vec<AllowedOrigin>,
+    /// Request headers a cross-origin caller may send
+    pub allowed_headers: Vec<String>,
+    /// Response headers a cross-origin caller may read
+    pub exposed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight response, in seconds
+    pub max_age: Option<u64>,
+}
+
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        Self::from_config(&CorsConfig::default())
+    }
+}
+
+impl CorsPolicy {
+    /// Policy from the `cors` settings, parsing each allowed origin
+    pub fn from_config(config: &CorsConfig) -> Self {
+        Self {
+            allowed_origins: config.allowed_origins.iter().map(|o| AllowedOrigin::parse(o)).collect(),
+            allowed_headers: config.allowed_headers.clone(),
+            exposed_headers: config.exposed_headers.clone(),
+            allow_credentials: config.allow_credentials,
+            max_age: config.max_age,
+        }
+    }
+
+    /// `Access-Control-Allow-Origin` value for a request from `origin`, or `None` if it is not allowed
+    fn allow_origin(&self, origin: Option<&str>) -> Option<String> {
+        match origin {
+            _ if self.allowed_origins.contains(&AllowedOrigin::Any) => Some("*".to_string()),
+            Some(origin) if self.allowed_origins.iter().any(|allowed| allowed.matches(origin)) => {
+                Some(origin.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the response depends on the `Origin` request header
+    fn varies_by_origin(&self) -> bool {
+        !self.allowed_origins.contains(&AllowedOrigin::Any)
+    }
+
+    /// Add the CORS headers for a request from `origin` to a response
+    pub fn apply(&self, origin: Option<&str>, headers: &mut HeaderMap) {
+        if self.varies_by_origin() {
+            append(headers, "vary", "Origin");
+        }
+        let Some(allow_origin) = self.allow_origin(origin) else { return };
+        insert(headers, "access-control-allow-origin", &allow_origin);
+        // Browsers refuse credentials with `*`, which configuration validation rejects
+        if self.allow_credentials && allow_origin != "*" {
+            insert(headers, "access-control-allow-credentials", "true");
+        }
+        if !self.exposed_headers.is_empty() {
+            insert(headers, "access-control-expose-headers", &self.exposed_headers.join(", "));
+        }
+    }
+
+    /// Add the headers answering a preflight for a path served with `methods`,
+    /// on top of those added by `apply`.
+    ///
+    /// Nothing is added for a disallowed origin, which makes the browser fail
+    /// the preflight without the request reaching any endpoint.
+    pub fn apply_preflight(&self, origin: Option<&str>, methods: &[Method], headers: &mut HeaderMap) {
+        if self.allow_origin(origin).is_none() {
+            return;
+        }
+        let methods: Vec<&str> = methods.iter().map(Method::as_str).chain(["OPTIONS"]).collect();
+        insert(headers, "access-control-allow-methods", &methods.join(", "));
+        if !self.allowed_headers.is_empty() {
+            insert(headers, "access-control-allow-headers", &self.allowed_headers.join(", "));
+        }
+        if let Some(max_age) = self.max_age {
+            insert(headers, "access-control-max-age", &max_age.to_string());
+        }
+    }
+}
+
+/// Whether a request is a CORS preflight rather than a plain `OPTIONS` call
+pub fn is_preflight(method: &Method, headers: &HeaderMap) -> bool {
+    method == Method::OPTIONS && headers.contains_key("access-control-request-method")
+}
+
+fn insert(headers: &mut HeaderMap, name: &'static str, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(HeaderName::from_static(name), value);
+    }
+}
+
+fn append(headers: &mut HeaderMap, name: &'static str, value: &'static str) {
+    headers.append(HeaderName::from_static(name), HeaderValue::from_static(value));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(origins: &[&str], allow_credentials: bool) -> CorsPolicy {
+        CorsPolicy {
+            allowed_origins: origins.iter().map(|o| AllowedOrigin::parse(o)).collect(),
+            exposed_headers: vec!["ETag".to_string()],
+            allow_credentials,
+            ..CorsPolicy::default()
+        }
+    }
+
+    #[test]
+    fn test_origin_patterns() {
+        let pattern = AllowedOrigin::parse("https://*.example.com");
+        assert!(pattern.matches("https://shop.example.com"));
+        assert!(pattern.matches("https://a.b.example.com"));
+        assert!(!pattern.matches("https://example.com"));
+        assert!(!pattern.matches("https://evil.com/.example.com"));
+        assert!(!pattern.matches("http://shop.example.com"));
+
+        let port = AllowedOrigin::parse("http://localhost:*");
+        assert!(port.matches("http://localhost:3000"));
+        assert_eq!(AllowedOrigin::parse("https://shop.example.com/"), AllowedOrigin::Exact("https://shop.example.com".into()));
+    }
+
+    #[test]
+    fn test_apply_any_origin() {
+        let mut headers = HeaderMap::new();
+        CorsPolicy::default().apply(Some("https://shop.example.com"), &mut headers);
+        assert_eq!(headers["access-control-allow-origin"], "*");
+        assert!(!headers.contains_key("vary"));
+
+        // Never echoed, so no site can make credentialed reads
+        let mut headers = HeaderMap::new();
+        policy(&["*"], true).apply(Some("https://shop.example.com"), &mut headers);
+        assert_eq!(headers["access-control-allow-origin"], "*");
+        assert!(!headers.contains_key("access-control-allow-credentials"));
+    }
+
+    #[test]
+    fn test_apply_origin_list() {
+        let policy = policy(&["https://shop.example.com", "https://*.admin.example.com"], false);
+
+        let mut headers = HeaderMap::new();
+        policy.apply(Some("https://eu.admin.example.com"), &mut headers);
+        assert_eq!(headers["access-control-allow-origin"], "https://eu.admin.example.com");
+        assert_eq!(headers["access-control-expose-headers"], "ETag");
+
+        let mut headers = HeaderMap::new();
+        policy.apply(Some("https://evil.com"), &mut headers);
+        assert!(!headers.contains_key("access-control-allow-origin"));
+        assert_eq!(headers["vary"], "Origin");
+    }
+
+    #[test]
+    fn test_preflight_headers() {
+        let policy = policy(&["https://shop.example.com"], false);
+
+        let mut headers = HeaderMap::new();
+        policy.apply_preflight(Some("https://shop.example.com"), &[Method::GET, Method::PUT], &mut headers);
+        assert_eq!(headers["access-control-allow-methods"], "GET, PUT, OPTIONS");
+        assert_eq!(headers["access-control-allow-headers"], "Content-Type, Authorization");
+        assert_eq!(headers["access-control-max-age"], "600");
+
+        let mut headers = HeaderMap::new();
+        policy.apply_preflight(Some("https://evil.com"), &[Method::GET], &mut headers);
+        assert!(!headers.contains_key("access-control-allow-methods"));
+    }
+}
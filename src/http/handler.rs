@@ -0,0 +1,1049 @@
+use crate::application::context::AppContext;
+use crate::application::idempotency::{IdempotencyOutcome, IdempotencyService};
+use crate::domain::auth::Principal;
+use crate::domain::errors::{DomainError, DomainResult};
+use crate::domain::idempotency::{request_hash, validate_key};
+use crate::domain::models::{RequestPayload, ResponsePayload};
+use crate::domain::rate_limit::RateLimitStatus;
+use crate::domain::validation::{parse_json, Validate};
+use crate::domain::request::RequestContext;
+use crate::http::auth::JwtAuth;
+use crate::http::context::{request_context, route_path};
+use crate::http::cors::{is_preflight, CorsPolicy};
+use crate::http::logging::{correlation_id, log_request, request_span, CORRELATION_ID_HEADER};
+use crate::http::response::{
+    create_error_response, domain_error_response, json_response, object_response, rate_limit_headers,
+    rate_limited_response, replayed_response, stored_response,
+};
+use crate::http::router::{RouteError, RouteMatch, Router};
+use crate::metrics::UNMATCHED_ROUTE;
+use lambda_http::http::{HeaderValue, Method, StatusCode};
+use lambda_http::{Body, Error, Request, RequestExt, Response};
+use std::collections::HashMap;
+use std::time::Instant;
+use tracing::{error, info, warn, Instrument};
+
+/// Endpoints served by this Lambda
+#[derive(Debug, Clone, Copy)]
+pub enum Endpoint {
+    Demo,
+    ListItems,
+    GetItem,
+    PutItem,
+    PatchItem,
+    DeleteItem,
+    UploadUrl,
+    DownloadUrl,
+    UploadObject,
+    DownloadObject,
+    IssueApiKey,
+    RevokeApiKey,
+}
+
+/// Route table: register new endpoints here
+pub fn build_router() -> Router<Endpoint> {
+    Router::new()
+        .route(Method::GET, "/demo", Endpoint::Demo)
+        .route(Method::POST, "/demo", Endpoint::Demo)
+        .route(Method::GET, "/items/{pk}", Endpoint::ListItems)
+        .route(Method::GET, "/items/{pk}/{sk}", Endpoint::GetItem)
+        .route(Method::PUT, "/items/{pk}/{sk}", Endpoint::PutItem)
+        .route(Method::PATCH, "/items/{pk}/{sk}", Endpoint::PatchItem)
+        .route(Method::DELETE, "/items/{pk}/{sk}", Endpoint::DeleteItem)
+        .route(Method::POST, "/upload-urls/{key+}", Endpoint::UploadUrl)
+        .route(Method::GET, "/download-urls/{key+}", Endpoint::DownloadUrl)
+        .route(Method::PUT, "/objects/{key+}", Endpoint::UploadObject)
+        .route(Method::GET, "/objects/{key+}", Endpoint::DownloadObject)
+        .route(Method::POST, "/api-keys", Endpoint::IssueApiKey)
+        .route(Method::DELETE, "/api-keys/{key_id}", Endpoint::RevokeApiKey)
+}
+
+/// HTTP-level settings, built once per cold start alongside `AppContext`
+pub struct HttpConfig {
+    pub router: Router<Endpoint>,
+    pub cors: CorsPolicy,
+    /// Bearer token verification; `None` serves every request unauthenticated
+    pub auth: Option<JwtAuth>,
+}
+
+impl HttpConfig {
+    pub fn new(cors: CorsPolicy) -> Self {
+        Self {
+            router: build_router(),
+            cors,
+            auth: None,
+        }
+    }
+
+    pub fn with_auth(mut self, auth: Option<JwtAuth>) -> Self {
+        self.auth = auth;
+        self
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self::new(CorsPolicy::default())
+    }
+}
+
+/// Main Lambda handler function
+pub async fn function_handler(ctx: &AppContext, http: &HttpConfig, event: Request) -> Result<Response<Body>, Error> {
+    // Every log line of the invocation carries the request, trace and correlation ids
+    let started = Instant::now();
+    let correlation_id = correlation_id(event.headers());
+    let span = request_span(&event, &correlation_id);
+    // Resolved once, for the span, the metrics and dispatch
+    let resolved = http.router.resolve(event.method(), route_path(&event));
+    let route_key = resolved.as_ref().map_or(UNMATCHED_ROUTE, |route| route.route_key);
+    if resolved.is_ok() {
+        span.record("route", route_key);
+    }
+
+    let result = handle(ctx, http, resolved, event).instrument(span.clone()).await;
+    let status = result.as_ref().map_or(500, |response| response.status().as_u16());
+    span.in_scope(|| info!(status, "Request completed"));
+    // Everything the invocation recorded, adapter calls included, is written at once
+    if let Some(metrics) = &ctx.metrics {
+        metrics.record_request(route_key, status, started.elapsed());
+        metrics.flush();
+    }
+
+    let mut response = result?;
+
+    if let Ok(value) = HeaderValue::from_str(&correlation_id) {
+        response.headers_mut().insert(CORRELATION_ID_HEADER, value);
+    }
+    Ok(response)
+}
+
+async fn handle(
+    ctx: &AppContext,
+    http: &HttpConfig,
+    resolved: Result<RouteMatch<'_, Endpoint>, RouteError>,
+    event: Request,
+) -> Result<Response<Body>, Error> {
+    log_request(&event);
+
+    let origin = event
+        .headers()
+        .get("origin")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    // Preflights are answered from the route table and never reach an endpoint;
+    // browsers send them without credentials, so they are not authenticated
+    let mut response = if is_preflight(event.method(), event.headers()) {
+        preflight_response(http, origin.as_deref(), route_path(&event))
+    } else {
+        match authenticate(ctx, http, &event).await {
+            Ok(principal) => {
+                let request = RequestContext {
+                    principal,
+                    ..request_context(&event)
+                };
+                dispatch(ctx, resolved, &request, event).await?
+            }
+            Err(e) => {
+                info!("Rejected unauthenticated request: {}", e);
+                auth_error_response(&e, http.auth.is_some())
+            }
+        }
+    };
+
+    // Every response, errors and binary downloads included, carries the same CORS headers
+    http.cors.apply(origin.as_deref(), response.headers_mut());
+    Ok(response)
+}
+
+/// Answer a CORS preflight with the methods the path is served with
+fn preflight_response(http: &HttpConfig, origin: Option<&str>, path: &str) -> Response<Body> {
+    let methods = http.router.allowed_methods(path);
+    if methods.is_empty() {
+        return domain_error_response(&DomainError::NotFound(format!("No route for OPTIONS {}", path)));
+    }
+    let mut response = Response::new(Body::Empty);
+    *response.status_mut() = StatusCode::NO_CONTENT;
+    http.cors.apply_preflight(origin, &methods, response.headers_mut());
+    response
+}
+
+/// The verified caller: the `x-api-key` key when one is sent and API keys are
+/// enabled, otherwise the bearer token. `None` when authentication is disabled.
+async fn authenticate(ctx: &AppContext, http: &HttpConfig, event: &Request) -> DomainResult<Option<Principal>> {
+    let api_key = event.headers().get("x-api-key").and_then(|v| v.to_str().ok());
+    match (&ctx.api_keys, api_key, &http.auth) {
+        (Some(api_keys), Some(key), _) => api_keys.authenticate(key).await.map(Some),
+        (_, _, Some(jwt)) => jwt.authenticate(event.headers()).map(Some),
+        (Some(_), _, None) => Err(DomainError::Unauthorized("Missing API key".to_string())),
+        (None, _, None) => Ok(None),
+    }
+}
+
+/// Failed authentication in the usual envelope; 401s from a bearer-protected
+/// API carry the challenge clients expect
+fn auth_error_response(err: &DomainError, bearer: bool) -> Response<Body> {
+    let mut response = domain_error_response(err);
+    if bearer && matches!(err, DomainError::Unauthorized(_)) {
+        response
+            .headers_mut()
+            .insert("WWW-Authenticate", HeaderValue::from_static("Bearer error=\"invalid_token\""));
+    }
+    response
+}
+
+/// Route a request to its endpoint, subject to the client's rate limit
+async fn dispatch(
+    ctx: &AppContext,
+    resolved: Result<RouteMatch<'_, Endpoint>, RouteError>,
+    request: &RequestContext,
+    event: Request,
+) -> Result<Response<Body>, Error> {
+    // Unroutable requests are answered before doing any work
+    let route = match resolved {
+        Ok(route) => route,
+        Err(RouteError::NotFound) => {
+            return Ok(domain_error_response(&DomainError::NotFound(format!(
+                "No route for {} {}",
+                event.method(),
+                route_path(&event)
+            ))));
+        }
+        Err(RouteError::MethodNotAllowed { allowed }) => {
+            let allowed = allowed.iter().map(Method::as_str).collect::<Vec<_>>().join(", ");
+            let mut response = create_error_response(
+                405,
+                "METHOD_NOT_ALLOWED",
+                &format!("Method {} not allowed for {}", event.method(), route_path(&event)),
+            );
+            if let Ok(value) = allowed.parse() {
+                response.headers_mut().insert("Allow", value);
+            }
+            return Ok(response);
+        }
+    };
+
+    // Clients over their limit are turned away before any work is done
+    let rate_limit = match &ctx.rate_limiter {
+        Some(limiter) => limiter.check(request, route.route_key).await,
+        None => None,
+    };
+    if let Some(status) = rate_limit.filter(RateLimitStatus::is_limited) {
+        info!("Rate limited {}", route.route_key);
+        return Ok(rate_limited_response(&status));
+    }
+
+    // POST /api-keys is left out: its response holds a secret that is never stored
+    let idempotency_key = event
+        .headers()
+        .get("idempotency-key")
+        .map(|value| value.to_str().unwrap_or_default().to_string());
+    let mut response = match (&ctx.idempotency, idempotency_key) {
+        (Some(idempotency), Some(key))
+            if event.method() == Method::POST && !matches!(route.target, Endpoint::IssueApiKey) =>
+        {
+            run_idempotent(ctx, idempotency, &key, request, route, event).await?
+        }
+        _ => run_endpoint(ctx, request, route, event).await?,
+    };
+    if let Some(status) = &rate_limit {
+        rate_limit_headers(status, response.headers_mut());
+    }
+    Ok(response)
+}
+
+/// Run a request carrying an `Idempotency-Key` at most once per key and
+/// client, replaying the stored response to repeats
+async fn run_idempotent(
+    ctx: &AppContext,
+    idempotency: &IdempotencyService,
+    key: &str,
+    request: &RequestContext,
+    route: RouteMatch<'_, Endpoint>,
+    event: Request,
+) -> Result<Response<Body>, Error> {
+    if let Err(e) = validate_key(key) {
+        return Ok(domain_error_response(&e));
+    }
+    // Scoped to the client, so one client cannot replay another's responses
+    let record_key = format!("{}#{}", request.client_key().as_deref().unwrap_or("anonymous"), key);
+    let uri = event.uri();
+    let hash = request_hash(event.method().as_str(), uri.path(), uri.query().unwrap_or_default(), event.body());
+    let locked_until = match idempotency.begin(&record_key, &hash).await {
+        Ok(IdempotencyOutcome::Proceed { locked_until }) => locked_until,
+        Ok(IdempotencyOutcome::Replay(stored)) => {
+            info!("Replaying response for Idempotency-Key {}", key);
+            return Ok(replayed_response(&stored));
+        }
+        Err(e) => return Ok(domain_error_response(&e)),
+    };
+
+    let response = run_endpoint(ctx, request, route, event).await?;
+    // A server error is not a final answer: free the key so the request can be retried
+    let recorded = if response.status().is_server_error() {
+        idempotency.release(&record_key).await
+    } else {
+        idempotency.complete(&record_key, &hash, locked_until, stored_response(&response)).await
+    };
+    if let Err(e) = recorded {
+        warn!("Failed to record the response for Idempotency-Key {}: {}", key, e);
+    }
+    Ok(response)
+}
+
+/// Parse the request for the resolved endpoint, run it and render the result
+async fn run_endpoint(
+    ctx: &AppContext,
+    request: &RequestContext,
+    route: RouteMatch<'_, Endpoint>,
+    event: Request,
+) -> Result<Response<Body>, Error> {
+    // Extract query parameters
+    let query_params = event.query_string_parameters();
+    let path_params = event.path_parameters();
+
+    // Upload bodies are raw bytes; every other endpoint takes an optional JSON body.
+    // lambda_http decodes base64-encoded bodies (API Gateway binary media types,
+    // Function URLs) into `Body::Binary`, so JSON may arrive as either variant.
+    let request_payload: Option<RequestPayload> = match (route.target, event.body()) {
+        (Endpoint::UploadObject, _) | (_, Body::Empty) => None,
+        (_, body) => match parse_json::<RequestPayload>(body).and_then(Validate::validated) {
+            Ok(payload) => Some(payload),
+            Err(e) => {
+                error!("Invalid request body: {}", e);
+                return Ok(domain_error_response(&e));
+            }
+        },
+    };
+
+    // The lambda_http query/path maps are converted to standard HashMaps for the processor
+    let mut q_params = HashMap::new();
+    for (k, v) in query_params.iter() {
+        q_params.insert(k.to_string(), v.to_string());
+    }
+
+    let mut p_params = HashMap::new();
+    for (k, v) in path_params.iter() {
+        p_params.insert(k.to_string(), v.to_string());
+    }
+    // Parameters captured by the router take precedence over API Gateway's
+    p_params.extend(route.path_params);
+
+    let processor = &ctx.processor;
+    let result = match route.target {
+        Endpoint::Demo => processor.demo(request_payload, &q_params).await.and_then(|report| {
+            let data = data_fields(&report)?;
+            let payload = ResponsePayload::success(report.message);
+            Ok(if data.is_empty() { payload } else { payload.with_data(data) })
+        }),
+        Endpoint::ListItems => processor.list_items(request, &q_params, &p_params).await.and_then(|page| {
+            let count = page.items.len();
+            Ok(ResponsePayload::success(format!("{} item(s)", count))
+                .with_data(data_entry("items", &page.items)?)
+                .with_next_token(page.next_token))
+        }),
+        Endpoint::GetItem => processor
+            .get_item(request, &p_params)
+            .await
+            .and_then(|item| Ok(ResponsePayload::success("Item found").with_data(data_entry("item", &item)?))),
+        Endpoint::PutItem => processor
+            .put_item(request, request_payload, &p_params)
+            .await
+            .and_then(|item| Ok(ResponsePayload::success("Item saved").with_data(data_entry("item", &item)?))),
+        Endpoint::PatchItem => processor
+            .patch_item(request, request_payload, &p_params)
+            .await
+            .and_then(|item| Ok(ResponsePayload::success("Item updated").with_data(data_entry("item", &item)?))),
+        Endpoint::DeleteItem => processor
+            .delete_item(request, &q_params, &p_params)
+            .await
+            .and_then(|item| Ok(ResponsePayload::success("Item deleted").with_data(data_entry("item", &item)?))),
+        Endpoint::UploadUrl => processor
+            .upload_url(request, request_payload, &q_params, &p_params)
+            .await
+            .and_then(|url| Ok(ResponsePayload::success("Upload URL created").with_data(data_fields(&url)?))),
+        Endpoint::DownloadUrl => processor
+            .download_url(request, &q_params, &p_params)
+            .await
+            .and_then(|url| Ok(ResponsePayload::success("Download URL created").with_data(data_fields(&url)?))),
+        Endpoint::UploadObject => {
+            let content_type = event.headers().get("content-type").and_then(|v| v.to_str().ok());
+            processor
+                .upload_object(request, &p_params, event.body().to_vec(), content_type)
+                .await
+                .and_then(|object| {
+                    Ok(ResponsePayload::success("Object uploaded").with_data(data_entry("object", &object)?))
+                })
+        }
+        // The only endpoint that answers with raw bytes instead of a JSON payload
+        Endpoint::DownloadObject => match processor.download_object(request, &p_params).await {
+            Ok((metadata, body)) => return object_response(&metadata, body),
+            Err(e) => Err(e),
+        },
+        Endpoint::IssueApiKey => match &ctx.api_keys {
+            Some(api_keys) => api_keys.issue(request.principal.as_ref(), request_payload).await.and_then(|issued| {
+                Ok(ResponsePayload::success("API key issued; store it now, it cannot be shown again")
+                    .with_data(data_fields(&issued)?))
+            }),
+            None => Err(api_keys_disabled()),
+        },
+        Endpoint::RevokeApiKey => match &ctx.api_keys {
+            Some(api_keys) => api_keys.revoke(request.principal.as_ref(), &p_params).await.and_then(|api_key| {
+                Ok(ResponsePayload::success("API key revoked").with_data(data_entry("api_key", &api_key)?))
+            }),
+            None => Err(api_keys_disabled()),
+        },
+    };
+
+    match result {
+        Ok(payload) => json_response(200, &payload),
+        Err(e) => {
+            error!("Processing failed: {}", e);
+            Ok(domain_error_response(&e))
+        }
+    }
+}
+
+fn api_keys_disabled() -> DomainError {
+    DomainError::NotFound("API keys are not enabled".to_string())
+}
+
+/// Serialize a value into a single-entry `ResponsePayload.data` map
+fn data_entry<T: serde::Serialize>(name: &str, value: &T) -> Result<HashMap<String, serde_json::Value>, DomainError> {
+    let value = serde_json::to_value(value)
+        .map_err(|e| DomainError::Internal(format!("Failed to serialize {}: {}", name, e)))?;
+    Ok(HashMap::from([(name.to_string(), value)]))
+}
+
+/// Serialize a struct's fields directly into the `ResponsePayload.data` map
+fn data_fields<T: serde::Serialize>(value: &T) -> Result<HashMap<String, serde_json::Value>, DomainError> {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::Object(fields)) => Ok(fields.into_iter().collect()),
+        Ok(_) => Err(DomainError::Internal("Response data must be an object".to_string())),
+        Err(e) => Err(DomainError::Internal(format!("Failed to serialize response data: {}", e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::mocks::{MockDatabase, MockStorage};
+    use crate::http::auth::JwtAuth;
+    use crate::http::cors::AllowedOrigin;
+    use lambda_http::http;
+    use std::sync::Arc;
+
+    fn body_json(response: &Response<Body>) -> serde_json::Value {
+        match response.body() {
+            Body::Text(text) => serde_json::from_str(text).unwrap(),
+            _ => panic!("expected text body"),
+        }
+    }
+
+    fn request(method: &str, uri: &str) -> Request {
+        http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::Empty)
+            .expect("Failed to build request")
+    }
+
+    #[tokio::test]
+    async fn test_ports_are_reused_across_invocations() {
+        let db = Arc::new(MockDatabase::new());
+        let storage = Arc::new(MockStorage::new());
+        let ctx = Arc::new(AppContext::new(db.clone(), storage.clone()));
+        let http = HttpConfig::default();
+
+        for _ in 0..3 {
+            let response = function_handler(&ctx, &http, request("POST", "/demo")).await.unwrap();
+            assert_eq!(response.status(), 200);
+        }
+
+        // Every invocation went through the single instance built up front
+        assert_eq!(db.call_count(), 3);
+        assert_eq!(storage.call_count(), 3);
+        assert_eq!(Arc::strong_count(&db), 2);
+        assert_eq!(Arc::strong_count(&storage), 2);
+    }
+
+    #[tokio::test]
+    async fn test_correlation_id_is_returned() {
+        let ctx = AppContext::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
+        let http = HttpConfig::default();
+
+        let mut event = request("GET", "/missing");
+        event.headers_mut().insert("x-correlation-id", HeaderValue::from_static("checkout-7f3a"));
+        let response = function_handler(&ctx, &http, event).await.unwrap();
+        assert_eq!(response.headers()["x-correlation-id"], "checkout-7f3a");
+
+        let response = function_handler(&ctx, &http, request("GET", "/missing")).await.unwrap();
+        assert_eq!(response.headers()["x-correlation-id"].len(), 36);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_are_flushed_per_invocation() {
+        use crate::metrics::{InMemoryRecorder, Metrics};
+
+        let recorder = Arc::new(InMemoryRecorder::new());
+        let metrics = Arc::new(Metrics::new("Orders").with_sink(recorder.clone()));
+        let ctx = AppContext::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()))
+            .with_metrics(Some(metrics));
+        let http = HttpConfig::default();
+
+        function_handler(&ctx, &http, request("GET", "/demo")).await.unwrap();
+        assert_eq!(recorder.values("Requests", &[("Route", "GET /demo"), ("StatusCode", "200")]), [1.0]);
+        assert_eq!(recorder.values("Latency", &[("Route", "GET /demo")]).len(), 1);
+
+        function_handler(&ctx, &http, request("GET", "/items/1111/10/extra")).await.unwrap();
+        assert_eq!(recorder.values("Requests", &[("Route", "Unmatched"), ("StatusCode", "404")]), [1.0]);
+        assert_eq!(recorder.values("ColdStart", &[]), [1.0, 0.0]);
+    }
+
+    #[tokio::test]
+    async fn test_named_stage_is_not_part_of_the_route() {
+        use crate::metrics::{InMemoryRecorder, Metrics};
+
+        let recorder = Arc::new(InMemoryRecorder::new());
+        let metrics = Arc::new(Metrics::new("Orders").with_sink(recorder.clone()));
+        let ctx = AppContext::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()))
+            .with_metrics(Some(metrics));
+        let http = HttpConfig::default();
+
+        let rest = serde_json::json!({
+            "resource": "/demo",
+            "path": "/demo",
+            "httpMethod": "GET",
+            "headers": {},
+            "requestContext": {
+                "accountId": "123456789012",
+                "resourceId": "abc",
+                "stage": "dev",
+                "requestId": "c6af9ac6-7b61-11e6-9a41-93e8deadbeef",
+                "identity": {"sourceIp": "203.0.113.7"},
+                "resourcePath": "/demo",
+                "httpMethod": "GET",
+                "apiId": "abc123"
+            },
+            "body": null,
+            "isBase64Encoded": false
+        });
+        let http_api = serde_json::json!({
+            "version": "2.0",
+            "routeKey": "GET /demo",
+            "rawPath": "/dev/demo",
+            "rawQueryString": "",
+            "headers": {},
+            "requestContext": {
+                "apiId": "abc123",
+                "domainName": "abc123.execute-api.eu-west-1.amazonaws.com",
+                "http": {"method": "GET", "path": "/dev/demo", "protocol": "HTTP/1.1", "sourceIp": "198.51.100.20"},
+                "requestId": "JKJaXmPLvHcESHA=",
+                "routeKey": "GET /demo",
+                "stage": "dev"
+            },
+            "isBase64Encoded": false
+        });
+        for event in [rest, http_api] {
+            let event = lambda_http::request::from_str(&event.to_string()).unwrap();
+            assert_eq!(event.uri().path(), "/dev/demo");
+            let response = function_handler(&ctx, &http, event).await.unwrap();
+            assert_eq!(response.status(), 200);
+        }
+        assert_eq!(recorder.values("Requests", &[("Route", "GET /demo"), ("StatusCode", "200")]), [1.0, 1.0]);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_route_returns_404() {
+        let ctx = AppContext::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
+        let response = function_handler(&ctx, &HttpConfig::default(), request("GET", "/missing")).await.unwrap();
+        assert_eq!(response.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_method_returns_405() {
+        let ctx = AppContext::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
+        let response = function_handler(&ctx, &HttpConfig::default(), request("DELETE", "/demo")).await.unwrap();
+        assert_eq!(response.status(), 405);
+        assert_eq!(response.headers().get("Allow").unwrap(), "GET, POST");
+    }
+
+    #[tokio::test]
+    async fn test_preflight_is_answered_without_dispatch() {
+        let db = Arc::new(MockDatabase::new());
+        let ctx = AppContext::new(db.clone(), Arc::new(MockStorage::new()));
+        let cors = CorsPolicy {
+            allowed_origins: vec![AllowedOrigin::parse("https://*.example.com")],
+            allow_credentials: true,
+            ..CorsPolicy::default()
+        };
+        let http = HttpConfig::new(cors);
+
+        let preflight = |origin: &str, uri: &str| {
+            http::Request::builder()
+                .method("OPTIONS")
+                .uri(uri)
+                .header("Origin", origin)
+                .header("Access-Control-Request-Method", "PATCH")
+                .body(Body::Empty)
+                .expect("Failed to build request")
+        };
+
+        let response = function_handler(&ctx, &http, preflight("https://shop.example.com", "/items/1111/10"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 204);
+        assert_eq!(response.headers()["Access-Control-Allow-Origin"], "https://shop.example.com");
+        assert_eq!(response.headers()["Access-Control-Allow-Credentials"], "true");
+        assert_eq!(response.headers()["Access-Control-Allow-Methods"], "GET, PUT, PATCH, DELETE, OPTIONS");
+        assert_eq!(response.headers()["Vary"], "Origin");
+
+        let denied = function_handler(&ctx, &http, preflight("https://evil.com", "/items/1111/10")).await.unwrap();
+        assert_eq!(denied.status(), 204);
+        assert!(!denied.headers().contains_key("Access-Control-Allow-Origin"));
+        assert!(!denied.headers().contains_key("Access-Control-Allow-Methods"));
+
+        let missing = function_handler(&ctx, &http, preflight("https://shop.example.com", "/missing")).await.unwrap();
+        assert_eq!(missing.status(), 404);
+        assert_eq!(db.call_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cors_headers_on_errors_and_downloads() {
+        let storage = MockStorage::new().with_object("demo-bucket", "a.bin", vec![1, 2, 3]);
+        let ctx = AppContext::new(Arc::new(MockDatabase::new()), Arc::new(storage));
+        let http = HttpConfig::default();
+
+        let with_origin = |uri: &str| {
+            http::Request::builder()
+                .method("GET")
+                .uri(uri)
+                .header("Origin", "https://shop.example.com")
+                .body(Body::Empty)
+                .expect("Failed to build request")
+        };
+
+        let download = function_handler(&ctx, &http, with_origin("/objects/a.bin")).await.unwrap();
+        assert_eq!(download.status(), 200);
+        assert_eq!(download.headers()["Access-Control-Allow-Origin"], "*");
+
+        let missing = function_handler(&ctx, &http, with_origin("/missing")).await.unwrap();
+        assert_eq!(missing.status(), 404);
+        assert_eq!(missing.headers()["Access-Control-Allow-Origin"], "*");
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token_is_required_when_auth_is_configured() {
+        use crate::http::auth::tests::{token, AUDIENCE, ISSUER, JWKS};
+
+        let storage = MockStorage::new().with_object("demo-bucket", "a.bin", vec![1, 2, 3]);
+        let ctx = AppContext::new(Arc::new(MockDatabase::new()), Arc::new(storage));
+        let http = HttpConfig::default().with_auth(Some(JwtAuth::new(JWKS, ISSUER, &[AUDIENCE]).unwrap()));
+
+        let with_token = |token: Option<String>| {
+            let mut builder = http::Request::builder().method("GET").uri("/objects/a.bin");
+            if let Some(token) = token {
+                builder = builder.header("Authorization", format!("Bearer {}", token));
+            }
+            builder.body(Body::Empty).expect("Failed to build request")
+        };
+
+        let anonymous = function_handler(&ctx, &http, with_token(None)).await.unwrap();
+        assert_eq!(anonymous.status(), 401);
+        assert_eq!(anonymous.headers()["WWW-Authenticate"], "Bearer error=\"invalid_token\"");
+        assert_eq!(anonymous.headers()["Access-Control-Allow-Origin"], "*");
+        let body = body_json(&anonymous);
+        assert_eq!(body["error_code"], "UNAUTHORIZED");
+
+        let unscoped = function_handler(&ctx, &http, with_token(Some(token(serde_json::json!({}))))).await.unwrap();
+        assert_eq!(unscoped.status(), 403);
+
+        let scoped = token(serde_json::json!({"scope": "objects:read"}));
+        let response = function_handler(&ctx, &http, with_token(Some(scoped))).await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_issue_use_and_revoke() {
+        use crate::application::api_keys::{ApiKeyService, API_KEYS_ADMIN};
+        use crate::domain::api_key::{hash_secret, ApiKey, KEY_ID_ATTRIBUTE};
+
+        // An admin key seeded directly, as done when bootstrapping a table
+        let admin = ApiKey {
+            key_id: "00000000000ad001".to_string(),
+            owner: "ops".to_string(),
+            scopes: vec![API_KEYS_ADMIN.to_string()],
+            disabled: false,
+            expires_at: None,
+            created_at: chrono::Utc::now(),
+            secret_hash: hash_secret("admin-secret"),
+        };
+        let db = MockDatabase::new()
+            .with_table("api-keys", &[KEY_ID_ATTRIBUTE])
+            .with_table("demo-table", &["order_id", "segment"])
+            .with_item("api-keys", admin.to_item());
+        let db = Arc::new(db);
+        let ctx = AppContext::new(db.clone(), Arc::new(MockStorage::new()))
+            .with_api_keys(Some(ApiKeyService::new(db, "api-keys")));
+        let http = HttpConfig::default();
+
+        let call = |method: &str, uri: &str, key: Option<&str>, body: Option<serde_json::Value>| {
+            let mut builder = http::Request::builder().method(method).uri(uri);
+            if let Some(key) = key {
+                builder = builder.header("x-api-key", key);
+            }
+            let body = body.map_or(Body::Empty, |b| Body::Text(serde_json::json!({ "data": b }).to_string()));
+            builder.body(body).expect("Failed to build request")
+        };
+
+        let anonymous = function_handler(&ctx, &http, call("GET", "/items/1111", None, None)).await.unwrap();
+        assert_eq!(anonymous.status(), 401);
+        assert!(!anonymous.headers().contains_key("WWW-Authenticate"));
+
+        let issue = call(
+            "POST",
+            "/api-keys",
+            Some("ak_00000000000ad001.admin-secret"),
+            Some(serde_json::json!({"owner": "reports", "scopes": ["items:read"]})),
+        );
+        let issued = function_handler(&ctx, &http, issue).await.unwrap();
+        assert_eq!(issued.status(), 200);
+        let issued = body_json(&issued);
+        let key = issued["data"]["key"].as_str().unwrap().to_string();
+        let key_id = issued["data"]["key_id"].as_str().unwrap().to_string();
+        assert!(issued["data"].get("secret_hash").is_none());
+
+        let read = function_handler(&ctx, &http, call("GET", "/items/1111", Some(&key), None)).await.unwrap();
+        assert_eq!(read.status(), 200);
+        let write = call("PUT", "/items/1111/10", Some(&key), Some(serde_json::json!({})));
+        assert_eq!(function_handler(&ctx, &http, write).await.unwrap().status(), 403);
+
+        let revoke = call("DELETE", &format!("/api-keys/{}", key_id), Some("ak_00000000000ad001.admin-secret"), None);
+        assert_eq!(function_handler(&ctx, &http, revoke).await.unwrap().status(), 200);
+        let read = function_handler(&ctx, &http, call("GET", "/items/1111", Some(&key), None)).await.unwrap();
+        assert_eq!(read.status(), 401);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_client_gets_429() {
+        use crate::application::rate_limiter::{RateLimiter, COUNTER_KEY_ATTRIBUTE};
+        use crate::domain::rate_limit::RateLimit;
+
+        let db = Arc::new(
+            MockDatabase::new()
+                .with_table("rate-limits", &[COUNTER_KEY_ATTRIBUTE])
+                .with_table("demo-table", &["order_id", "segment"]),
+        );
+        let limiter = RateLimiter::new(db.clone(), "rate-limits").with_route_limit("GET /items/{pk}", RateLimit::new(2, 3600));
+        let ctx = AppContext::new(db, Arc::new(MockStorage::new())).with_rate_limiter(Some(limiter));
+        let http = HttpConfig::default();
+        let from = |ip: &str| {
+            http::Request::builder()
+                .method("GET")
+                .uri("/items/1111")
+                .header("X-Forwarded-For", ip)
+                .body(Body::Empty)
+                .expect("Failed to build request")
+        };
+
+        let first = function_handler(&ctx, &http, from("203.0.113.7")).await.unwrap();
+        assert_eq!(first.status(), 200);
+        assert_eq!(first.headers()["X-RateLimit-Limit"], "2");
+        assert_eq!(first.headers()["X-RateLimit-Remaining"], "1");
+        function_handler(&ctx, &http, from("203.0.113.7")).await.unwrap();
+
+        // A forged chain in front of the address ALB appended does not reset the count
+        let limited = function_handler(&ctx, &http, from("192.0.2.1, 203.0.113.7")).await.unwrap();
+        assert_eq!(limited.status(), 429);
+        assert!(limited.headers().contains_key("Retry-After"));
+        assert_eq!(limited.headers()["X-RateLimit-Remaining"], "0");
+
+        assert_eq!(function_handler(&ctx, &http, from("198.51.100.20")).await.unwrap().status(), 200);
+        // Routes without a limit carry no rate limit headers
+        let demo = function_handler(&ctx, &http, request("GET", "/demo")).await.unwrap();
+        assert!(!demo.headers().contains_key("X-RateLimit-Limit"));
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_replays_post_response() {
+        use crate::application::idempotency::IdempotencyService;
+        use crate::domain::idempotency::IDEMPOTENCY_KEY_ATTRIBUTE;
+
+        let db = Arc::new(MockDatabase::new().with_table("idempotency", &[IDEMPOTENCY_KEY_ATTRIBUTE]));
+        let storage = Arc::new(MockStorage::new());
+        let ctx = AppContext::new(db.clone(), storage.clone())
+            .with_idempotency(Some(IdempotencyService::new(db, "idempotency")));
+        let http = HttpConfig::default();
+        let post = |key: &str, message: &str| {
+            http::Request::builder()
+                .method("POST")
+                .uri("/demo")
+                .header("Idempotency-Key", key)
+                .body(Body::Text(serde_json::json!({ "message": message }).to_string()))
+                .expect("Failed to build request")
+        };
+
+        let first = function_handler(&ctx, &http, post("order-42", "hello")).await.unwrap();
+        assert_eq!(first.status(), 200);
+        let replayed = function_handler(&ctx, &http, post("order-42", "hello")).await.unwrap();
+        assert_eq!(replayed.status(), 200);
+        assert_eq!(replayed.headers()["Idempotent-Replayed"], "true");
+        assert_eq!(replayed.body(), first.body());
+        // The processor ran once
+        assert_eq!(storage.call_count(), 1);
+
+        let reused = function_handler(&ctx, &http, post("order-42", "bye")).await.unwrap();
+        assert_eq!(reused.status(), 422);
+        let invalid = function_handler(&ctx, &http, post("has space", "hello")).await.unwrap();
+        assert_eq!(invalid.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_reused_with_another_query_is_rejected() {
+        use crate::application::idempotency::IdempotencyService;
+        use crate::domain::idempotency::IDEMPOTENCY_KEY_ATTRIBUTE;
+
+        let db = Arc::new(MockDatabase::new().with_table("idempotency", &[IDEMPOTENCY_KEY_ATTRIBUTE]));
+        let ctx = AppContext::new(db.clone(), Arc::new(MockStorage::new()))
+            .with_idempotency(Some(IdempotencyService::new(db, "idempotency")));
+        let http = HttpConfig::default();
+        let post = |uri: &str| {
+            http::Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("Idempotency-Key", "order-42")
+                .body(Body::Text(serde_json::json!({ "message": "hello" }).to_string()))
+                .expect("Failed to build request")
+        };
+
+        assert_eq!(function_handler(&ctx, &http, post("/demo")).await.unwrap().status(), 200);
+        let reused = function_handler(&ctx, &http, post("/demo?health=true")).await.unwrap();
+        assert_eq!(reused.status(), 422);
+        assert!(!reused.headers().contains_key("Idempotent-Replayed"));
+    }
+
+    #[tokio::test]
+    async fn test_list_items_exposes_next_token() {
+        let mut db = MockDatabase::new().with_table("demo-table", &["order_id", "segment"]);
+        for segment in 1..=2 {
+            let mut item = crate::domain::item::Item::new();
+            item.insert("order_id".to_string(), "1111".into());
+            item.insert("segment".to_string(), crate::domain::item::ItemValue::number(segment));
+            db = db.with_item("demo-table", item);
+        }
+        let ctx = AppContext::new(Arc::new(db), Arc::new(MockStorage::new()));
+
+        let event = request("GET", "/items/1111")
+            .with_query_string_parameters(HashMap::from([("limit".to_string(), "1".to_string())]));
+        let response = function_handler(&ctx, &HttpConfig::default(), event).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let body = body_json(&response);
+        assert_eq!(body["data"]["items"].as_array().unwrap().len(), 1);
+        assert!(body["next_token"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_item_crud_round_trip() {
+        let db = MockDatabase::new().with_table("demo-table", &["order_id", "segment"]);
+        let ctx = AppContext::new(Arc::new(db), Arc::new(MockStorage::new()));
+        let http = HttpConfig::default();
+        let call = |method: &str, body: Option<serde_json::Value>| {
+            http::Request::builder()
+                .method(method)
+                .uri("/items/1111/10")
+                .body(body.map_or(Body::Empty, |b| Body::Text(b.to_string())))
+                .expect("Failed to build request")
+        };
+
+        assert_eq!(function_handler(&ctx, &http, call("GET", None)).await.unwrap().status(), 404);
+        let put = serde_json::json!({ "data": { "status": { "S": "open" } } });
+        assert_eq!(function_handler(&ctx, &http, call("PUT", Some(put))).await.unwrap().status(), 200);
+
+        let read = body_json(&function_handler(&ctx, &http, call("GET", None)).await.unwrap());
+        assert_eq!(read["data"]["item"]["status"], serde_json::json!({ "S": "open" }));
+        assert_eq!(read["data"]["item"]["version"], serde_json::json!({ "N": "1" }));
+
+        let patch = serde_json::json!({ "data": { "set": { "status": { "S": "closed" } } } });
+        let patched = body_json(&function_handler(&ctx, &http, call("PATCH", Some(patch))).await.unwrap());
+        assert_eq!(patched["data"]["item"]["status"], serde_json::json!({ "S": "closed" }));
+
+        assert_eq!(function_handler(&ctx, &http, call("DELETE", None)).await.unwrap().status(), 200);
+        assert_eq!(function_handler(&ctx, &http, call("GET", None)).await.unwrap().status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_demo_returns_structured_data() {
+        let ctx = AppContext::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
+        let response = function_handler(&ctx, &HttpConfig::default(), request("GET", "/demo")).await.unwrap();
+        let body = body_json(&response);
+        assert_eq!(body["message"], "Received message: No payload provided");
+        assert_eq!(body["data"]["item"]["status"], "not_found");
+        assert_eq!(body["data"]["object"]["status"], "not_found");
+
+        let root = function_handler(&ctx, &HttpConfig::default(), request("GET", "/")).await.unwrap();
+        assert_eq!(root.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_stale_version_returns_409() {
+        let db = MockDatabase::new().with_table("demo-table", &["order_id", "segment"]);
+        let ctx = AppContext::new(Arc::new(db), Arc::new(MockStorage::new()));
+        let http = HttpConfig::default();
+
+        let put = |body: serde_json::Value| {
+            http::Request::builder()
+                .method("PUT")
+                .uri("/items/1111/10")
+                .body(Body::Text(serde_json::json!({ "data": body }).to_string()))
+                .expect("Failed to build request")
+        };
+
+        let created = function_handler(&ctx, &http, put(serde_json::json!({}))).await.unwrap();
+        assert_eq!(created.status(), 200);
+
+        let response = function_handler(&ctx, &http, put(serde_json::json!({}))).await.unwrap();
+        assert_eq!(response.status(), 409);
+        let body = body_json(&response);
+        assert_eq!(body["error_code"], "CONDITION_FAILED");
+    }
+
+    #[tokio::test]
+    async fn test_patch_and_delete_item() {
+        let db = MockDatabase::new().with_table("demo-table", &["order_id", "segment"]);
+        let ctx = AppContext::new(Arc::new(db), Arc::new(MockStorage::new()));
+        let http = HttpConfig::default();
+
+        let with_body = |method: &str, body: serde_json::Value| {
+            http::Request::builder()
+                .method(method)
+                .uri("/items/1111/10")
+                .body(Body::Text(serde_json::json!({ "data": body }).to_string()))
+                .expect("Failed to build request")
+        };
+
+        let put = function_handler(&ctx, &http, with_body("PUT", serde_json::json!({}))).await.unwrap();
+        assert_eq!(put.status(), 200);
+
+        let patch = with_body("PATCH", serde_json::json!({"add": {"views": {"N": "1"}}}));
+        let patched = function_handler(&ctx, &http, patch).await.unwrap();
+        assert_eq!(patched.status(), 200);
+
+        let deleted = function_handler(&ctx, &http, request("DELETE", "/items/1111/10")).await.unwrap();
+        assert_eq!(deleted.status(), 200);
+        let body = body_json(&deleted);
+        assert_eq!(body["data"]["item"]["views"]["N"], "1");
+
+        let missing = function_handler(&ctx, &http, request("DELETE", "/items/1111/10")).await.unwrap();
+        assert_eq!(missing.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_presigned_upload_and_download_urls() {
+        let storage = MockStorage::new().with_object("demo-bucket", "photos/2024/a.png", vec![0; 16]);
+        let ctx = AppContext::new(Arc::new(MockDatabase::new()), Arc::new(storage));
+        let http = HttpConfig::default();
+
+        let upload = http::Request::builder()
+            .method("POST")
+            .uri("/upload-urls/photos/2024/b.png")
+            .body(Body::Text(
+                serde_json::json!({"data": {"content_type": "image/png", "content_length": 2048}}).to_string(),
+            ))
+            .expect("Failed to build request")
+            .with_query_string_parameters(HashMap::from([("expires_in".to_string(), "60".to_string())]));
+        let response = function_handler(&ctx, &http, upload).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let body = body_json(&response);
+        assert_eq!(body["data"]["url"], "https://demo-bucket.mock-s3.local/photos/2024/b.png?method=PUT&expires_in=60");
+        assert_eq!(body["data"]["method"], "PUT");
+        assert_eq!(body["data"]["headers"]["content-length"], "2048");
+        assert_eq!(body["data"]["expires_in"], 60);
+
+        let download = function_handler(&ctx, &http, request("GET", "/download-urls/photos/2024/a.png")).await.unwrap();
+        assert_eq!(download.status(), 200);
+
+        let missing = function_handler(&ctx, &http, request("GET", "/download-urls/photos/2024/b.png")).await.unwrap();
+        assert_eq!(missing.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_binary_upload_and_download() {
+        let ctx = AppContext::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
+        let http = HttpConfig::default();
+        let bytes = vec![0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0xff];
+
+        let upload = http::Request::builder()
+            .method("PUT")
+            .uri("/objects/images/logo.png")
+            .header("Content-Type", "image/png")
+            .body(Body::Binary(bytes.clone()))
+            .expect("Failed to build request");
+        let response = function_handler(&ctx, &http, upload).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let response = function_handler(&ctx, &http, request("GET", "/objects/images/logo.png")).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["Content-Type"], "image/png");
+        assert_eq!(response.headers()["Content-Length"], "10");
+        assert_eq!(response.headers()["Content-Disposition"], "attachment; filename=\"logo.png\"");
+        assert!(matches!(response.body(), Body::Binary(body) if body == &bytes));
+
+        let missing = function_handler(&ctx, &http, request("GET", "/objects/images/none.png")).await.unwrap();
+        assert_eq!(missing.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_json_body_may_arrive_as_binary() {
+        let db = MockDatabase::new().with_table("demo-table", &["order_id", "segment"]);
+        let ctx = AppContext::new(Arc::new(db), Arc::new(MockStorage::new()));
+        let http = HttpConfig::default();
+
+        let body = serde_json::json!({"data": {"status": {"S": "open"}}}).to_string().into_bytes();
+        let put = http::Request::builder()
+            .method("PUT")
+            .uri("/items/1111/10")
+            .body(Body::Binary(body))
+            .expect("Failed to build request");
+        let response = function_handler(&ctx, &http, put).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let garbage = http::Request::builder()
+            .method("PUT")
+            .uri("/items/1111/10")
+            .body(Body::Binary(vec![0xff, 0xfe]))
+            .expect("Failed to build request");
+        let response = function_handler(&ctx, &http, garbage).await.unwrap();
+        assert_eq!(response.status(), 422);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_body_lists_field_errors() {
+        let ctx = AppContext::new(Arc::new(MockDatabase::new()), Arc::new(MockStorage::new()));
+        let http = HttpConfig::default();
+
+        let wrong_type = http::Request::builder()
+            .method("POST")
+            .uri("/demo")
+            .body(Body::Text(r#"{"message": 42}"#.to_string()))
+            .expect("Failed to build request");
+        let response = function_handler(&ctx, &http, wrong_type).await.unwrap();
+        assert_eq!(response.status(), 422);
+        let body = body_json(&response);
+        assert_eq!(body["error_code"], "INVALID_INPUT");
+        assert_eq!(body["data"]["errors"][0]["field"], "message");
+        assert_eq!(body["data"]["errors"][0]["rule"], "type");
+
+        let upload = http::Request::builder()
+            .method("POST")
+            .uri("/upload-urls/a.bin")
+            .body(Body::Text(r#"{"data": {"content_length": -1, "content_type": 7}}"#.to_string()))
+            .expect("Failed to build request");
+        let response = function_handler(&ctx, &http, upload).await.unwrap();
+        assert_eq!(response.status(), 422);
+        let body = body_json(&response);
+        let errors = body["data"]["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0]["field"], "data.content_length");
+        assert_eq!(errors[1]["field"], "data.content_type");
+    }
+}
@@ -0,0 +1,242 @@
+use crate::domain::request::CREDENTIAL_HEADERS;
+use lambda_http::http::HeaderMap;
+use lambda_http::{Body, Request, RequestExt};
+use serde_json::{Map, Value};
+use tracing::{debug, field, info, info_span, Span};
+
+/// Header carrying the id that ties together one request's logs across services
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+/// Replacement for values that must not reach the logs
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Header and body field names containing any of these, ignoring case and
+/// `-`/`_`, are logged as `[REDACTED]`
+const SENSITIVE_NAMES: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "apikey",
+    "authorization",
+    "credential",
+    "cookie",
+    "signature",
+    "cardnumber",
+    "cvv",
+    "ssn",
+];
+
+/// Longest incoming correlation id that is propagated instead of replaced
+const MAX_CORRELATION_ID_LEN: usize = 128;
+
+/// The caller's `X-Correlation-Id`, or a new one when it sent none (or one
+/// that is empty, too long or not printable ASCII)
+pub fn correlation_id(headers: &HeaderMap) -> String {
+    headers
+        .get(CORRELATION_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_CORRELATION_ID_LEN && id.chars().all(|c| c.is_ascii_graphic()))
+        .map(str::to_string)
+        .unwrap_or_else(new_correlation_id)
+}
+
+/// A random UUID v4
+fn new_correlation_id() -> String {
+    let mut bytes = [0u8; 16];
+    // Without randomness the id only needs to be unlikely to collide within a log search
+    if getrandom::getrandom(&mut bytes).is_err() {
+        bytes = (chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u128).to_be_bytes();
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Span wrapping one invocation, so every log line written while handling it
+/// carries the Lambda request id, the X-Ray trace id and the correlation id.
+/// `route` is recorded once the request is routed.
+pub fn request_span(event: &Request, correlation_id: &str) -> Span {
+    let lambda = event.lambda_context_ref();
+    let trace_id = lambda
+        .and_then(|lambda| lambda.xray_trace_id.as_deref())
+        .or_else(|| event.headers().get("x-amzn-trace-id").and_then(|v| v.to_str().ok()));
+    info_span!(
+        "request",
+        request_id = lambda.map(|lambda| lambda.request_id.as_str()).filter(|id| !id.is_empty()),
+        trace_id,
+        route = field::Empty,
+        correlation_id,
+    )
+}
+
+/// Log the incoming request with credentials and sensitive body fields
+/// redacted; the body is only logged at debug level
+pub fn log_request(event: &Request) {
+    info!(
+        method = %event.method(),
+        path = event.uri().path(),
+        headers = %redacted_headers(event.headers()),
+        "Request received"
+    );
+    debug!(body = %redacted_body(event.body()), "Request body");
+}
+
+/// Headers by lowercase name, sensitive ones redacted
+pub fn redacted_headers(headers: &HeaderMap) -> Value {
+    let mut map = Map::new();
+    for (name, value) in headers {
+        let value = if is_sensitive(name.as_str()) {
+            REDACTED.to_string()
+        } else {
+            String::from_utf8_lossy(value.as_bytes()).into_owned()
+        };
+        map.insert(name.as_str().to_string(), Value::String(value));
+    }
+    Value::Object(map)
+}
+
+/// A JSON body with sensitive fields redacted at any depth; other bodies are
+/// only described by their size, since they may hold anything
+pub fn redacted_body(body: &Body) -> Value {
+    let bytes: &[u8] = match body {
+        Body::Empty => return Value::Null,
+        Body::Text(text) => text.as_bytes(),
+        Body::Binary(bytes) => bytes,
+    };
+    match serde_json::from_slice(bytes) {
+        Ok(mut value) => {
+            redact(&mut value);
+            value
+        }
+        Err(_) => Value::String(format!("<{} bytes>", bytes.len())),
+    }
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if is_sensitive(name) {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    if CREDENTIAL_HEADERS.contains(&name.as_str()) {
+        return true;
+    }
+    let name = name.replace(['-', '_'], "");
+    SENSITIVE_NAMES.iter().any(|sensitive| name.contains(sensitive))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lambda_http::http;
+    use serde_json::json;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_correlation_id_is_propagated_or_generated() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CORRELATION_ID_HEADER, "checkout-7f3a".parse().unwrap());
+        assert_eq!(correlation_id(&headers), "checkout-7f3a");
+
+        headers.insert(CORRELATION_ID_HEADER, "has spaces".parse().unwrap());
+        let generated = correlation_id(&headers);
+        assert_eq!(generated.len(), 36);
+        assert_eq!(&generated[14..15], "4");
+        assert_ne!(correlation_id(&HeaderMap::new()), generated);
+    }
+
+    #[test]
+    fn test_sensitive_values_are_redacted() {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer abc".parse().unwrap());
+        headers.insert("x-api-key", "ak_1.secret".parse().unwrap());
+        headers.insert("x-auth-token", "t".parse().unwrap());
+        headers.insert("accept", "application/json".parse().unwrap());
+        assert_eq!(
+            redacted_headers(&headers),
+            json!({"authorization": REDACTED, "x-api-key": REDACTED, "x-auth-token": REDACTED, "accept": "application/json"})
+        );
+
+        let body = Body::Text(
+            json!({
+                "message": "hi",
+                "data": {"password": "hunter2", "users": [{"name": "a", "apiKey": "k", "client_secret": "s"}]}
+            })
+            .to_string(),
+        );
+        assert_eq!(
+            redacted_body(&body),
+            json!({
+                "message": "hi",
+                "data": {"password": REDACTED, "users": [{"name": "a", "apiKey": REDACTED, "client_secret": REDACTED}]}
+            })
+        );
+        assert_eq!(redacted_body(&Body::Binary(vec![0xff, 0x00])), json!("<2 bytes>"));
+        assert_eq!(redacted_body(&Body::Empty), Value::Null);
+    }
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_log_lines_carry_request_fields() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .with_span_list(true)
+            .with_current_span(false)
+            .with_writer(move || writer.clone())
+            .finish();
+
+        let event: Request = http::Request::builder()
+            .method("POST")
+            .uri("/demo")
+            .header("authorization", "Bearer abc")
+            .header("x-amzn-trace-id", "Root=1-5759e988-bd862e3fe1be46a994272793")
+            .body(Body::Text(r#"{"token": "t0p"}"#.to_string()))
+            .unwrap();
+        tracing::subscriber::with_default(subscriber, || {
+            let span = request_span(&event, "corr-1");
+            span.in_scope(|| {
+                Span::current().record("route", "POST /demo");
+                log_request(&event);
+            });
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+        assert_eq!(line["fields"]["message"], "Request received");
+        assert_eq!(line["spans"][0]["correlation_id"], "corr-1");
+        assert_eq!(line["spans"][0]["route"], "POST /demo");
+        assert_eq!(line["spans"][0]["trace_id"], "Root=1-5759e988-bd862e3fe1be46a994272793");
+        assert!(!output.contains("Bearer abc"));
+        assert!(!output.contains("t0p"));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod auth;
+pub mod cors;
+pub mod context;
+pub mod handler;
+pub mod logging;
+pub mod response;
+pub mod router;
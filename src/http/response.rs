@@ -0,0 +1,347 @@
+use crate::domain::errors::DomainError;
+use crate::domain::idempotency::StoredResponse;
+use crate::domain::models::ResponsePayload;
+use crate::domain::object::ObjectMetadata;
+use crate::domain::rate_limit::RateLimitStatus;
+use lambda_http::http::{HeaderMap, HeaderValue};
+use lambda_http::{Body, Error, Response};
+use std::collections::HashMap;
+use tracing::error;
+
+/// HTTP status code for each domain error variant
+pub fn status_for(err: &DomainError) -> u16 {
+    match err {
+        DomainError::NotFound(_) => 404,
+        DomainError::Conflict(_) | DomainError::ConditionFailed(_) | DomainError::TransactionCanceled { .. } => 409,
+        DomainError::Validation(_) => 400,
+        DomainError::InvalidInput { .. } => 422,
+        DomainError::Throttled(_) => 429,
+        DomainError::Unauthorized(_) => 401,
+        DomainError::Forbidden(_) => 403,
+        DomainError::Upstream(_) => 502,
+        DomainError::Internal(_) => 500,
+    }
+}
+
+/// Create an error response from a domain error.
+///
+/// Details of 5xx errors are logged but not returned to the client.
+pub fn domain_error_response(err: &DomainError) -> Response<Body> {
+    let status = status_for(err);
+    let message = if status >= 500 {
+        error!("Request failed: {}", err);
+        match err {
+            DomainError::Upstream(_) => "A downstream service failed",
+            _ => "Internal server error",
+        }
+    } else {
+        err.message()
+    };
+
+    let mut payload = error_payload(err.code(), message);
+    payload.data = match err {
+        DomainError::TransactionCanceled { reasons, .. } => Some(HashMap::from([(
+            "cancellation_reasons".to_string(),
+            serde_json::to_value(reasons).unwrap_or_default(),
+        )])),
+        DomainError::InvalidInput { errors, .. } => Some(HashMap::from([(
+            "errors".to_string(),
+            serde_json::to_value(errors).unwrap_or_default(),
+        )])),
+        _ => None,
+    };
+    error_response(status, &payload)
+}
+
+fn error_payload(error_code: &str, message: &str) -> ResponsePayload {
+    ResponsePayload {
+        status: "error".to_string(),
+        message: message.to_string(),
+        data: None,
+        error_code: Some(error_code.to_string()),
+        next_token: None,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+/// Serialize a payload into a JSON response
+pub fn json_response(status: u16, payload: &ResponsePayload) -> Result<Response<Body>, Error> {
+    let response_body = serde_json::to_string(payload)
+        .map_err(|e| Error::from(format!("Failed to serialize response: {}", e)))?;
+
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::Text(response_body))
+        .map_err(|e| Error::from(format!("Failed to build response: {}", e)))
+}
+
+/// Return an object's bytes as a binary download.
+///
+/// `Body::Binary` is base64-encoded by lambda_http and flagged with
+/// `isBase64Encoded`, which API Gateway and Function URLs decode for the client.
+pub fn object_response(metadata: &ObjectMetadata, body: Vec<u8>) -> Result<Response<Body>, Error> {
+    let mut builder = Response::builder()
+        .status(200)
+        .header("Content-Type", metadata.content_type.as_deref().unwrap_or("application/octet-stream"))
+        .header("Content-Length", body.len())
+        .header("Content-Disposition", content_disposition(&metadata.key));
+    if let Some(etag) = &metadata.etag {
+        builder = builder.header("ETag", etag);
+    }
+    builder
+        .body(Body::Binary(body))
+        .map_err(|e| Error::from(format!("Failed to build response: {}", e)))
+}
+
+/// `attachment` disposition named after the last segment of `key`.
+///
+/// Non-ASCII names get an ASCII `filename` fallback plus an RFC 5987 `filename*`.
+fn content_disposition(key: &str) -> String {
+    let name = key.rsplit('/').next().filter(|n| !n.is_empty()).unwrap_or("download");
+    let fallback: String = name
+        .chars()
+        .map(|c| if (c.is_ascii_graphic() || c == ' ') && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+    if fallback == name {
+        return format!("attachment; filename=\"{}\"", name);
+    }
+
+    let encoded: String = name
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'!' | b'#' | b'$' | b'&' | b'+' | b'-' | b'.' | b'^'
+            | b'_' | b'`' | b'|' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
+
+/// `429 THROTTLED` for a client over its rate limit, with `Retry-After`
+pub fn rate_limited_response(status: &RateLimitStatus) -> Response<Body> {
+    let retry_after = status.retry_after.unwrap_or(status.reset_after);
+    let mut response = domain_error_response(&DomainError::Throttled(format!(
+        "Rate limit of {} requests exceeded, retry in {} seconds",
+        status.limit, retry_after
+    )));
+    rate_limit_headers(status, response.headers_mut());
+    response.headers_mut().insert("Retry-After", HeaderValue::from(retry_after));
+    response
+}
+
+/// Report a client's rate limit: `X-RateLimit-Limit`, `X-RateLimit-Remaining`
+/// and `X-RateLimit-Reset` (seconds until the current window ends)
+pub fn rate_limit_headers(status: &RateLimitStatus, headers: &mut HeaderMap) {
+    headers.insert("X-RateLimit-Limit", HeaderValue::from(status.limit));
+    headers.insert("X-RateLimit-Remaining", HeaderValue::from(status.remaining));
+    headers.insert("X-RateLimit-Reset", HeaderValue::from(status.reset_after));
+}
+
+/// Capture a response for replay to requests repeating its `Idempotency-Key`
+pub fn stored_response(response: &Response<Body>) -> StoredResponse {
+    StoredResponse {
+        status: response.status().as_u16(),
+        headers: response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| value.to_str().ok().map(|v| (name.as_str().to_string(), v.to_string())))
+            .collect(),
+        body: response.body().to_vec(),
+        is_text: matches!(response.body(), Body::Text(_)),
+    }
+}
+
+/// Rebuild a stored response, marked with `Idempotent-Replayed: true`
+pub fn replayed_response(stored: &StoredResponse) -> Response<Body> {
+    let body = match (stored.is_text, stored.body.is_empty()) {
+        (true, _) => Body::Text(String::from_utf8_lossy(&stored.body).into_owned()),
+        (false, true) => Body::Empty,
+        (false, false) => Body::Binary(stored.body.clone()),
+    };
+    let mut builder = Response::builder().status(stored.status);
+    for (name, value) in &stored.headers {
+        builder = builder.header(name, value);
+    }
+    let mut response = builder
+        .body(body)
+        .unwrap_or_else(|_| domain_error_response(&DomainError::Internal("Stored response is invalid".to_string())));
+    response
+        .headers_mut()
+        .insert("Idempotent-Replayed", HeaderValue::from_static("true"));
+    response
+}
+
+/// Create an error response with the given HTTP status code and error code
+pub fn create_error_response(status: u16, error_code: &str, message: &str) -> Response<Body> {
+    error_response(status, &error_payload(error_code, message))
+}
+
+fn error_response(status: u16, payload: &ResponsePayload) -> Response<Body> {
+    let error_body = serde_json::to_string(payload).unwrap_or_else(|_| {
+        r#"{"status":"error","message":"Failed to serialize error response","error_code":"INTERNAL_ERROR","timestamp":"1970-01-01T00:00:00Z"}"#.to_string()
+    });
+
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::Text(error_body))
+        .unwrap_or_else(|_| {
+            Response::builder()
+                .status(500)
+                .body(Body::Text("Internal server error".to_string()))
+                .unwrap()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::errors::{CancellationReason, FieldError};
+
+    fn body_json(response: &Response<Body>) -> serde_json::Value {
+        match response.body() {
+            Body::Text(text) => serde_json::from_str(text).unwrap(),
+            _ => panic!("expected text body"),
+        }
+    }
+
+    #[test]
+    fn test_status_for_each_variant() {
+        assert_eq!(status_for(&DomainError::NotFound("x".into())), 404);
+        assert_eq!(status_for(&DomainError::Conflict("x".into())), 409);
+        assert_eq!(status_for(&DomainError::ConditionFailed("x".into())), 409);
+        assert_eq!(status_for(&DomainError::Validation("x".into())), 400);
+        let invalid = DomainError::InvalidInput { message: "x".into(), errors: Vec::new() };
+        assert_eq!(status_for(&invalid), 422);
+        assert_eq!(status_for(&DomainError::Throttled("x".into())), 429);
+        assert_eq!(status_for(&DomainError::Unauthorized("x".into())), 401);
+        assert_eq!(status_for(&DomainError::Forbidden("x".into())), 403);
+        assert_eq!(status_for(&DomainError::Upstream("x".into())), 502);
+        assert_eq!(status_for(&DomainError::Internal("x".into())), 500);
+    }
+
+    #[test]
+    fn test_domain_error_response_body() {
+        let response = domain_error_response(&DomainError::NotFound("Item 42 not found".into()));
+        assert_eq!(response.status(), 404);
+        let body = body_json(&response);
+        assert_eq!(body["status"], "error");
+        assert_eq!(body["error_code"], "NOT_FOUND");
+        assert_eq!(body["message"], "Item 42 not found");
+    }
+
+    #[test]
+    fn test_transaction_canceled_lists_reasons() {
+        let err = DomainError::TransactionCanceled {
+            message: "Transaction canceled".into(),
+            reasons: vec![CancellationReason {
+                index: 1,
+                code: "ConditionalCheckFailed".into(),
+                message: None,
+            }],
+        };
+        let response = domain_error_response(&err);
+        assert_eq!(response.status(), 409);
+        let body = body_json(&response);
+        assert_eq!(body["error_code"], "TRANSACTION_CANCELED");
+        assert_eq!(body["data"]["cancellation_reasons"][0]["index"], 1);
+        assert_eq!(body["data"]["cancellation_reasons"][0]["code"], "ConditionalCheckFailed");
+    }
+
+    #[test]
+    fn test_invalid_input_lists_field_errors() {
+        let err = DomainError::InvalidInput {
+            message: "Request body is invalid".into(),
+            errors: vec![FieldError {
+                field: "data.content_length".into(),
+                rule: "required".into(),
+                message: "content_length is required".into(),
+            }],
+        };
+        let response = domain_error_response(&err);
+        assert_eq!(response.status(), 422);
+        let body = body_json(&response);
+        assert_eq!(body["error_code"], "INVALID_INPUT");
+        assert_eq!(body["data"]["errors"][0]["field"], "data.content_length");
+        assert_eq!(body["data"]["errors"][0]["rule"], "required");
+    }
+
+    #[test]
+    fn test_server_errors_hide_details() {
+        let response = domain_error_response(&DomainError::Internal("secret table name".into()));
+        assert_eq!(response.status(), 500);
+        let body = body_json(&response);
+        assert_eq!(body["error_code"], "INTERNAL_ERROR");
+        assert_eq!(body["message"], "Internal server error");
+    }
+
+    #[test]
+    fn test_object_response_headers() {
+        let metadata = ObjectMetadata {
+            key: "reports/2024/q1.pdf".into(),
+            size: 3,
+            etag: Some("\"abc\"".into()),
+            content_type: Some("application/pdf".into()),
+            last_modified: None,
+            metadata: HashMap::new(),
+        };
+        let response = object_response(&metadata, vec![1, 2, 3]).unwrap();
+        let headers = response.headers();
+        assert_eq!(headers["Content-Type"], "application/pdf");
+        assert_eq!(headers["Content-Length"], "3");
+        assert_eq!(headers["Content-Disposition"], "attachment; filename=\"q1.pdf\"");
+        assert_eq!(headers["ETag"], "\"abc\"");
+        assert!(matches!(response.body(), Body::Binary(bytes) if bytes == &[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_rate_limited_response_headers() {
+        let status = RateLimitStatus {
+            limit: 100,
+            remaining: 0,
+            reset_after: 20,
+            retry_after: Some(35),
+        };
+        let response = rate_limited_response(&status);
+        assert_eq!(response.status(), 429);
+        assert_eq!(body_json(&response)["error_code"], "THROTTLED");
+        let headers = response.headers();
+        assert_eq!(headers["Retry-After"], "35");
+        assert_eq!(headers["X-RateLimit-Limit"], "100");
+        assert_eq!(headers["X-RateLimit-Remaining"], "0");
+        assert_eq!(headers["X-RateLimit-Reset"], "20");
+    }
+
+    #[test]
+    fn test_stored_response_replays_exactly() {
+        let original = create_error_response(404, "NOT_FOUND", "Item not found");
+        let replayed = replayed_response(&stored_response(&original));
+        assert_eq!(replayed.status(), 404);
+        assert_eq!(replayed.headers()["Content-Type"], "application/json");
+        assert_eq!(replayed.headers()["Idempotent-Replayed"], "true");
+        assert_eq!(body_json(&replayed), body_json(&original));
+
+        let metadata = ObjectMetadata {
+            key: "a.bin".into(),
+            size: 2,
+            etag: None,
+            content_type: None,
+            last_modified: None,
+            metadata: HashMap::new(),
+        };
+        let binary = object_response(&metadata, vec![0, 159]).unwrap();
+        let replayed = replayed_response(&stored_response(&binary));
+        assert!(matches!(replayed.body(), Body::Binary(bytes) if bytes == &[0, 159]));
+    }
+
+    #[test]
+    fn test_content_disposition_escapes_names() {
+        assert_eq!(content_disposition("a/\"b\".txt"), "attachment; filename=\"_b_.txt\"; filename*=UTF-8''%22b%22.txt");
+        assert_eq!(
+            content_disposition("café.txt"),
+            "attachment; filename=\"caf_.txt\"; filename*=UTF-8''caf%C3%A9.txt"
+        );
+        assert_eq!(content_disposition("folder/"), "attachment; filename=\"download\"");
+    }
+}
@@ -0,0 +1,299 @@
+use lambda_http::http::Method;
+use std::collections::HashMap;
+
+/// A single segment of a route template
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// Matches the segment text exactly, e.g. `items`
+    Literal(String),
+    /// Captures one segment, e.g. `{id}`
+    Param(String),
+    /// Captures the rest of the path including slashes, e.g. `{key+}`.
+    /// Only allowed as the last segment of a template.
+    Greedy(String),
+}
+
+struct Route<T> {
+    method: Method,
+    /// `<METHOD> <template>`, e.g. `GET /items/{pk}`
+    key: String,
+    segments: Vec<Segment>,
+    target: T,
+}
+
+impl<T> Route<T> {
+    /// Match the route against the split request path, returning the captured parameters
+    fn matches(&self, path: &[&str]) -> Option<HashMap<String, String>> {
+        let mut params = HashMap::new();
+
+        for (index, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Literal(literal) => {
+                    if path.get(index) != Some(&literal.as_str()) {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    let value = path.get(index).filter(|value| !value.is_empty())?;
+                    params.insert(name.clone(), (*value).to_string());
+                }
+                Segment::Greedy(name) => {
+                    let rest = path.get(index..).unwrap_or_default();
+                    if rest.is_empty() || rest.iter().all(|value| value.is_empty()) {
+                        return None;
+                    }
+                    params.insert(name.clone(), rest.join("/"));
+                    return Some(params);
+                }
+            }
+        }
+
+        (path.len() == self.segments.len()).then_some(params)
+    }
+
+    /// Literal segments rank higher than parameters so that `/items/latest`
+    /// wins over `/items/{id}` regardless of registration order
+    fn specificity(&self) -> usize {
+        self.segments
+            .iter()
+            .filter(|segment| matches!(segment, Segment::Literal(_)))
+            .count()
+    }
+}
+
+/// Result of a successful route lookup
+#[derive(Debug)]
+pub struct RouteMatch<'a, T> {
+    pub target: &'a T,
+    /// The matched route as `<METHOD> <template>`, e.g. `GET /items/{pk}`
+    pub route_key: &'a str,
+    pub path_params: HashMap<String, String>,
+}
+
+/// Reasons a request could not be routed
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteError {
+    /// No template matches the request path
+    NotFound,
+    /// The path matches, but not with this HTTP method
+    MethodNotAllowed { allowed: Vec<Method> },
+}
+
+/// Method and path-template router.
+///
+/// Templates are `/`-separated; `{name}` captures one segment and a trailing
+/// `{name+}` captures the remainder of the path. Captured values are returned
+/// as the path-parameter map expected by `RequestProcessor`.
+pub struct Router<T> {
+    routes: Vec<Route<T>>,
+}
+
+impl<T> Default for Router<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Router<T> {
+    pub fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    /// Register `target` for `method` requests whose path matches `template`.
+    ///
+    /// Panics if the template is malformed, since routes are declared at startup.
+    pub fn route(mut self, method: Method, template: &str, target: T) -> Self {
+        let segments = parse_template(template)
+            .unwrap_or_else(|e| panic!("Invalid route template '{}': {}", template, e));
+        let key = format!("{} {}", method, template);
+        self.routes.push(Route {
+            method,
+            key,
+            segments,
+            target,
+        });
+        self
+    }
+
+    /// Methods registered for templates matching `path`, in registration order;
+    /// empty if no template matches
+    pub fn allowed_methods(&self, path: &str) -> Vec<Method> {
+        let path_segments = split_path(path);
+        let mut allowed: Vec<Method> = Vec::new();
+        for route in &self.routes {
+            if route.matches(&path_segments).is_some() && !allowed.contains(&route.method) {
+                allowed.push(route.method.clone());
+            }
+        }
+        allowed
+    }
+
+    /// Find the route for a request.
+    ///
+    /// `HEAD` requests fall back to `GET` routes when no explicit `HEAD` route exists.
+    pub fn resolve(&self, method: &Method, path: &str) -> Result<RouteMatch<'_, T>, RouteError> {
+        let path_segments = split_path(path);
+
+        let mut candidates: Vec<(&Route<T>, HashMap<String, String>)> = self
+            .routes
+            .iter()
+            .filter_map(|route| route.matches(&path_segments).map(|params| (route, params)))
+            .collect();
+
+        if candidates.is_empty() {
+            return Err(RouteError::NotFound);
+        }
+
+        candidates.sort_by_key(|(route, _)| std::cmp::Reverse(route.specificity()));
+
+        let exact = candidates.iter().position(|(route, _)| route.method == method);
+        let fallback = || {
+            (method == Method::HEAD)
+                .then(|| candidates.iter().position(|(route, _)| route.method == Method::GET))
+                .flatten()
+        };
+
+        match exact.or_else(fallback) {
+            Some(index) => {
+                let (route, path_params) = candidates.swap_remove(index);
+                Ok(RouteMatch {
+                    target: &route.target,
+                    route_key: &route.key,
+                    path_params,
+                })
+            }
+            None => {
+                let mut allowed: Vec<Method> = Vec::new();
+                for (route, _) in &candidates {
+                    if !allowed.contains(&route.method) {
+                        allowed.push(route.method.clone());
+                    }
+                }
+                Err(RouteError::MethodNotAllowed { allowed })
+            }
+        }
+    }
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    let trimmed = path.trim_matches('/');
+    if trimmed.is_empty() {
+        Vec::new()
+    } else {
+        trimmed.split('/').collect()
+    }
+}
+
+fn parse_template(template: &str) -> Result<Vec<Segment>, String> {
+    let parts = split_path(template);
+    let mut segments = Vec::with_capacity(parts.len());
+
+    for (index, part) in parts.iter().enumerate() {
+        let segment = match part.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+            Some(name) if name.ends_with('+') => {
+                if index != parts.len() - 1 {
+                    return Err("greedy parameter must be the last segment".to_string());
+                }
+                Segment::Greedy(name.trim_end_matches('+').to_string())
+            }
+            Some(name) => Segment::Param(name.to_string()),
+            None => Segment::Literal(part.to_string()),
+        };
+
+        if let Segment::Param(name) | Segment::Greedy(name) = &segment {
+            if name.is_empty() {
+                return Err("parameter name cannot be empty".to_string());
+            }
+        }
+        segments.push(segment);
+    }
+
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router() -> Router<&'static str> {
+        Router::new()
+            .route(Method::GET, "/", "root")
+            .route(Method::GET, "/items/{id}", "get_item")
+            .route(Method::PUT, "/items/{id}", "put_item")
+            .route(Method::GET, "/items/latest", "latest_item")
+            .route(Method::POST, "/objects/{bucket}/{key+}", "put_object")
+    }
+
+    #[test]
+    fn test_resolve_literal_root() {
+        let router = router();
+        let matched = router.resolve(&Method::GET, "/").unwrap();
+        assert_eq!(*matched.target, "root");
+        assert!(matched.path_params.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_fills_path_params() {
+        let router = router();
+        let matched = router.resolve(&Method::PUT, "/items/42/").unwrap();
+        assert_eq!(*matched.target, "put_item");
+        assert_eq!(matched.route_key, "PUT /items/{id}");
+        assert_eq!(matched.path_params.get("id").unwrap(), "42");
+    }
+
+    #[test]
+    fn test_literal_beats_param() {
+        let router = router();
+        let matched = router.resolve(&Method::GET, "/items/latest").unwrap();
+        assert_eq!(*matched.target, "latest_item");
+    }
+
+    #[test]
+    fn test_greedy_param_captures_rest_of_path() {
+        let router = router();
+        let matched = router.resolve(&Method::POST, "/objects/my-bucket/a/b/c.txt").unwrap();
+        assert_eq!(*matched.target, "put_object");
+        assert_eq!(matched.path_params.get("bucket").unwrap(), "my-bucket");
+        assert_eq!(matched.path_params.get("key").unwrap(), "a/b/c.txt");
+    }
+
+    #[test]
+    fn test_not_found() {
+        let router = router();
+        assert_eq!(router.resolve(&Method::GET, "/missing").unwrap_err(), RouteError::NotFound);
+        assert_eq!(router.resolve(&Method::POST, "/objects/my-bucket").unwrap_err(), RouteError::NotFound);
+    }
+
+    #[test]
+    fn test_method_not_allowed_lists_methods() {
+        let router = router();
+        let err = router.resolve(&Method::DELETE, "/items/42").unwrap_err();
+        assert_eq!(
+            err,
+            RouteError::MethodNotAllowed {
+                allowed: vec![Method::GET, Method::PUT]
+            }
+        );
+    }
+
+    #[test]
+    fn test_allowed_methods() {
+        let router = router();
+        assert_eq!(router.allowed_methods("/items/42"), vec![Method::GET, Method::PUT]);
+        assert_eq!(router.allowed_methods("/items/latest"), vec![Method::GET, Method::PUT]);
+        assert!(router.allowed_methods("/missing").is_empty());
+    }
+
+    #[test]
+    fn test_head_falls_back_to_get() {
+        let router = router();
+        let matched = router.resolve(&Method::HEAD, "/items/42").unwrap();
+        assert_eq!(*matched.target, "get_item");
+    }
+
+    #[test]
+    #[should_panic(expected = "greedy parameter")]
+    fn test_greedy_param_must_be_last() {
+        let _ = Router::new().route(Method::GET, "/{key+}/meta", ());
+    }
+}
@@ -0,0 +1,83 @@
+use crate::domain::errors::DomainError;
+use aws_sdk_dynamodb::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use std::error::Error as StdError;
+use std::fmt::Debug;
+
+/// Classify an AWS SDK error into a `DomainError`.
+///
+/// Service errors are classified by their AWS error code; transport failures
+/// (timeouts, dispatch and unparseable responses) are reported as `Upstream`.
+pub(crate) fn classify_sdk_error<E, R>(service: &str, err: SdkError<E, R>) -> DomainError
+where
+    E: ProvideErrorMetadata + StdError + 'static,
+    R: Debug + 'static,
+{
+    let detail = format!("{} error: {}", service, DisplayErrorContext(&err));
+
+    match &err {
+        SdkError::ServiceError(_) => match err.code() {
+            Some(code) => classify_code(code, detail),
+            None => DomainError::Upstream(detail),
+        },
+        SdkError::ConstructionFailure(_) => DomainError::Internal(detail),
+        _ => DomainError::Upstream(detail),
+    }
+}
+
+/// Map an AWS error code to a `DomainError` variant
+pub(crate) fn classify_code(code: &str, detail: String) -> DomainError {
+    match code {
+        // S3
+        "NoSuchKey" | "NoSuchBucket" | "NotFound" | "NoSuchUpload" => DomainError::NotFound(detail),
+        "PreconditionFailed" => DomainError::ConditionFailed(detail),
+        "InvalidArgument" | "InvalidRequest" | "EntityTooLarge" | "KeyTooLongError" => DomainError::Validation(detail),
+        "SlowDown" => DomainError::Throttled(detail),
+
+        // DynamoDB
+        "ConditionalCheckFailedException" => DomainError::ConditionFailed(detail),
+        "TransactionConflictException"
+        | "TransactionInProgressException" => DomainError::Conflict(detail),
+        "ProvisionedThroughputExceededException" | "RequestLimitExceeded" => DomainError::Throttled(detail),
+        "ValidationException" | "ItemCollectionSizeLimitExceededException" => DomainError::Validation(detail),
+        // A missing table is a deployment problem, not a missing resource from the caller's point of view
+        "ResourceNotFoundException" => DomainError::Internal(detail),
+
+        // SES
+        "MessageRejected" => DomainError::Validation(detail),
+        // Unverified senders and missing templates or configuration sets are deployment problems
+        "MailFromDomainNotVerifiedException"
+        | "TemplateDoesNotExist"
+        | "ConfigurationSetDoesNotExist" => DomainError::Internal(detail),
+
+        // Secrets Manager and SSM: keys the role cannot use are deployment problems
+        "DecryptionFailure" | "InvalidKeyId" | "InvalidKeyIdException" => DomainError::Internal(detail),
+
+        // Shared
+        "ThrottlingException" | "Throttling" | "TooManyRequestsException" => DomainError::Throttled(detail),
+        // Permission failures belong to this function's IAM role, not to the caller
+        "AccessDenied" | "AccessDeniedException" | "UnrecognizedClientException" | "InvalidAccessKeyId"
+        | "SignatureDoesNotMatch" | "ExpiredToken" | "ExpiredTokenException" => DomainError::Internal(detail),
+
+        _ => DomainError::Upstream(detail),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_code() {
+        let detail = || "detail".to_string();
+        assert!(matches!(classify_code("NoSuchKey", detail()), DomainError::NotFound(_)));
+        assert!(matches!(classify_code("ConditionalCheckFailedException", detail()), DomainError::ConditionFailed(_)));
+        assert!(matches!(classify_code("ProvisionedThroughputExceededException", detail()), DomainError::Throttled(_)));
+        assert!(matches!(classify_code("SlowDown", detail()), DomainError::Throttled(_)));
+        assert!(matches!(classify_code("ValidationException", detail()), DomainError::Validation(_)));
+        assert!(matches!(classify_code("AccessDeniedException", detail()), DomainError::Internal(_)));
+        assert!(matches!(classify_code("MessageRejected", detail()), DomainError::Validation(_)));
+        assert!(matches!(classify_code("TemplateDoesNotExist", detail()), DomainError::Internal(_)));
+        assert!(matches!(classify_code("DecryptionFailure", detail()), DomainError::Internal(_)));
+        assert!(matches!(classify_code("InternalServerError", detail()), DomainError::Upstream(_)));
+    }
+}
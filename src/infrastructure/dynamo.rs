@@ -1,72 +1,548 @@
+use crate::domain::batch::{TransactItem, WriteRequest, BATCH_GET_LIMIT, BATCH_WRITE_LIMIT, TRANSACT_WRITE_LIMIT};
+use crate::domain::condition::{Condition, PutOptions};
+use crate::domain::errors::{CancellationReason, DomainError, DomainResult};
+use crate::domain::item::{Item, ItemValue};
 use crate::domain::ports::DatabasePort;
+use crate::domain::query::{decode_token, encode_token, Page, QueryRequest, ScanRequest};
+use crate::domain::update::{ReturnValues, UpdateRequest};
+use crate::metrics::{Metered, Metrics};
+use super::aws_error::classify_sdk_error;
+use super::expression::ExpressionBuilder;
+use super::retry::{send_in_chunks, RetryPolicy};
 use async_trait::async_trait;
-use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::primitives::Blob;
+use aws_sdk_dynamodb::operation::transact_write_items::TransactWriteItemsError;
+use aws_sdk_dynamodb::types::{
+    self as ddb, AttributeValue, KeysAndAttributes, ReturnValue, TransactWriteItem,
+};
 use aws_sdk_dynamodb::Client;
 use std::collections::HashMap;
-use std::error::Error as StdError;
+use std::sync::Arc;
 
 pub struct DynamoDbAdapter {
     client: Client,
+    retry: RetryPolicy,
+    metrics: Option<Arc<Metrics>>,
 }
 
 impl DynamoDbAdapter {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            retry: RetryPolicy::default(),
+            metrics: None,
+        }
+    }
+
+    /// Backoff used to resend unprocessed batch items
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Record the latency and outcome of every DynamoDB call
+    pub fn with_metrics(mut self, metrics: Option<Arc<Metrics>>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+}
+
+/// Convert a domain value into a DynamoDB attribute value
+pub(crate) fn to_attribute_value(value: ItemValue) -> AttributeValue {
+    match value {
+        ItemValue::String(s) => AttributeValue::S(s),
+        ItemValue::Number(n) => AttributeValue::N(n),
+        ItemValue::Binary(b) => AttributeValue::B(Blob::new(b)),
+        ItemValue::Bool(b) => AttributeValue::Bool(b),
+        ItemValue::Null => AttributeValue::Null(true),
+        ItemValue::Map(m) => AttributeValue::M(to_attribute_map(m)),
+        ItemValue::List(l) => AttributeValue::L(l.into_iter().map(to_attribute_value).collect()),
+        ItemValue::StringSet(ss) => AttributeValue::Ss(ss),
+        ItemValue::NumberSet(ns) => AttributeValue::Ns(ns),
+        ItemValue::BinarySet(bs) => AttributeValue::Bs(bs.into_iter().map(Blob::new).collect()),
+    }
+}
+
+/// Convert a DynamoDB attribute value into a domain value
+pub(crate) fn from_attribute_value(value: AttributeValue) -> DomainResult<ItemValue> {
+    Ok(match value {
+        AttributeValue::S(s) => ItemValue::String(s),
+        AttributeValue::N(n) => ItemValue::Number(n),
+        AttributeValue::B(b) => ItemValue::Binary(b.into_inner()),
+        AttributeValue::Bool(b) => ItemValue::Bool(b),
+        AttributeValue::Null(_) => ItemValue::Null,
+        AttributeValue::M(m) => ItemValue::Map(from_attribute_map(m)?),
+        AttributeValue::L(l) => ItemValue::List(
+            l.into_iter().map(from_attribute_value).collect::<DomainResult<_>>()?,
+        ),
+        AttributeValue::Ss(ss) => ItemValue::StringSet(ss),
+        AttributeValue::Ns(ns) => ItemValue::NumberSet(ns),
+        AttributeValue::Bs(bs) => ItemValue::BinarySet(bs.into_iter().map(Blob::into_inner).collect()),
+        other => {
+            return Err(DomainError::Upstream(format!(
+                "DynamoDB error: unsupported attribute type {:?}",
+                other
+            )))
+        }
+    })
+}
+
+pub(crate) fn to_attribute_map(item: Item) -> HashMap<String, AttributeValue> {
+    item.into_iter().map(|(k, v)| (k, to_attribute_value(v))).collect()
+}
+
+pub(crate) fn from_attribute_map(item: HashMap<String, AttributeValue>) -> DomainResult<Item> {
+    item.into_iter()
+        .map(|(k, v)| from_attribute_value(v).map(|v| (k, v)))
+        .collect()
+}
+
+/// Turn an incoming continuation token into an `ExclusiveStartKey`
+fn start_key(next_token: Option<&str>) -> DomainResult<Option<HashMap<String, AttributeValue>>> {
+    next_token
+        .map(|token| decode_token(token).map(to_attribute_map))
+        .transpose()
+}
+
+/// Build a page from the items and `LastEvaluatedKey` of a response
+fn to_page(
+    items: Option<Vec<HashMap<String, AttributeValue>>>,
+    last_evaluated_key: Option<HashMap<String, AttributeValue>>,
+) -> DomainResult<Page> {
+    let items = items
+        .unwrap_or_default()
+        .into_iter()
+        .map(from_attribute_map)
+        .collect::<DomainResult<Vec<_>>>()?;
+    let next_token = last_evaluated_key
+        .map(from_attribute_map)
+        .transpose()?
+        .map(|key| encode_token(&key));
+
+    Ok(Page { items, next_token })
+}
+
+/// DynamoDB takes `i32` limits; clamp instead of overflowing
+fn to_limit(limit: Option<u32>) -> Option<i32> {
+    limit.map(|l| l.min(i32::MAX as u32) as i32)
+}
+
+fn to_return_value(return_values: ReturnValues) -> ReturnValue {
+    match return_values {
+        ReturnValues::None => ReturnValue::None,
+        ReturnValues::AllOld => ReturnValue::AllOld,
+        ReturnValues::UpdatedOld => ReturnValue::UpdatedOld,
+        ReturnValues::AllNew => ReturnValue::AllNew,
+        ReturnValues::UpdatedNew => ReturnValue::UpdatedNew,
     }
 }
 
+fn build_error(e: impl std::fmt::Display) -> DomainError {
+    DomainError::Internal(format!("DynamoDB error: invalid request: {}", e))
+}
+
+fn to_write_request(request: WriteRequest) -> DomainResult<ddb::WriteRequest> {
+    Ok(match request {
+        WriteRequest::Put(item) => ddb::WriteRequest::builder()
+            .put_request(
+                ddb::PutRequest::builder()
+                    .set_item(Some(to_attribute_map(item)))
+                    .build()
+                    .map_err(build_error)?,
+            )
+            .build(),
+        WriteRequest::Delete(key) => ddb::WriteRequest::builder()
+            .delete_request(
+                ddb::DeleteRequest::builder()
+                    .set_key(Some(to_attribute_map(key)))
+                    .build()
+                    .map_err(build_error)?,
+            )
+            .build(),
+    })
+}
+
+fn to_transact_item(item: TransactItem) -> DomainResult<TransactWriteItem> {
+    let mut expr = ExpressionBuilder::new();
+    Ok(match item {
+        TransactItem::Put { table_name, item, conditions } => {
+            let condition = expr.conditions(&conditions)?;
+            let put = ddb::Put::builder()
+                .table_name(table_name)
+                .set_item(Some(to_attribute_map(item)))
+                .set_condition_expression(condition)
+                .set_expression_attribute_names(expr.names())
+                .set_expression_attribute_values(expr.values())
+                .build()
+                .map_err(build_error)?;
+            TransactWriteItem::builder().put(put).build()
+        }
+        TransactItem::Update { table_name, request } => {
+            let update_expression = expr
+                .update(&request.actions)
+                .ok_or_else(|| DomainError::Validation("Update has no actions".to_string()))?;
+            let condition = expr.conditions(&request.conditions)?;
+            let update = ddb::Update::builder()
+                .table_name(table_name)
+                .set_key(Some(to_attribute_map(request.key)))
+                .update_expression(update_expression)
+                .set_condition_expression(condition)
+                .set_expression_attribute_names(expr.names())
+                .set_expression_attribute_values(expr.values())
+                .build()
+                .map_err(build_error)?;
+            TransactWriteItem::builder().update(update).build()
+        }
+        TransactItem::Delete { table_name, key, conditions } => {
+            let condition = expr.conditions(&conditions)?;
+            let delete = ddb::Delete::builder()
+                .table_name(table_name)
+                .set_key(Some(to_attribute_map(key)))
+                .set_condition_expression(condition)
+                .set_expression_attribute_names(expr.names())
+                .set_expression_attribute_values(expr.values())
+                .build()
+                .map_err(build_error)?;
+            TransactWriteItem::builder().delete(delete).build()
+        }
+        TransactItem::ConditionCheck { table_name, key, conditions } => {
+            let condition = expr
+                .conditions(&conditions)?
+                .ok_or_else(|| DomainError::Validation("Condition check has no conditions".to_string()))?;
+            let check = ddb::ConditionCheck::builder()
+                .table_name(table_name)
+                .set_key(Some(to_attribute_map(key)))
+                .condition_expression(condition)
+                .set_expression_attribute_names(expr.names())
+                .set_expression_attribute_values(expr.values())
+                .build()
+                .map_err(build_error)?;
+            TransactWriteItem::builder().condition_check(check).build()
+        }
+    })
+}
+
+/// Keep the reasons of the items that caused a cancellation; the rest report `None`
+fn to_cancellation_reasons(reasons: Vec<ddb::CancellationReason>) -> Vec<CancellationReason> {
+    reasons
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, reason)| {
+            let code = reason.code.filter(|code| code != "None")?;
+            Some(CancellationReason {
+                index,
+                code,
+                message: reason.message,
+            })
+        })
+        .collect()
+}
+
 #[async_trait]
 impl DatabasePort for DynamoDbAdapter {
     async fn get_item(
         &self,
         table_name: &str,
-        key: HashMap<String, String>,
-    ) -> Result<Option<HashMap<String, String>>, Box<dyn StdError + Send + Sync>> {
-        let mut dynamo_key = HashMap::new();
-        for (k, v) in key {
-            dynamo_key.insert(k, AttributeValue::S(v));
-        }
-
+        key: Item,
+    ) -> DomainResult<Option<Item>> {
         let response = self.client
             .get_item()
             .table_name(table_name)
-            .set_key(Some(dynamo_key))
+            .set_key(Some(to_attribute_map(key)))
             .send()
+            .metered(self.metrics.as_deref(), "DynamoDB", "GetItem")
             .await
-            .map_err(|e| Box::new(e) as Box<dyn StdError + Send + Sync>)?;
+            .map_err(|e| classify_sdk_error("DynamoDB", e))?;
 
-        if let Some(item) = response.item {
-            let mut result = HashMap::new();
-            for (k, v) in item {
-                if let AttributeValue::S(s) = v {
-                    result.insert(k, s);
-                }
-                // Note: Ignoring non-string values for this simple port implementation
-            }
-            Ok(Some(result))
-        } else {
-            Ok(None)
-        }
+        response.item.map(from_attribute_map).transpose()
     }
 
-    async fn put_item(
+    async fn put_item_with(
         &self,
         table_name: &str,
-        item: HashMap<String, String>,
-    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
-        let mut dynamo_item = HashMap::new();
-        for (k, v) in item {
-            dynamo_item.insert(k, AttributeValue::S(v));
-        }
+        item: Item,
+        options: PutOptions,
+    ) -> DomainResult<Item> {
+        let (item, conditions) = options.prepare(item)?;
+        let mut expr = ExpressionBuilder::new();
+        let condition = expr.conditions(&conditions)?;
 
         self.client
             .put_item()
             .table_name(table_name)
-            .set_item(Some(dynamo_item))
+            .set_item(Some(to_attribute_map(item.clone())))
+            .set_condition_expression(condition)
+            .set_expression_attribute_names(expr.names())
+            .set_expression_attribute_values(expr.values())
+            .send()
+            .metered(self.metrics.as_deref(), "DynamoDB", "PutItem")
+            .await
+            .map_err(|e| classify_sdk_error("DynamoDB", e))?;
+
+        Ok(item)
+    }
+
+    async fn update_item(
+        &self,
+        table_name: &str,
+        request: UpdateRequest,
+    ) -> DomainResult<Option<Item>> {
+        let mut expr = ExpressionBuilder::new();
+        let update = expr
+            .update(&request.actions)
+            .ok_or_else(|| DomainError::Validation("Update has no actions".to_string()))?;
+        let condition = expr.conditions(&request.conditions)?;
+
+        let response = self.client
+            .update_item()
+            .table_name(table_name)
+            .set_key(Some(to_attribute_map(request.key)))
+            .update_expression(update)
+            .set_condition_expression(condition)
+            .set_expression_attribute_names(expr.names())
+            .set_expression_attribute_values(expr.values())
+            .return_values(to_return_value(request.return_values))
+            .send()
+            .metered(self.metrics.as_deref(), "DynamoDB", "UpdateItem")
+            .await
+            .map_err(|e| classify_sdk_error("DynamoDB", e))?;
+
+        response.attributes.map(from_attribute_map).transpose()
+    }
+
+    async fn delete_item(
+        &self,
+        table_name: &str,
+        key: Item,
+        conditions: Vec<Condition>,
+    ) -> DomainResult<Option<Item>> {
+        let mut expr = ExpressionBuilder::new();
+        let condition = expr.conditions(&conditions)?;
+
+        let response = self.client
+            .delete_item()
+            .table_name(table_name)
+            .set_key(Some(to_attribute_map(key)))
+            .set_condition_expression(condition)
+            .set_expression_attribute_names(expr.names())
+            .set_expression_attribute_values(expr.values())
+            .return_values(ReturnValue::AllOld)
             .send()
+            .metered(self.metrics.as_deref(), "DynamoDB", "DeleteItem")
             .await
-            .map_err(|e| Box::new(e) as Box<dyn StdError + Send + Sync>)?;
+            .map_err(|e| classify_sdk_error("DynamoDB", e))?;
+
+        response.attributes.map(from_attribute_map).transpose()
+    }
+
+    async fn batch_get(
+        &self,
+        table_name: &str,
+        keys: Vec<Item>,
+    ) -> DomainResult<Vec<Item>> {
+        let keys = keys.into_iter().map(to_attribute_map).collect();
+
+        send_in_chunks(keys, BATCH_GET_LIMIT, self.retry, |chunk| async move {
+            let keys_and_attributes = KeysAndAttributes::builder()
+                .set_keys(Some(chunk))
+                .build()
+                .map_err(build_error)?;
+            let response = self.client
+                .batch_get_item()
+                .request_items(table_name, keys_and_attributes)
+                .send()
+                .metered(self.metrics.as_deref(), "DynamoDB", "BatchGetItem")
+                .await
+                .map_err(|e| classify_sdk_error("DynamoDB", e))?;
+
+            let items = response
+                .responses
+                .and_then(|mut tables| tables.remove(table_name))
+                .unwrap_or_default()
+                .into_iter()
+                .map(from_attribute_map)
+                .collect::<DomainResult<Vec<_>>>()?;
+            let unprocessed = response
+                .unprocessed_keys
+                .and_then(|mut tables| tables.remove(table_name))
+                .map(|keys| keys.keys)
+                .unwrap_or_default();
+            Ok((items, unprocessed))
+        })
+        .await
+    }
+
+    async fn batch_write(
+        &self,
+        table_name: &str,
+        requests: Vec<WriteRequest>,
+    ) -> DomainResult<()> {
+        let requests = requests
+            .into_iter()
+            .map(to_write_request)
+            .collect::<DomainResult<Vec<_>>>()?;
+
+        send_in_chunks(requests, BATCH_WRITE_LIMIT, self.retry, |chunk| async move {
+            let response = self.client
+                .batch_write_item()
+                .request_items(table_name, chunk)
+                .send()
+                .metered(self.metrics.as_deref(), "DynamoDB", "BatchWriteItem")
+                .await
+                .map_err(|e| classify_sdk_error("DynamoDB", e))?;
+
+            let unprocessed = response
+                .unprocessed_items
+                .and_then(|mut tables| tables.remove(table_name))
+                .unwrap_or_default();
+            Ok((Vec::<()>::new(), unprocessed))
+        })
+        .await?;
 
         Ok(())
     }
+
+    async fn transact_write(&self, items: Vec<TransactItem>) -> DomainResult<()> {
+        if items.is_empty() || items.len() > TRANSACT_WRITE_LIMIT {
+            return Err(DomainError::Validation(format!(
+                "A transaction needs between 1 and {} items, got {}",
+                TRANSACT_WRITE_LIMIT,
+                items.len()
+            )));
+        }
+        let items = items
+            .into_iter()
+            .map(to_transact_item)
+            .collect::<DomainResult<Vec<_>>>()?;
+
+        let result = self.client
+            .transact_write_items()
+            .set_transact_items(Some(items))
+            .send()
+            .metered(self.metrics.as_deref(), "DynamoDB", "TransactWriteItems")
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(err) => match err.as_service_error() {
+                Some(TransactWriteItemsError::TransactionCanceledException(canceled)) => {
+                    Err(DomainError::TransactionCanceled {
+                        message: canceled.message().unwrap_or("Transaction canceled").to_string(),
+                        reasons: to_cancellation_reasons(canceled.cancellation_reasons().to_vec()),
+                    })
+                }
+                _ => Err(classify_sdk_error("DynamoDB", err)),
+            },
+        }
+    }
+
+    async fn query(
+        &self,
+        table_name: &str,
+        request: QueryRequest,
+    ) -> DomainResult<Page> {
+        let mut expr = ExpressionBuilder::new();
+        let (pk_name, pk_value) = request.partition_key;
+        let mut key_condition = format!("{} = {}", expr.name(&pk_name), expr.value(pk_value));
+        if let Some((sk_name, condition)) = &request.sort_key {
+            key_condition.push_str(" AND ");
+            key_condition.push_str(&expr.sort_key_condition(sk_name, condition));
+        }
+        let filter = expr.filters(&request.filters);
+
+        let response = self.client
+            .query()
+            .table_name(table_name)
+            .set_index_name(request.index_name)
+            .key_condition_expression(key_condition)
+            .set_filter_expression(filter)
+            .set_expression_attribute_names(expr.names())
+            .set_expression_attribute_values(expr.values())
+            .set_limit(to_limit(request.limit))
+            .scan_index_forward(request.scan_forward)
+            .set_exclusive_start_key(start_key(request.next_token.as_deref())?)
+            .send()
+            .metered(self.metrics.as_deref(), "DynamoDB", "Query")
+            .await
+            .map_err(|e| classify_sdk_error("DynamoDB", e))?;
+
+        to_page(response.items, response.last_evaluated_key)
+    }
+
+    async fn scan(
+        &self,
+        table_name: &str,
+        request: ScanRequest,
+    ) -> DomainResult<Page> {
+        let mut expr = ExpressionBuilder::new();
+        let filter = expr.filters(&request.filters);
+
+        let response = self.client
+            .scan()
+            .table_name(table_name)
+            .set_index_name(request.index_name)
+            .set_filter_expression(filter)
+            .set_expression_attribute_names(expr.names())
+            .set_expression_attribute_values(expr.values())
+            .set_limit(to_limit(request.limit))
+            .set_segment(request.segment.map(|s| s.segment as i32))
+            .set_total_segments(request.segment.map(|s| s.total_segments as i32))
+            .set_exclusive_start_key(start_key(request.next_token.as_deref())?)
+            .send()
+            .metered(self.metrics.as_deref(), "DynamoDB", "Scan")
+            .await
+            .map_err(|e| classify_sdk_error("DynamoDB", e))?;
+
+        to_page(response.items, response.last_evaluated_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribute_value_round_trip() {
+        let item: Item = HashMap::from([
+            ("s".to_string(), ItemValue::from("text")),
+            ("n".to_string(), ItemValue::Number("123456789012345678901234567890.000001".to_string())),
+            ("b".to_string(), ItemValue::Binary(vec![0xde, 0xad, 0xbe, 0xef])),
+            ("bool".to_string(), ItemValue::Bool(false)),
+            ("null".to_string(), ItemValue::Null),
+            (
+                "m".to_string(),
+                ItemValue::Map(HashMap::from([("nested".to_string(), ItemValue::number(7))])),
+            ),
+            ("l".to_string(), ItemValue::List(vec![ItemValue::from("a"), ItemValue::number(1)])),
+            ("ss".to_string(), ItemValue::StringSet(vec!["a".to_string(), "b".to_string()])),
+            ("ns".to_string(), ItemValue::NumberSet(vec!["1".to_string(), "2.5".to_string()])),
+            ("bs".to_string(), ItemValue::BinarySet(vec![vec![1], vec![2, 3]])),
+        ]);
+
+        let attributes = to_attribute_map(item.clone());
+        assert_eq!(attributes["ss"], AttributeValue::Ss(vec!["a".to_string(), "b".to_string()]));
+        assert!(matches!(attributes["l"], AttributeValue::L(_)));
+
+        assert_eq!(from_attribute_map(attributes).unwrap(), item);
+    }
+
+    #[test]
+    fn test_cancellation_reasons_keep_failed_items() {
+        let reasons = vec![
+            ddb::CancellationReason::builder().code("None").build(),
+            ddb::CancellationReason::builder()
+                .code("ConditionalCheckFailed")
+                .message("The conditional request failed")
+                .build(),
+            ddb::CancellationReason::builder().code("None").build(),
+        ];
+        assert_eq!(
+            to_cancellation_reasons(reasons),
+            vec![CancellationReason {
+                index: 1,
+                code: "ConditionalCheckFailed".to_string(),
+                message: Some("The conditional request failed".to_string()),
+            }]
+        );
+    }
 }
@@ -0,0 +1,282 @@
+use crate::domain::condition::Condition;
+use crate::domain::errors::{DomainError, DomainResult};
+use crate::domain::item::ItemValue;
+use crate::domain::query::{Filter, FilterOp, SortKeyCondition};
+use crate::domain::update::UpdateAction;
+use super::dynamo::to_attribute_value;
+use aws_sdk_dynamodb::types::AttributeValue;
+use std::collections::HashMap;
+
+/// Builds DynamoDB expressions with placeholder names (`#n0`) and values (`:v0`),
+/// so attribute names never clash with reserved words and values are never inlined
+#[derive(Default)]
+pub(crate) struct ExpressionBuilder {
+    names: HashMap<String, String>,
+    values: HashMap<String, AttributeValue>,
+}
+
+impl ExpressionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Placeholder for an attribute name; nested paths (`a.b`) are split per segment
+    pub fn name(&mut self, attribute: &str) -> String {
+        attribute
+            .split('.')
+            .map(|segment| self.attribute_name(segment))
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// Placeholder for one top-level attribute, dots included in its name
+    pub fn attribute_name(&mut self, attribute: &str) -> String {
+        if let Some((placeholder, _)) = self.names.iter().find(|(_, name)| name.as_str() == attribute) {
+            return placeholder.clone();
+        }
+        let placeholder = format!("#n{}", self.names.len());
+        self.names.insert(placeholder.clone(), attribute.to_string());
+        placeholder
+    }
+
+    /// Placeholder for a value
+    pub fn value(&mut self, value: ItemValue) -> String {
+        let placeholder = format!(":v{}", self.values.len());
+        self.values.insert(placeholder.clone(), to_attribute_value(value));
+        placeholder
+    }
+
+    pub fn sort_key_condition(&mut self, attribute: &str, condition: &SortKeyCondition) -> String {
+        let name = self.name(attribute);
+        match condition {
+            SortKeyCondition::Eq(v) => format!("{} = {}", name, self.value(v.clone())),
+            SortKeyCondition::Lt(v) => format!("{} < {}", name, self.value(v.clone())),
+            SortKeyCondition::Le(v) => format!("{} <= {}", name, self.value(v.clone())),
+            SortKeyCondition::Gt(v) => format!("{} > {}", name, self.value(v.clone())),
+            SortKeyCondition::Ge(v) => format!("{} >= {}", name, self.value(v.clone())),
+            SortKeyCondition::Between(low, high) => {
+                let low = self.value(low.clone());
+                let high = self.value(high.clone());
+                format!("{} BETWEEN {} AND {}", name, low, high)
+            }
+            SortKeyCondition::BeginsWith(prefix) => {
+                format!("begins_with({}, {})", name, self.value(ItemValue::from(prefix.as_str())))
+            }
+        }
+    }
+
+    pub fn filter(&mut self, filter: &Filter) -> String {
+        let name = self.name(&filter.attribute);
+        match &filter.op {
+            FilterOp::Eq(v) => format!("{} = {}", name, self.value(v.clone())),
+            FilterOp::Ne(v) => format!("{} <> {}", name, self.value(v.clone())),
+            FilterOp::Lt(v) => format!("{} < {}", name, self.value(v.clone())),
+            FilterOp::Le(v) => format!("{} <= {}", name, self.value(v.clone())),
+            FilterOp::Gt(v) => format!("{} > {}", name, self.value(v.clone())),
+            FilterOp::Ge(v) => format!("{} >= {}", name, self.value(v.clone())),
+            FilterOp::Between(low, high) => {
+                let low = self.value(low.clone());
+                let high = self.value(high.clone());
+                format!("{} BETWEEN {} AND {}", name, low, high)
+            }
+            FilterOp::BeginsWith(prefix) => {
+                format!("begins_with({}, {})", name, self.value(ItemValue::from(prefix.as_str())))
+            }
+            FilterOp::Contains(v) => format!("contains({}, {})", name, self.value(v.clone())),
+            FilterOp::Exists => format!("attribute_exists({})", name),
+            FilterOp::NotExists => format!("attribute_not_exists({})", name),
+        }
+    }
+
+    /// AND-combine the filters, or `None` when there are none
+    pub fn filters(&mut self, filters: &[Filter]) -> Option<String> {
+        if filters.is_empty() {
+            return None;
+        }
+        let parts: Vec<String> = filters.iter().map(|f| self.filter(f)).collect();
+        Some(parts.join(" AND "))
+    }
+
+    /// AND-combine write conditions, or `None` when there are none.
+    ///
+    /// Raw expressions bring their own placeholders, which must not clash with generated ones.
+    pub fn conditions(&mut self, conditions: &[Condition]) -> DomainResult<Option<String>> {
+        let mut parts = Vec::with_capacity(conditions.len());
+        for condition in conditions {
+            match condition {
+                Condition::Match(filters) => {
+                    if let Some(part) = self.filters(filters) {
+                        parts.push(part);
+                    }
+                }
+                Condition::Expression { expression, names, values } => {
+                    let generated = |placeholder: &str, prefix: &str| {
+                        placeholder.strip_prefix(prefix).is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
+                    };
+                    if let Some(clash) = names
+                        .keys()
+                        .find(|p| generated(p, "#n") || self.names.contains_key(*p))
+                        .or_else(|| values.keys().find(|p| generated(p, ":v") || self.values.contains_key(*p)))
+                    {
+                        return Err(DomainError::Validation(format!(
+                            "Condition placeholder '{}' is reserved or already bound",
+                            clash
+                        )));
+                    }
+                    self.names.extend(names.iter().map(|(k, v)| (k.clone(), v.clone())));
+                    self.values.extend(values.iter().map(|(k, v)| (k.clone(), to_attribute_value(v.clone()))));
+                    parts.push(expression.clone());
+                }
+            }
+        }
+
+        Ok(match parts.len() {
+            0 => None,
+            1 => parts.pop(),
+            _ => Some(parts.iter().map(|p| format!("({})", p)).collect::<Vec<_>>().join(" AND ")),
+        })
+    }
+
+    /// Update expression grouping actions into `SET`, `REMOVE`, `ADD` and `DELETE` clauses.
+    ///
+    /// Actions name top-level attributes, as items are written whole by `PutItem`,
+    /// so `a.b` is one attribute rather than a nested path.
+    pub fn update(&mut self, actions: &[UpdateAction]) -> Option<String> {
+        let mut set = Vec::new();
+        let mut remove = Vec::new();
+        let mut add = Vec::new();
+        let mut delete = Vec::new();
+
+        for action in actions {
+            let name = self.attribute_name(action.attribute());
+            match action {
+                UpdateAction::Set(_, v) => set.push(format!("{} = {}", name, self.value(v.clone()))),
+                UpdateAction::SetIfNotExists(_, v) => {
+                    set.push(format!("{} = if_not_exists({}, {})", name, name, self.value(v.clone())))
+                }
+                UpdateAction::Remove(_) => remove.push(name),
+                UpdateAction::Add(_, v) => add.push(format!("{} {}", name, self.value(v.clone()))),
+                UpdateAction::DeleteFromSet(_, v) => delete.push(format!("{} {}", name, self.value(v.clone()))),
+            }
+        }
+
+        let clauses: Vec<String> = [("SET", set), ("REMOVE", remove), ("ADD", add), ("DELETE", delete)]
+            .into_iter()
+            .filter(|(_, parts)| !parts.is_empty())
+            .map(|(keyword, parts)| format!("{} {}", keyword, parts.join(", ")))
+            .collect();
+
+        (!clauses.is_empty()).then(|| clauses.join(" "))
+    }
+
+    /// Names map, or `None` when empty (DynamoDB rejects empty maps)
+    pub fn names(&self) -> Option<HashMap<String, String>> {
+        (!self.names.is_empty()).then(|| self.names.clone())
+    }
+
+    /// Values map, or `None` when empty (DynamoDB rejects empty maps)
+    pub fn values(&self) -> Option<HashMap<String, AttributeValue>> {
+        (!self.values.is_empty()).then(|| self.values.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_condition_and_filters() {
+        let mut builder = ExpressionBuilder::new();
+        let pk = builder.name("order_id");
+        let pk_value = builder.value(ItemValue::from("1111"));
+        let sk = builder.sort_key_condition(
+            "segment",
+            &SortKeyCondition::Between(ItemValue::number(1), ItemValue::number(9)),
+        );
+        let filter = builder
+            .filters(&[
+                Filter::new("status", FilterOp::Eq(ItemValue::from("open"))),
+                Filter::new("address.city", FilterOp::Exists),
+            ])
+            .unwrap();
+
+        assert_eq!(format!("{} = {} AND {}", pk, pk_value, sk), "#n0 = :v0 AND #n1 BETWEEN :v1 AND :v2");
+        assert_eq!(filter, "#n2 = :v3 AND attribute_exists(#n3.#n4)");
+
+        let names = builder.names().unwrap();
+        assert_eq!(names["#n2"], "status");
+        assert_eq!(names["#n4"], "city");
+        assert_eq!(builder.values().unwrap()[":v0"], AttributeValue::S("1111".to_string()));
+    }
+
+    #[test]
+    fn test_conditions_combine_generated_and_raw() {
+        let mut builder = ExpressionBuilder::new();
+        let conditions = [
+            Condition::attribute_not_exists("order_id"),
+            Condition::expression("#s IN (:open, :held)")
+                .name("#s", "status")
+                .value(":open", "open")
+                .value(":held", "held"),
+        ];
+        let expression = builder.conditions(&conditions).unwrap().unwrap();
+        assert_eq!(expression, "(attribute_not_exists(#n0)) AND (#s IN (:open, :held))");
+        assert_eq!(builder.names().unwrap()["#s"], "status");
+        assert_eq!(builder.values().unwrap()[":held"], AttributeValue::S("held".to_string()));
+    }
+
+    #[test]
+    fn test_conditions_reject_reserved_placeholders() {
+        let mut builder = ExpressionBuilder::new();
+        let condition = Condition::expression("#n0 = :x").name("#n0", "status").value(":x", "open");
+        assert!(matches!(builder.conditions(&[condition]), Err(DomainError::Validation(_))));
+        assert!(builder.conditions(&[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_update_groups_clauses() {
+        let mut builder = ExpressionBuilder::new();
+        let update = builder
+            .update(&[
+                UpdateAction::Set("status".to_string(), ItemValue::from("closed")),
+                UpdateAction::Add("views".to_string(), ItemValue::number(1)),
+                UpdateAction::Remove("notes".to_string()),
+                UpdateAction::SetIfNotExists("created".to_string(), ItemValue::from("now")),
+            ])
+            .unwrap();
+        assert_eq!(update, "SET #n0 = :v0, #n3 = if_not_exists(#n3, :v2) REMOVE #n2 ADD #n1 :v1");
+        assert!(ExpressionBuilder::new().update(&[]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_attributes_match_the_mock() {
+        use crate::domain::mocks::MockDatabase;
+        use crate::domain::ports::DatabasePort;
+        use crate::domain::update::{ReturnValues, UpdateRequest};
+
+        let mut key = crate::domain::item::Item::new();
+        key.insert("order_id".to_string(), "1111".into());
+        let request = UpdateRequest::new(key)
+            .set("address.city", "Lisbon")
+            .returning(ReturnValues::AllNew);
+
+        let mut builder = ExpressionBuilder::new();
+        assert_eq!(builder.update(&request.actions).unwrap(), "SET #n0 = :v0");
+        let names = builder.names().unwrap();
+
+        let db = MockDatabase::new().with_table("orders", &["order_id"]);
+        let item = db.update_item("orders", request).await.unwrap().unwrap();
+        // Both write the dotted name as one top-level attribute
+        assert_eq!(item.get(&names["#n0"]), Some(&ItemValue::from("Lisbon")));
+        assert_eq!(names.len(), 1);
+    }
+
+    #[test]
+    fn test_reuses_name_placeholders() {
+        let mut builder = ExpressionBuilder::new();
+        assert_eq!(builder.name("status"), "#n0");
+        assert_eq!(builder.name("status"), "#n0");
+        assert!(builder.values().is_none());
+        assert!(builder.filters(&[]).is_none());
+    }
+}
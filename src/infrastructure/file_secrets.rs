@@ -0,0 +1,56 @@
+use crate::domain::errors::{DomainError, DomainResult};
+use crate::domain::ports::SecretsPort;
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+
+/// SecretsPort over a local JSON file, for running without AWS.
+///
+/// The file maps secret names to values; an object value is returned as its
+/// JSON text, so `secret://name#field` references work as with Secrets
+/// Manager. The file is read on every call, so edits are picked up once a
+/// cached value expires.
+pub struct FileSecretsAdapter {
+    path: String,
+}
+
+impl FileSecretsAdapter {
+    pub fn new(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+#[async_trait]
+impl SecretsPort for FileSecretsAdapter {
+    async fn get_secret(&self, name: &str) -> DomainResult<String> {
+        let contents = tokio::fs::read_to_string(&self.path)
+            .await
+            .map_err(|e| DomainError::Internal(format!("Failed to read secrets file '{}': {}", self.path, e)))?;
+        let mut secrets: Map<String, Value> = serde_json::from_str(&contents)
+            .map_err(|e| DomainError::Internal(format!("Secrets file '{}' is not a JSON object: {}", self.path, e)))?;
+
+        match secrets.remove(name) {
+            Some(Value::String(value)) => Ok(value),
+            Some(Value::Null) | None => Err(DomainError::NotFound(format!("Secret '{}' does not exist", name))),
+            Some(other) => Ok(other.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reads_strings_and_objects() {
+        let path = std::env::temp_dir().join(format!("file-secrets-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"jwks": "{\"keys\":[]}", "payments": {"api_key": "sk_test_1"}}"#).unwrap();
+        let secrets = FileSecretsAdapter::new(path.to_str().unwrap());
+
+        assert_eq!(secrets.get_secret("jwks").await.unwrap(), "{\"keys\":[]}");
+        assert_eq!(secrets.get_secret("payments").await.unwrap(), r#"{"api_key":"sk_test_1"}"#);
+        assert!(matches!(secrets.get_secret("missing").await, Err(DomainError::NotFound(_))));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(secrets.get_secret("jwks").await, Err(DomainError::Internal(_))));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::domain::email::{Attachment, EmailMessage};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fmt::Write;
+
+/// Length of base64 lines in encoded bodies (RFC 2045)
+const LINE_LENGTH: usize = 76;
+
+/// Render a message as a raw MIME document for `SendRawEmail`.
+///
+/// Bcc recipients are left out of the headers; they are only passed as
+/// destinations. Bodies and attachments are base64-encoded, and `boundary`
+/// must not occur in any header value.
+pub(crate) fn build_mime(message: &EmailMessage, boundary: &str) -> Vec<u8> {
+    let envelope = &message.envelope;
+    let mut out = String::new();
+
+    header(&mut out, "From", &envelope.from);
+    if !envelope.to.is_empty() {
+        header(&mut out, "To", &envelope.to.join(", "));
+    }
+    if !envelope.cc.is_empty() {
+        header(&mut out, "Cc", &envelope.cc.join(", "));
+    }
+    if !envelope.reply_to.is_empty() {
+        header(&mut out, "Reply-To", &envelope.reply_to.join(", "));
+    }
+    header(&mut out, "Subject", &encode_word(&message.subject));
+    header(&mut out, "MIME-Version", "1.0");
+
+    let body = |out: &mut String, boundary: &str| match (&message.text, &message.html) {
+        (Some(text), Some(html)) => {
+            let alternative = format!("{}_alt", boundary);
+            header(out, "Content-Type", &format!("multipart/alternative; boundary=\"{}\"", alternative));
+            out.push_str("\r\n");
+            part_separator(out, &alternative);
+            text_part(out, "text/plain", text);
+            part_separator(out, &alternative);
+            text_part(out, "text/html", html);
+            closing_separator(out, &alternative);
+        }
+        (Some(text), None) => text_part(out, "text/plain", text),
+        (None, Some(html)) => text_part(out, "text/html", html),
+        (None, None) => text_part(out, "text/plain", ""),
+    };
+
+    if message.attachments.is_empty() {
+        body(&mut out, boundary);
+    } else {
+        header(&mut out, "Content-Type", &format!("multipart/mixed; boundary=\"{}\"", boundary));
+        out.push_str("\r\n");
+        part_separator(&mut out, boundary);
+        body(&mut out, boundary);
+        for attachment in &message.attachments {
+            part_separator(&mut out, boundary);
+            attachment_part(&mut out, attachment);
+        }
+        closing_separator(&mut out, boundary);
+    }
+
+    out.into_bytes()
+}
+
+fn header(out: &mut String, name: &str, value: &str) {
+    let _ = write!(out, "{}: {}\r\n", name, value);
+}
+
+fn part_separator(out: &mut String, boundary: &str) {
+    let _ = write!(out, "\r\n--{}\r\n", boundary);
+}
+
+fn closing_separator(out: &mut String, boundary: &str) {
+    let _ = write!(out, "\r\n--{}--\r\n", boundary);
+}
+
+fn text_part(out: &mut String, content_type: &str, body: &str) {
+    header(out, "Content-Type", &format!("{}; charset=UTF-8", content_type));
+    header(out, "Content-Transfer-Encoding", "base64");
+    out.push_str("\r\n");
+    push_base64(out, body.as_bytes());
+}
+
+fn attachment_part(out: &mut String, attachment: &Attachment) {
+    let filename = encode_word(&attachment.filename.replace(['"', '\\'], "_"));
+    header(out, "Content-Type", &format!("{}; name=\"{}\"", attachment.content_type, filename));
+    header(out, "Content-Disposition", &format!("attachment; filename=\"{}\"", filename));
+    header(out, "Content-Transfer-Encoding", "base64");
+    out.push_str("\r\n");
+    push_base64(out, &attachment.data);
+}
+
+fn push_base64(out: &mut String, data: &[u8]) {
+    let encoded = STANDARD.encode(data);
+    for (i, line) in encoded.as_bytes().chunks(LINE_LENGTH).enumerate() {
+        if i > 0 {
+            out.push_str("\r\n");
+        }
+        // base64 output is ASCII
+        out.push_str(std::str::from_utf8(line).unwrap_or_default());
+    }
+}
+
+/// RFC 2047 encoded word for header values that are not plain ASCII
+fn encode_word(value: &str) -> String {
+    if value.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+        value.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", STANDARD.encode(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(message: &EmailMessage) -> String {
+        String::from_utf8(build_mime(message, "=_boundary")).unwrap()
+    }
+
+    #[test]
+    fn test_text_only_message() {
+        let message = EmailMessage::new("shop@example.com", "Your order")
+            .to("buyer@example.com")
+            .bcc("audit@example.com")
+            .text("Thanks!");
+        let mime = render(&message);
+        assert!(mime.starts_with("From: shop@example.com\r\nTo: buyer@example.com\r\nSubject: Your order\r\n"));
+        assert!(!mime.contains("audit@example.com"));
+        assert!(mime.contains("Content-Type: text/plain; charset=UTF-8\r\n"));
+        assert!(mime.ends_with(&STANDARD.encode("Thanks!")));
+    }
+
+    #[test]
+    fn test_alternative_bodies_and_attachment() {
+        let message = EmailMessage::new("shop@example.com", "Commande confirmée")
+            .to("buyer@example.com")
+            .text("Thanks!")
+            .html("<p>Thanks!</p>")
+            .attachment(Attachment::new("invoice.pdf", "application/pdf", vec![0u8; 100]));
+        let mime = render(&message);
+
+        assert!(mime.contains(&format!("Subject: =?UTF-8?B?{}?=\r\n", STANDARD.encode("Commande confirmée"))));
+        assert!(mime.contains("Content-Type: multipart/mixed; boundary=\"=_boundary\""));
+        assert!(mime.contains("Content-Type: multipart/alternative; boundary=\"=_boundary_alt\""));
+        assert!(mime.contains("Content-Disposition: attachment; filename=\"invoice.pdf\""));
+        assert!(mime.trim_end().ends_with("--=_boundary--"));
+        // The 100-byte attachment encodes to 136 characters, wrapped at 76
+        assert!(mime.contains(&format!("\r\n{}\r\n{}==\r\n", "A".repeat(76), "A".repeat(58))));
+        assert!(mime.split("\r\n").all(|line| line.len() <= LINE_LENGTH));
+    }
+}
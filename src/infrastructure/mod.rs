@@ -1,2 +1,10 @@
+mod aws_error;
 pub mod dynamo;
+mod expression;
+pub mod file_secrets;
+mod mime;
+pub mod retry;
 pub mod s3;
+pub mod secrets_manager;
+pub mod ses;
+pub mod ssm;
@@ -0,0 +1,135 @@
+use crate::domain::errors::{DomainError, DomainResult};
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Exponential backoff applied while a batch call leaves work unprocessed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Calls per chunk, including the first one
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (1-based): `base * 2^(retry-1)`, capped,
+    /// with the upper half jittered so concurrent writers spread out
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)));
+        let capped = exponential.min(self.max_delay);
+        let half = capped / 2;
+        let jitter_range = half.as_nanos().max(1) as u64;
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos() as u64);
+        half + Duration::from_nanos(nanos % jitter_range)
+    }
+}
+
+/// Send `pending` in chunks of at most `chunk_size`.
+///
+/// `send` returns the results of a call and the inputs it left unprocessed;
+/// those are resent with backoff until none remain or `max_attempts` is reached,
+/// which is reported as `Throttled`.
+pub(crate) async fn send_in_chunks<T, R, F, Fut>(
+    pending: Vec<T>,
+    chunk_size: usize,
+    policy: RetryPolicy,
+    mut send: F,
+) -> DomainResult<Vec<R>>
+where
+    F: FnMut(Vec<T>) -> Fut,
+    Fut: Future<Output = DomainResult<(Vec<R>, Vec<T>)>>,
+{
+    let mut results = Vec::new();
+    let mut pending = pending.into_iter().peekable();
+
+    while pending.peek().is_some() {
+        let mut chunk: Vec<T> = pending.by_ref().take(chunk_size).collect();
+        let mut attempt = 1;
+        loop {
+            let (processed, unprocessed) = send(chunk).await?;
+            results.extend(processed);
+            if unprocessed.is_empty() {
+                break;
+            }
+            if attempt >= policy.max_attempts {
+                return Err(DomainError::Throttled(format!(
+                    "{} batch request(s) still unprocessed after {} attempts",
+                    unprocessed.len(),
+                    attempt
+                )));
+            }
+            tokio::time::sleep(policy.delay(attempt)).await;
+            attempt += 1;
+            chunk = unprocessed;
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+        }
+    }
+
+    #[test]
+    fn test_delay_grows_and_is_capped() {
+        let policy = RetryPolicy::default();
+        let first = policy.delay(1);
+        assert!(first >= Duration::from_millis(25) && first <= Duration::from_millis(50));
+        let fourth = policy.delay(4);
+        assert!(fourth >= Duration::from_millis(200) && fourth <= Duration::from_millis(400));
+        assert!(policy.delay(30) <= policy.max_delay);
+    }
+
+    #[tokio::test]
+    async fn test_chunks_and_retries_unprocessed() {
+        let calls = Mutex::new(Vec::new());
+        let results = send_in_chunks((0..60).collect(), 25, fast_policy(3), |chunk: Vec<u32>| {
+            calls.lock().unwrap().push(chunk.len());
+            // The first call of each chunk leaves its last two inputs unprocessed
+            let first_try = chunk.len() > 2;
+            async move {
+                if first_try {
+                    let split = chunk.len() - 2;
+                    Ok((chunk[..split].to_vec(), chunk[split..].to_vec()))
+                } else {
+                    Ok((chunk, Vec::new()))
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        let mut results = results;
+        results.sort();
+        assert_eq!(results, (0..60).collect::<Vec<_>>());
+        assert_eq!(*calls.lock().unwrap(), vec![25, 2, 25, 2, 10, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let result: DomainResult<Vec<u32>> =
+            send_in_chunks(vec![1, 2], 25, fast_policy(3), |chunk: Vec<u32>| async move { Ok((Vec::new(), chunk)) })
+                .await;
+        assert!(matches!(result, Err(DomainError::Throttled(_))));
+    }
+}
@@ -1,55 +1,558 @@
-use crate::domain::ports::StoragePort;
+use crate::domain::errors::{DomainError, DomainResult};
+use crate::domain::object::{
+    DeleteFailure, ListObjectsRequest, ObjectListing, ObjectMetadata, ObjectSummary, PresignMethod, PresignRequest,
+    PresignedUrl, PutObjectOptions, DELETE_OBJECTS_LIMIT,
+};
+use crate::domain::ports::{ByteReader, StoragePort};
+use crate::metrics::{Metered, Metrics};
+use super::aws_error::classify_sdk_error;
 use async_trait::async_trait;
+use aws_sdk_s3::presigning::PresigningConfig;
 use aws_sdk_s3::Client;
 use aws_sdk_s3::primitives::ByteStream;
-use std::error::Error as StdError;
+use aws_sdk_s3::primitives::DateTime as AwsDateTime;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart, Delete, ObjectIdentifier};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::warn;
+
+/// Smallest part S3 accepts, except for the last one
+pub const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+/// Most parts one multipart upload may have
+pub const MAX_PARTS: i32 = 10_000;
+/// Most buffer space reserved before any byte is read; larger reads grow the buffer
+const INITIAL_READ_CAPACITY: usize = 64 * 1024;
+
+/// When and how `put_object_stream` switches to multipart upload
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MultipartConfig {
+    /// Bodies of at least this many bytes are uploaded in parts; lowered to
+    /// `part_size` if larger, so no more than one part is ever buffered
+    pub threshold: usize,
+    /// Size of every part but the last; raised to `MIN_PART_SIZE` if smaller
+    pub part_size: usize,
+}
+
+impl Default for MultipartConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 8 * 1024 * 1024,
+            part_size: 8 * 1024 * 1024,
+        }
+    }
+}
 
 pub struct S3Adapter {
     client: Client,
+    multipart: MultipartConfig,
+    /// `MAX_PARTS`, lowered by tests to reach the limit with small bodies
+    max_parts: i32,
+    metrics: Option<Arc<Metrics>>,
 }
 
 impl S3Adapter {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            multipart: MultipartConfig::default(),
+            max_parts: MAX_PARTS,
+            metrics: None,
+        }
+    }
+
+    /// Record the latency and outcome of every S3 call
+    pub fn with_metrics(mut self, metrics: Option<Arc<Metrics>>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn with_multipart(mut self, multipart: MultipartConfig) -> Self {
+        let part_size = multipart.part_size.max(MIN_PART_SIZE);
+        self.multipart = MultipartConfig {
+            threshold: multipart.threshold.clamp(1, part_size),
+            part_size,
+        };
+        self
+    }
+
+    /// Upload `first` and the rest of `body` in parts; the caller aborts the upload on error
+    async fn upload_parts(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        first: Vec<u8>,
+        body: &mut ByteReader,
+    ) -> DomainResult<()> {
+        let mut parts = Vec::new();
+        let mut chunk = first;
+        let mut part_number = 1;
+
+        while !chunk.is_empty() {
+            if part_number > self.max_parts {
+                return Err(DomainError::Validation(format!(
+                    "Object exceeds {} parts of {} bytes",
+                    self.max_parts, self.multipart.part_size
+                )));
+            }
+            let response = self.client
+                .upload_part()
+                .bucket(bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .body(ByteStream::from(chunk))
+                .send()
+                .metered(self.metrics.as_deref(), "S3", "UploadPart")
+                .await
+                .map_err(|e| classify_sdk_error("S3", e))?;
+            parts.push(
+                CompletedPart::builder()
+                    .part_number(part_number)
+                    .set_e_tag(response.e_tag)
+                    .build(),
+            );
+
+            part_number += 1;
+            chunk = read_up_to(body, self.multipart.part_size).await?;
+        }
+
+        self.client
+            .complete_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+            .send()
+            .metered(self.metrics.as_deref(), "S3", "CompleteMultipartUpload")
+            .await
+            .map_err(|e| classify_sdk_error("S3", e))?;
+
+        Ok(())
     }
 }
 
+/// Read until `limit` bytes or the end of the stream, whichever comes first
+async fn read_up_to<R: AsyncRead + Unpin>(reader: &mut R, limit: usize) -> DomainResult<Vec<u8>> {
+    let mut buffer = Vec::with_capacity(limit.min(INITIAL_READ_CAPACITY));
+    reader
+        .take(limit as u64)
+        .read_to_end(&mut buffer)
+        .await
+        .map_err(|e| DomainError::Upstream(format!("Failed to read upload body: {}", e)))?;
+    Ok(buffer)
+}
+
+fn to_chrono(time: AwsDateTime) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(time.secs(), time.subsec_nanos())
+}
+
+fn build_error(e: impl std::fmt::Display) -> DomainError {
+    DomainError::Internal(format!("S3 error: invalid request: {}", e))
+}
+
 #[async_trait]
 impl StoragePort for S3Adapter {
     async fn get_object(
         &self,
         bucket: &str,
         key: &str,
-    ) -> Result<Vec<u8>, Box<dyn StdError + Send + Sync>> {
+    ) -> DomainResult<Vec<u8>> {
         let response = self.client
             .get_object()
             .bucket(bucket)
             .key(key)
             .send()
+            .metered(self.metrics.as_deref(), "S3", "GetObject")
             .await
-            .map_err(|e| Box::new(e) as Box<dyn StdError + Send + Sync>)?;
+            .map_err(|e| classify_sdk_error("S3", e))?;
 
         let data = response.body.collect().await
-            .map_err(|e| Box::new(e) as Box<dyn StdError + Send + Sync>)?;
+            .map_err(|e| DomainError::Upstream(format!("S3 error: failed to read object body: {}", e)))?;
             
         Ok(data.into_bytes().to_vec())
     }
 
-    async fn put_object(
+    async fn put_object_with(
         &self,
         bucket: &str,
         key: &str,
         body: Vec<u8>,
-    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        options: PutObjectOptions,
+    ) -> DomainResult<()> {
         self.client
             .put_object()
             .bucket(bucket)
             .key(key)
+            .set_content_type(options.content_type)
             .body(ByteStream::from(body))
             .send()
+            .metered(self.metrics.as_deref(), "S3", "PutObject")
             .await
-            .map_err(|e| Box::new(e) as Box<dyn StdError + Send + Sync>)?;
+            .map_err(|e| classify_sdk_error("S3", e))?;
 
         Ok(())
     }
+
+    async fn get_object_stream(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> DomainResult<ByteReader> {
+        let response = self.client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .metered(self.metrics.as_deref(), "S3", "GetObject")
+            .await
+            .map_err(|e| classify_sdk_error("S3", e))?;
+
+        Ok(Box::pin(response.body.into_async_read()))
+    }
+
+    async fn put_object_stream(
+        &self,
+        bucket: &str,
+        key: &str,
+        mut body: ByteReader,
+    ) -> DomainResult<()> {
+        // Small bodies fit in one request
+        let mut first = read_up_to(&mut body, self.multipart.threshold).await?;
+        if first.len() < self.multipart.threshold {
+            return self.put_object(bucket, key, first).await;
+        }
+        // Only the last part may be smaller than the part size
+        if first.len() < self.multipart.part_size {
+            let rest = read_up_to(&mut body, self.multipart.part_size - first.len()).await?;
+            first.extend(rest);
+        }
+
+        let upload = self.client
+            .create_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .metered(self.metrics.as_deref(), "S3", "CreateMultipartUpload")
+            .await
+            .map_err(|e| classify_sdk_error("S3", e))?;
+        let upload_id = upload
+            .upload_id
+            .ok_or_else(|| DomainError::Upstream("S3 error: multipart upload has no upload id".to_string()))?;
+
+        let result = self.upload_parts(bucket, key, &upload_id, first, &mut body).await;
+        if result.is_err() {
+            // Uploaded parts are billed until the upload is aborted
+            if let Err(e) = self.client
+                .abort_multipart_upload()
+                .bucket(bucket)
+                .key(key)
+                .upload_id(&upload_id)
+                .send()
+                .metered(self.metrics.as_deref(), "S3", "AbortMultipartUpload")
+                .await
+            {
+                warn!("Failed to abort multipart upload {} for {}/{}: {}", upload_id, bucket, key, classify_sdk_error("S3", e));
+            }
+        }
+        result
+    }
+
+    async fn head_object(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> DomainResult<Option<ObjectMetadata>> {
+        // A missing object is an answer, not a failed call
+        let head = async {
+            match self.client.head_object().bucket(bucket).key(key).send().await {
+                Ok(response) => Ok(Some(response)),
+                Err(e) => match classify_sdk_error("S3", e) {
+                    // HEAD has no body, so a missing key only shows up as the 404 status
+                    DomainError::NotFound(_) => Ok(None),
+                    err => Err(err),
+                },
+            }
+        };
+        let Some(response) = head.metered(self.metrics.as_deref(), "S3", "HeadObject").await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(ObjectMetadata {
+            key: key.to_string(),
+            size: response.content_length.unwrap_or_default().max(0) as u64,
+            etag: response.e_tag,
+            content_type: response.content_type,
+            last_modified: response.last_modified.and_then(to_chrono),
+            metadata: response.metadata.unwrap_or_default(),
+        }))
+    }
+
+    async fn delete_object(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> DomainResult<()> {
+        self.client
+            .delete_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .metered(self.metrics.as_deref(), "S3", "DeleteObject")
+            .await
+            .map_err(|e| classify_sdk_error("S3", e))?;
+
+        Ok(())
+    }
+
+    async fn delete_objects(
+        &self,
+        bucket: &str,
+        keys: Vec<String>,
+    ) -> DomainResult<Vec<DeleteFailure>> {
+        let mut failures = Vec::new();
+
+        for chunk in keys.chunks(DELETE_OBJECTS_LIMIT) {
+            let objects = chunk
+                .iter()
+                .map(|key| ObjectIdentifier::builder().key(key).build().map_err(build_error))
+                .collect::<DomainResult<Vec<_>>>()?;
+            let delete = Delete::builder()
+                .set_objects(Some(objects))
+                .quiet(true)
+                .build()
+                .map_err(build_error)?;
+
+            let response = self.client
+                .delete_objects()
+                .bucket(bucket)
+                .delete(delete)
+                .send()
+                .metered(self.metrics.as_deref(), "S3", "DeleteObjects")
+                .await
+                .map_err(|e| classify_sdk_error("S3", e))?;
+
+            failures.extend(response.errors.unwrap_or_default().into_iter().map(|e| DeleteFailure {
+                key: e.key.unwrap_or_default(),
+                code: e.code.unwrap_or_else(|| "Unknown".to_string()),
+                message: e.message,
+            }));
+        }
+
+        Ok(failures)
+    }
+
+    async fn list_objects(
+        &self,
+        bucket: &str,
+        request: ListObjectsRequest,
+    ) -> DomainResult<ObjectListing> {
+        let response = self.client
+            .list_objects_v2()
+            .bucket(bucket)
+            .set_prefix(request.prefix)
+            .set_delimiter(request.delimiter)
+            .set_max_keys(request.max_keys.map(|m| m.min(i32::MAX as u32) as i32))
+            .set_continuation_token(request.next_token)
+            .send()
+            .metered(self.metrics.as_deref(), "S3", "ListObjectsV2")
+            .await
+            .map_err(|e| classify_sdk_error("S3", e))?;
+
+        let objects = response
+            .contents
+            .unwrap_or_default()
+            .into_iter()
+            .map(|object| ObjectSummary {
+                key: object.key.unwrap_or_default(),
+                size: object.size.unwrap_or_default().max(0) as u64,
+                etag: object.e_tag,
+                last_modified: object.last_modified.and_then(to_chrono),
+            })
+            .collect();
+        let common_prefixes = response
+            .common_prefixes
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|p| p.prefix)
+            .collect();
+
+        Ok(ObjectListing {
+            objects,
+            common_prefixes,
+            next_token: response.next_continuation_token,
+        })
+    }
+
+    async fn presign(
+        &self,
+        bucket: &str,
+        key: &str,
+        request: PresignRequest,
+    ) -> DomainResult<PresignedUrl> {
+        request.validate()?;
+        let config = PresigningConfig::expires_in(Duration::from_secs(request.expires_in_secs))
+            .map_err(build_error)?;
+
+        let presigned = match request.method {
+            PresignMethod::Get => self
+                .client
+                .get_object()
+                .bucket(bucket)
+                .key(key)
+                .presigned(config)
+                .await
+                .map_err(|e| classify_sdk_error("S3", e))?,
+            PresignMethod::Put => self
+                .client
+                .put_object()
+                .bucket(bucket)
+                .key(key)
+                .set_content_type(request.content_type)
+                .set_content_length(request.content_length.map(|l| l.min(i64::MAX as u64) as i64))
+                .presigned(config)
+                .await
+                .map_err(|e| classify_sdk_error("S3", e))?,
+        };
+
+        Ok(PresignedUrl {
+            url: presigned.uri().to_string(),
+            method: request.method,
+            headers: presigned.headers().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            expires_in: request.expires_in_secs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+    use aws_smithy_http_client::test_util::infallible_client_fn;
+    use lambda_http::http;
+    use std::sync::Mutex;
+
+    /// Operations the stub received, with their request body sizes
+    type Calls = Arc<Mutex<Vec<(&'static str, usize)>>>;
+
+    /// Adapter over a stub S3 endpoint that answers every call, except that
+    /// `UploadPart` fails for `failing_part`
+    fn stub_adapter(failing_part: Option<i32>) -> (S3Adapter, Calls) {
+        let calls = Calls::default();
+        let recorded = calls.clone();
+        let http_client = infallible_client_fn(move |request: http::Request<aws_sdk_s3::primitives::SdkBody>| {
+            let query = request.uri().query().unwrap_or_default().to_string();
+            let param = |name: &str| {
+                query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix(name).and_then(|rest| rest.strip_prefix('=')))
+                    .map(str::to_string)
+            };
+            let operation = match (request.method().as_str(), param("partNumber"), param("uploadId")) {
+                ("PUT", Some(_), _) => "UploadPart",
+                ("PUT", None, _) => "PutObject",
+                ("POST", _, Some(_)) => "CompleteMultipartUpload",
+                ("POST", _, None) => "CreateMultipartUpload",
+                ("DELETE", _, Some(_)) => "AbortMultipartUpload",
+                (method, _, _) => panic!("unexpected {} {}", method, request.uri()),
+            };
+            let size = request.body().bytes().map_or(0, <[u8]>::len);
+            recorded.lock().unwrap().push((operation, size));
+
+            let response = http::Response::builder();
+            match operation {
+                "UploadPart" if param("partNumber").and_then(|n| n.parse().ok()) == failing_part => response
+                    .status(400)
+                    .body("<Error><Code>InvalidRequest</Code><Message>Part rejected</Message></Error>".to_string()),
+                "CreateMultipartUpload" => response.status(200).body(
+                    "<InitiateMultipartUploadResult><Bucket>uploads</Bucket><Key>big.bin</Key>\
+                     <UploadId>upload-1</UploadId></InitiateMultipartUploadResult>"
+                        .to_string(),
+                ),
+                "CompleteMultipartUpload" => response
+                    .status(200)
+                    .body("<CompleteMultipartUploadResult><Key>big.bin</Key></CompleteMultipartUploadResult>".to_string()),
+                "AbortMultipartUpload" => response.status(204).body(String::new()),
+                _ => response.status(200).header("ETag", "\"etag\"").body(String::new()),
+            }
+            .unwrap()
+        });
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("eu-west-1"))
+            .credentials_provider(Credentials::new("AKID", "SECRET", None, None, "test"))
+            .http_client(http_client)
+            .build();
+        let adapter = S3Adapter::new(Client::from_conf(config)).with_multipart(MultipartConfig {
+            threshold: MIN_PART_SIZE,
+            part_size: MIN_PART_SIZE,
+        });
+        (adapter, calls)
+    }
+
+    fn stream(size: usize) -> ByteReader {
+        Box::pin(std::io::Cursor::new(vec![7u8; size]))
+    }
+
+    fn operations(calls: &Calls) -> Vec<&'static str> {
+        calls.lock().unwrap().iter().map(|(operation, _)| *operation).collect()
+    }
+
+    #[tokio::test]
+    async fn test_small_stream_is_put_in_one_request() {
+        let (adapter, calls) = stub_adapter(None);
+        adapter.put_object_stream("uploads", "small.bin", stream(1024)).await.unwrap();
+        assert_eq!(*calls.lock().unwrap(), [("PutObject", 1024)]);
+    }
+
+    #[tokio::test]
+    async fn test_large_stream_is_uploaded_in_parts() {
+        let (adapter, calls) = stub_adapter(None);
+        adapter.put_object_stream("uploads", "big.bin", stream(2 * MIN_PART_SIZE + 3)).await.unwrap();
+
+        let calls = calls.lock().unwrap();
+        let parts: Vec<usize> = calls.iter().filter(|(op, _)| *op == "UploadPart").map(|(_, size)| *size).collect();
+        assert_eq!(parts, [MIN_PART_SIZE, MIN_PART_SIZE, 3]);
+        assert_eq!(calls.first().unwrap().0, "CreateMultipartUpload");
+        assert_eq!(calls.last().unwrap().0, "CompleteMultipartUpload");
+    }
+
+    #[tokio::test]
+    async fn test_failed_part_aborts_the_upload() {
+        let (adapter, calls) = stub_adapter(Some(2));
+        let result = adapter.put_object_stream("uploads", "big.bin", stream(3 * MIN_PART_SIZE)).await;
+        assert!(result.is_err());
+        assert_eq!(
+            operations(&calls),
+            ["CreateMultipartUpload", "UploadPart", "UploadPart", "AbortMultipartUpload"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_too_many_parts_aborts_the_upload() {
+        let (mut adapter, calls) = stub_adapter(None);
+        adapter.max_parts = 2;
+        let result = adapter.put_object_stream("uploads", "big.bin", stream(2 * MIN_PART_SIZE + 1)).await;
+        assert!(matches!(result, Err(DomainError::Validation(message)) if message.contains("2 parts")));
+        assert_eq!(
+            operations(&calls),
+            ["CreateMultipartUpload", "UploadPart", "UploadPart", "AbortMultipartUpload"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_up_to_splits_stream() {
+        let data: Vec<u8> = (0..10u8).collect();
+        let mut reader = data.as_slice();
+        assert_eq!(read_up_to(&mut reader, 4).await.unwrap(), vec![0, 1, 2, 3]);
+        assert_eq!(read_up_to(&mut reader, 4).await.unwrap(), vec![4, 5, 6, 7]);
+        assert_eq!(read_up_to(&mut reader, 4).await.unwrap(), vec![8, 9]);
+        assert!(read_up_to(&mut reader, 4).await.unwrap().is_empty());
+
+        // A short body does not reserve the whole limit
+        let small = read_up_to(&mut b"tiny".as_slice(), 8 * 1024 * 1024).await.unwrap();
+        assert!(small.capacity() <= INITIAL_READ_CAPACITY);
+    }
 }
@@ -0,0 +1,42 @@
+use crate::domain::errors::{DomainError, DomainResult};
+use crate::domain::ports::SecretsPort;
+use super::aws_error::classify_sdk_error;
+use async_trait::async_trait;
+use aws_sdk_secretsmanager::error::ProvideErrorMetadata;
+use aws_sdk_secretsmanager::Client;
+
+/// AWS Secrets Manager adapter implementing SecretsPort.
+///
+/// Secrets are read by name or ARN, at their `AWSCURRENT` version.
+pub struct SecretsManagerAdapter {
+    client: Client,
+}
+
+impl SecretsManagerAdapter {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl SecretsPort for SecretsManagerAdapter {
+    async fn get_secret(&self, name: &str) -> DomainResult<String> {
+        let output = self
+            .client
+            .get_secret_value()
+            .secret_id(name)
+            .send()
+            .await
+            .map_err(|e| match e.code() {
+                Some("ResourceNotFoundException") => DomainError::NotFound(format!("Secret '{}' does not exist", name)),
+                _ => classify_sdk_error("Secrets Manager", e),
+            })?;
+
+        match (output.secret_string(), output.secret_binary()) {
+            (Some(value), _) => Ok(value.to_string()),
+            (None, Some(binary)) => String::from_utf8(binary.as_ref().to_vec())
+                .map_err(|_| DomainError::Internal(format!("Secret '{}' is binary, not UTF-8 text", name))),
+            (None, None) => Err(DomainError::Internal(format!("Secret '{}' has no value", name))),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::domain::email::{EmailMessage, Envelope, TemplatedEmail, MAX_MESSAGE_BYTES};
+use crate::domain::errors::{DomainError, DomainResult};
+use crate::domain::ports::EmailPort;
+use super::aws_error::classify_sdk_error;
+use super::mime::build_mime;
+use async_trait::async_trait;
+use aws_sdk_ses::primitives::Blob;
+use aws_sdk_ses::types::{Body, Content, Destination, Message, RawMessage};
+use aws_sdk_ses::Client;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct SesAdapter {
+    client: Client,
+}
+
+impl SesAdapter {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+fn build_error(e: impl std::fmt::Display) -> DomainError {
+    DomainError::Internal(format!("SES error: invalid request: {}", e))
+}
+
+/// Empty lists are left unset rather than sent as empty parameters
+fn addresses(list: &[String]) -> Option<Vec<String>> {
+    (!list.is_empty()).then(|| list.to_vec())
+}
+
+fn destination(envelope: &Envelope) -> Destination {
+    Destination::builder()
+        .set_to_addresses(addresses(&envelope.to))
+        .set_cc_addresses(addresses(&envelope.cc))
+        .set_bcc_addresses(addresses(&envelope.bcc))
+        .build()
+}
+
+fn utf8_content(data: &str) -> DomainResult<Content> {
+    Content::builder().data(data).charset("UTF-8").build().map_err(build_error)
+}
+
+/// MIME boundary unlikely to collide across messages; base64 bodies can never contain `=_`
+fn boundary() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    format!("=_Part_{:x}", nanos)
+}
+
+#[async_trait]
+impl EmailPort for SesAdapter {
+    async fn send_email(&self, message: EmailMessage) -> DomainResult<String> {
+        message.validate()?;
+        let envelope = &message.envelope;
+
+        // SendEmail has no attachments; those need a raw MIME message
+        if !message.attachments.is_empty() {
+            let raw = build_mime(&message, &boundary());
+            if raw.len() > MAX_MESSAGE_BYTES {
+                return Err(DomainError::Validation(format!(
+                    "Email is {} bytes once encoded, at most {} are allowed",
+                    raw.len(),
+                    MAX_MESSAGE_BYTES
+                )));
+            }
+            let raw_message = RawMessage::builder().data(Blob::new(raw)).build().map_err(build_error)?;
+            let response = self
+                .client
+                .send_raw_email()
+                .source(&envelope.from)
+                .set_destinations(Some(envelope.recipients().cloned().collect()))
+                .raw_message(raw_message)
+                .set_configuration_set_name(envelope.configuration_set.clone())
+                .send()
+                .await
+                .map_err(|e| classify_sdk_error("SES", e))?;
+            return Ok(response.message_id().to_string());
+        }
+
+        let mut body = Body::builder();
+        if let Some(text) = &message.text {
+            body = body.text(utf8_content(text)?);
+        }
+        if let Some(html) = &message.html {
+            body = body.html(utf8_content(html)?);
+        }
+        let content = Message::builder()
+            .subject(utf8_content(&message.subject)?)
+            .body(body.build())
+            .build();
+
+        let response = self
+            .client
+            .send_email()
+            .source(&envelope.from)
+            .destination(destination(envelope))
+            .message(content)
+            .set_reply_to_addresses(addresses(&envelope.reply_to))
+            .set_configuration_set_name(envelope.configuration_set.clone())
+            .send()
+            .await
+            .map_err(|e| classify_sdk_error("SES", e))?;
+
+        Ok(response.message_id().to_string())
+    }
+
+    async fn send_templated_email(&self, email: TemplatedEmail) -> DomainResult<String> {
+        email.validate()?;
+        let envelope = &email.envelope;
+
+        let response = self
+            .client
+            .send_templated_email()
+            .source(&envelope.from)
+            .destination(destination(envelope))
+            .template(&email.template)
+            .template_data(email.template_data.to_string())
+            .set_reply_to_addresses(addresses(&envelope.reply_to))
+            .set_configuration_set_name(envelope.configuration_set.clone())
+            .send()
+            .await
+            .map_err(|e| classify_sdk_error("SES", e))?;
+
+        Ok(response.message_id().to_string())
+    }
+}
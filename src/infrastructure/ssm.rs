@@ -0,0 +1,43 @@
+use crate::domain::errors::{DomainError, DomainResult};
+use crate::domain::ports::SecretsPort;
+use super::aws_error::classify_sdk_error;
+use async_trait::async_trait;
+use aws_sdk_ssm::error::ProvideErrorMetadata;
+use aws_sdk_ssm::Client;
+
+/// SSM Parameter Store adapter implementing SecretsPort.
+///
+/// Parameters are read by name (e.g. `/orders/prod/jwks`) with decryption,
+/// so `SecureString` parameters come back in plain text.
+pub struct SsmAdapter {
+    client: Client,
+}
+
+impl SsmAdapter {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl SecretsPort for SsmAdapter {
+    async fn get_secret(&self, name: &str) -> DomainResult<String> {
+        let output = self
+            .client
+            .get_parameter()
+            .name(name)
+            .with_decryption(true)
+            .send()
+            .await
+            .map_err(|e| match e.code() {
+                Some("ParameterNotFound") => DomainError::NotFound(format!("Parameter '{}' does not exist", name)),
+                _ => classify_sdk_error("SSM", e),
+            })?;
+
+        output
+            .parameter()
+            .and_then(|parameter| parameter.value())
+            .map(str::to_string)
+            .ok_or_else(|| DomainError::Internal(format!("Parameter '{}' has no value", name)))
+    }
+}
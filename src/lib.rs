@@ -6,4 +6,7 @@
 pub mod domain;
 pub mod infrastructure;
 pub mod application;
+pub mod http;
 
+pub mod config;
+pub mod metrics;
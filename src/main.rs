@@ -1,141 +1,102 @@
-use lambda_http::{run, service_fn, Body, Error, Request, RequestExt, Response};
-use mk_test_lambda::application::service::RequestProcessor;
-use mk_test_lambda::domain::models::{RequestPayload, ResponsePayload};
+use lambda_http::{run, service_fn, Error};
+use mk_test_lambda::application::api_keys::ApiKeyService;
+use mk_test_lambda::application::context::AppContext;
+use mk_test_lambda::application::idempotency::IdempotencyService;
+use mk_test_lambda::application::rate_limiter::RateLimiter;
+use mk_test_lambda::application::secrets::SecretsService;
+use mk_test_lambda::config::{Config, SecretsProvider};
+use mk_test_lambda::domain::ports::SecretsPort;
+use mk_test_lambda::http::auth::JwtAuth;
+use mk_test_lambda::http::cors::CorsPolicy;
+use mk_test_lambda::http::handler::{function_handler, HttpConfig};
 use mk_test_lambda::infrastructure::dynamo::DynamoDbAdapter;
+use mk_test_lambda::infrastructure::file_secrets::FileSecretsAdapter;
 use mk_test_lambda::infrastructure::s3::S3Adapter;
-use aws_config::meta::region::RegionProviderChain;
+use mk_test_lambda::infrastructure::secrets_manager::SecretsManagerAdapter;
+use mk_test_lambda::infrastructure::ssm::SsmAdapter;
+use mk_test_lambda::metrics::Metrics;
+use aws_config::Region;
 use aws_sdk_dynamodb::Client as DynamoClient;
 use aws_sdk_s3::Client as S3Client;
-use tracing::{info, error};
+use aws_sdk_secretsmanager::Client as SecretsManagerClient;
+use aws_sdk_ssm::Client as SsmClient;
+use std::sync::Arc;
+use tracing::info;
 
-/// Main Lambda handler function
-async fn function_handler(event: Request) -> Result<Response<Body>, Error> {
-    info!("Processing request: {:?}", event);
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    // Load and validate configuration once per cold start; a bad setting fails here
+    let mut app_config = Config::load()?;
 
-    // Extract query parameters
-    let query_params = event.query_string_parameters();
-    let path_params = event.path_parameters();
-    
-    // Parse request body if present
-    let request_payload: Option<RequestPayload> = match event.body() {
-        Body::Empty => None,
-        Body::Text(text) => {
-            match serde_json::from_str(text) {
-                Ok(payload) => Some(payload),
-                Err(e) => {
-                    error!("Failed to parse request body: {}", e);
-                    return Ok(create_error_response("Invalid JSON in request body"));
-                }
-            }
-        }
-        Body::Binary(_) => {
-            error!("Binary body not supported");
-            return Ok(create_error_response("Binary body not supported"));
-        }
-    };
+    // JSON logs, one object per line; each carries the spans it was written in,
+    // so request, trace and correlation ids reach every line of an invocation
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(app_config.log_filter())
+        .with_current_span(false)
+        .with_span_list(true)
+        .with_target(false)
+        .init();
+
+    info!("Starting Rust Lambda function");
 
-    // Initialize AWS configuration
-    let region_provider = RegionProviderChain::default_provider().or_else("us-east-1");
+    // Initialize AWS configuration once per cold start
     let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
-        .region(region_provider)
+        .region(Region::new(app_config.region.clone()))
         .load()
         .await;
 
+    // Resolve secret:// references before anything reads the settings that hold them
+    let secrets_port: Option<Arc<dyn SecretsPort>> = match app_config.secrets.provider {
+        Some(SecretsProvider::SecretsManager) => Some(Arc::new(SecretsManagerAdapter::new(SecretsManagerClient::new(&config)))),
+        Some(SecretsProvider::Ssm) => Some(Arc::new(SsmAdapter::new(SsmClient::new(&config)))),
+        Some(SecretsProvider::File) => app_config
+            .secrets
+            .file
+            .as_deref()
+            .map(|path| Arc::new(FileSecretsAdapter::new(path)) as Arc<dyn SecretsPort>),
+        None => None,
+    };
+    if let Some(port) = secrets_port {
+        app_config.resolve_secrets(&SecretsService::from_config(port, &app_config.secrets)).await?;
+    }
+    info!("Configuration ({}): {:?}", app_config.stage.as_str(), app_config);
+
     // Initialize Infrastructure Adapters
     let dynamo_client = DynamoClient::new(&config);
     let s3_client = S3Client::new(&config);
 
-    let database_adapter = Box::new(DynamoDbAdapter::new(dynamo_client));
-    let storage_adapter = Box::new(S3Adapter::new(s3_client));
-
-    // Initialize Application Service
-    let processor = RequestProcessor::new(database_adapter, storage_adapter);
-
-    // Process the request
-    // Note: query_params and path_params need to be converted to HashMap<String, String>
-    // The lambda_http types are effectively maps, but we need to convert them to standard HashMaps for our port
-    // For simplicity in this demo, we'll just pass empty maps or convert if needed.
-    // The RequestProcessor signature expects &HashMap<String, String>.
-    // lambda_http::aws_lambda_events::query_map::QueryMap is iterable.
-    
-    let mut q_params = std::collections::HashMap::new();
-    for (k, v) in query_params.iter() {
-        q_params.insert(k.to_string(), v.to_string());
-    }
-
-    let mut p_params = std::collections::HashMap::new();
-    for (k, v) in path_params.iter() {
-        p_params.insert(k.to_string(), v.to_string());
-    }
-
-    let result = processor.process_request(request_payload, &q_params, &p_params).await;
-
-    match result {
-        Ok(message) => {
-            // Create response
-            let response_payload = ResponsePayload {
-                status: "success".to_string(),
-                message,
-                data: None,
-                timestamp: chrono::Utc::now().to_rfc3339(),
-            };
-
-            let response_body = serde_json::to_string(&response_payload)
-                .map_err(|e| Error::from(format!("Failed to serialize response: {}", e)))?;
-
-            Ok(Response::builder()
-                .status(200)
-                .header("Content-Type", "application/json")
-                .header("Access-Control-Allow-Origin", "*")
-                .header("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS")
-                .header("Access-Control-Allow-Headers", "Content-Type, Authorization")
-                .body(Body::Text(response_body))
-                .map_err(|e| Error::from(format!("Failed to build response: {}", e)))?)
-        }
-        Err(e) => {
-            error!("Processing failed: {}", e);
-            Ok(create_error_response(&format!("Processing failed: {}", e)))
-        }
-    }
-}
-
-/// Create an error response
-fn create_error_response(message: &str) -> Response<Body> {
-    let error_response = ResponsePayload {
-        status: "error".to_string(),
-        message: message.to_string(),
-        data: None,
-        timestamp: chrono::Utc::now().to_rfc3339(),
-    };
-
-    let error_body = serde_json::to_string(&error_response).unwrap_or_else(|_| {
-        r#"{"status":"error","message":"Failed to serialize error response","timestamp":"1970-01-01T00:00:00Z"}"#.to_string()
-    });
-
-    Response::builder()
-        .status(400)
-        .header("Content-Type", "application/json")
-        .header("Access-Control-Allow-Origin", "*")
-        .body(Body::Text(error_body))
-        .unwrap_or_else(|_| {
-            Response::builder()
-                .status(500)
-                .body(Body::Text("Internal server error".to_string()))
-                .unwrap()
-        })
-}
-
-#[tokio::main]
-async fn main() -> Result<(), Error> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .with_target(false)
-        .without_time()
-        .init();
-
-    info!("Starting Rust Lambda function");
+    // Metrics are written to stdout as EMF, so they need no client
+    let metrics = Metrics::from_config(&app_config.metrics).map(Arc::new);
+    let database_adapter = Arc::new(DynamoDbAdapter::new(dynamo_client).with_metrics(metrics.clone()));
+    let storage_adapter = Arc::new(
+        S3Adapter::new(s3_client)
+            .with_multipart(app_config.storage.multipart())
+            .with_metrics(metrics.clone()),
+    );
+
+    // Initialize Application Context, shared by every invocation
+    let api_keys = ApiKeyService::from_config(database_adapter.clone(), &app_config.api_keys);
+    let rate_limiter = RateLimiter::from_config(database_adapter.clone(), &app_config.rate_limit);
+    let idempotency = IdempotencyService::from_config(database_adapter.clone(), &app_config.idempotency);
+    let ctx = Arc::new(
+        AppContext::new(database_adapter, storage_adapter)
+            .with_config(&app_config)
+            .with_api_keys(api_keys)
+            .with_rate_limiter(rate_limiter)
+            .with_idempotency(idempotency)
+            .with_metrics(metrics),
+    );
+    // A JWKS that cannot be loaded fails the cold start rather than serving requests unauthenticated
+    let http = Arc::new(
+        HttpConfig::new(CorsPolicy::from_config(&app_config.cors)).with_auth(JwtAuth::from_config(&app_config.auth)?),
+    );
 
     // Run the Lambda function
-    run(service_fn(function_handler)).await
+    run(service_fn(move |event| {
+        let ctx = Arc::clone(&ctx);
+        let http = Arc::clone(&http);
+        async move { function_handler(&ctx, &http, event).await }
+    }))
+    .await
 }
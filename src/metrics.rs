@@ -0,0 +1,337 @@
+//! CloudWatch metrics written as Embedded Metric Format (EMF) log lines.
+//!
+//! Metrics are buffered during an invocation and flushed together at its
+//! end; CloudWatch Logs extracts them from the function's output, so no API
+//! calls are made on the request path.
+
+use crate::config::MetricsConfig;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Dimension holding the matched route, e.g. `GET /items/{pk}`
+pub const ROUTE_DIMENSION: &str = "Route";
+/// Dimension holding the response status code
+pub const STATUS_DIMENSION: &str = "StatusCode";
+/// Dimension holding the AWS service an adapter called, e.g. `DynamoDB`
+pub const ADAPTER_DIMENSION: &str = "Adapter";
+/// Dimension holding the API operation an adapter called, e.g. `GetItem`
+pub const OPERATION_DIMENSION: &str = "Operation";
+/// Dimensions set per metric, which configured dimensions may not use
+pub const RESERVED_DIMENSIONS: &[&str] = &[ROUTE_DIMENSION, STATUS_DIMENSION, ADAPTER_DIMENSION, OPERATION_DIMENSION];
+/// Most dimensions CloudWatch accepts on one metric
+pub const MAX_DIMENSIONS: usize = 30;
+/// Route dimension of requests that matched no route
+pub const UNMATCHED_ROUTE: &str = "Unmatched";
+
+/// Most values EMF accepts for one metric in one document
+const MAX_VALUES: usize = 100;
+
+/// Unit of a metric's values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Milliseconds,
+    Count,
+}
+
+impl Unit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Unit::Milliseconds => "Milliseconds",
+            Unit::Count => "Count",
+        }
+    }
+}
+
+/// Destination of flushed EMF documents
+pub trait MetricsSink: Send + Sync {
+    fn emit(&self, documents: Vec<Value>);
+}
+
+/// Writes each document as one line on stdout, where Lambda's log capture
+/// hands it to CloudWatch
+pub struct StdoutSink;
+
+impl MetricsSink for StdoutSink {
+    fn emit(&self, documents: Vec<Value>) {
+        let mut lines = String::new();
+        for document in documents {
+            lines.push_str(&document.to_string());
+            lines.push('\n');
+        }
+        // One write, so a flush is never interleaved with log lines
+        let _ = std::io::stdout().lock().write_all(lines.as_bytes());
+    }
+}
+
+/// Keeps flushed documents in memory, for tests
+#[derive(Default)]
+pub struct InMemoryRecorder {
+    documents: Mutex<Vec<Value>>,
+}
+
+impl InMemoryRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every document flushed so far
+    pub fn documents(&self) -> Vec<Value> {
+        self.documents.lock().unwrap().clone()
+    }
+
+    /// Values recorded for metric `name` in documents carrying all of `dimensions`
+    pub fn values(&self, name: &str, dimensions: &[(&str, &str)]) -> Vec<f64> {
+        self.documents()
+            .iter()
+            .filter(|document| dimensions.iter().all(|(key, value)| document[*key] == *value))
+            .flat_map(|document| match &document[name] {
+                Value::Array(values) => values.iter().filter_map(Value::as_f64).collect::<Vec<_>>(),
+                value => value.as_f64().into_iter().collect(),
+            })
+            .collect()
+    }
+}
+
+impl MetricsSink for InMemoryRecorder {
+    fn emit(&self, documents: Vec<Value>) {
+        self.documents.lock().unwrap().extend(documents);
+    }
+}
+
+/// Values of each metric by name, for one set of dimension values
+type MetricValues = BTreeMap<String, (Unit, Vec<f64>)>;
+
+/// Metrics facade shared by the handler and the adapters.
+///
+/// Every metric carries the configured dimensions plus its own; metrics with
+/// the same dimension values are flushed in one EMF document.
+pub struct Metrics {
+    namespace: String,
+    dimensions: BTreeMap<String, String>,
+    sink: Arc<dyn MetricsSink>,
+    pending: Mutex<BTreeMap<BTreeMap<String, String>, MetricValues>>,
+    cold_start: AtomicBool,
+}
+
+impl Metrics {
+    pub fn new(namespace: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            dimensions: BTreeMap::new(),
+            sink: Arc::new(StdoutSink),
+            pending: Mutex::new(BTreeMap::new()),
+            cold_start: AtomicBool::new(true),
+        }
+    }
+
+    /// Add a dimension to every metric, e.g. the service or stage
+    pub fn with_dimension(mut self, name: &str, value: &str) -> Self {
+        self.dimensions.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_sink(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.sink = sink;
+        self
+    }
+
+    /// `None` when metrics are disabled (no namespace configured)
+    pub fn from_config(config: &MetricsConfig) -> Option<Self> {
+        let namespace = config.namespace.as_deref()?;
+        Some(
+            config
+                .dimensions
+                .iter()
+                .fold(Self::new(namespace), |metrics, (name, value)| metrics.with_dimension(name, value)),
+        )
+    }
+
+    /// Buffer one value until the next flush
+    pub fn record(&self, name: &str, value: f64, unit: Unit, dimensions: &[(&str, &str)]) {
+        let mut key = self.dimensions.clone();
+        key.extend(dimensions.iter().map(|(name, value)| (name.to_string(), value.to_string())));
+        self.pending
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .entry(name.to_string())
+            .or_insert_with(|| (unit, Vec::new()))
+            .1
+            .push(value);
+    }
+
+    /// Record a handled request: `Latency` by route, `Requests` by route and
+    /// status code, and `ColdStart` (1 for the first invocation of this
+    /// instance, 0 after, so its average is the cold-start rate)
+    pub fn record_request(&self, route: &str, status: u16, latency: Duration) {
+        let status = status.to_string();
+        self.record("Latency", millis(latency), Unit::Milliseconds, &[(ROUTE_DIMENSION, route)]);
+        self.record("Requests", 1.0, Unit::Count, &[(ROUTE_DIMENSION, route), (STATUS_DIMENSION, &status)]);
+        let cold_start = self.cold_start.swap(false, Ordering::Relaxed);
+        self.record("ColdStart", if cold_start { 1.0 } else { 0.0 }, Unit::Count, &[]);
+    }
+
+    /// Record one adapter call: `AdapterLatency` and `AdapterErrors` (1 for a
+    /// failed call, 0 otherwise, so its average is the error rate)
+    pub fn record_call(&self, adapter: &str, operation: &str, latency: Duration, failed: bool) {
+        let dimensions = [(ADAPTER_DIMENSION, adapter), (OPERATION_DIMENSION, operation)];
+        self.record("AdapterLatency", millis(latency), Unit::Milliseconds, &dimensions);
+        self.record("AdapterErrors", if failed { 1.0 } else { 0.0 }, Unit::Count, &dimensions);
+    }
+
+    /// Write everything recorded since the last flush to the sink
+    pub fn flush(&self) {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if pending.is_empty() {
+            return;
+        }
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let documents = pending
+            .iter()
+            .flat_map(|(dimensions, metrics)| self.documents(timestamp, dimensions, metrics))
+            .collect();
+        self.sink.emit(documents);
+    }
+
+    /// EMF documents for one set of dimension values, split so no metric has
+    /// more values than one document may hold
+    fn documents(&self, timestamp: i64, dimensions: &BTreeMap<String, String>, metrics: &MetricValues) -> Vec<Value> {
+        let longest = metrics.values().map(|(_, values)| values.len()).max().unwrap_or_default();
+        (0..longest.div_ceil(MAX_VALUES))
+            .map(|chunk| {
+                let mut document = Map::new();
+                let mut definitions = Vec::new();
+                for (name, (unit, values)) in metrics {
+                    let Some(values) = values.chunks(MAX_VALUES).nth(chunk) else {
+                        continue;
+                    };
+                    definitions.push(json!({"Name": name, "Unit": unit.as_str()}));
+                    let value = match values {
+                        [value] => json!(value),
+                        values => json!(values),
+                    };
+                    document.insert(name.clone(), value);
+                }
+                for (name, value) in dimensions {
+                    document.insert(name.clone(), Value::from(value.as_str()));
+                }
+                document.insert(
+                    "_aws".to_string(),
+                    json!({
+                        "Timestamp": timestamp,
+                        "CloudWatchMetrics": [{
+                            "Namespace": self.namespace,
+                            "Dimensions": [dimensions.keys().collect::<Vec<_>>()],
+                            "Metrics": definitions,
+                        }],
+                    }),
+                );
+                Value::Object(document)
+            })
+            .collect()
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Times the calls adapters make to AWS, e.g.
+/// `client.get_item().send().metered(metrics, "DynamoDB", "GetItem")`
+pub trait Metered<T, E>: Future<Output = Result<T, E>> + Sized {
+    /// Record the call's latency and whether it failed; without metrics the call is just awaited
+    fn metered(self, metrics: Option<&Metrics>, adapter: &str, operation: &str) -> impl Future<Output = Result<T, E>> + Send
+    where
+        Self: Send;
+}
+
+impl<F, T, E> Metered<T, E> for F
+where
+    F: Future<Output = Result<T, E>>,
+{
+    async fn metered(self, metrics: Option<&Metrics>, adapter: &str, operation: &str) -> Result<T, E>
+    where
+        Self: Send,
+    {
+        let Some(metrics) = metrics else {
+            return self.await;
+        };
+        let started = Instant::now();
+        let result = self.await;
+        metrics.record_call(adapter, operation, started.elapsed(), result.is_err());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics() -> (Metrics, Arc<InMemoryRecorder>) {
+        let recorder = Arc::new(InMemoryRecorder::new());
+        let metrics = Metrics::new("Orders").with_dimension("Service", "orders-api").with_sink(recorder.clone());
+        (metrics, recorder)
+    }
+
+    #[test]
+    fn test_request_metrics_flush_as_emf() {
+        let (metrics, recorder) = metrics();
+        metrics.record_request("GET /items/{pk}", 200, Duration::from_millis(12));
+        metrics.record_request("GET /items/{pk}", 404, Duration::from_millis(3));
+        assert!(recorder.documents().is_empty());
+
+        metrics.flush();
+        let documents = recorder.documents();
+        // Latency by route, requests by route and status (twice), cold start
+        assert_eq!(documents.len(), 4);
+        let latency = documents.iter().find(|d| d.get("Latency").is_some()).unwrap();
+        assert_eq!(latency["Service"], "orders-api");
+        assert_eq!(latency["Route"], "GET /items/{pk}");
+        assert_eq!(latency["Latency"], json!([12.0, 3.0]));
+        let directive = &latency["_aws"]["CloudWatchMetrics"][0];
+        assert_eq!(directive["Namespace"], "Orders");
+        assert_eq!(directive["Dimensions"], json!([["Route", "Service"]]));
+        assert_eq!(directive["Metrics"], json!([{"Name": "Latency", "Unit": "Milliseconds"}]));
+
+        assert_eq!(recorder.values("Requests", &[("StatusCode", "404")]), [1.0]);
+        assert_eq!(recorder.values("ColdStart", &[]), [1.0, 0.0]);
+
+        // Nothing is written twice
+        metrics.flush();
+        assert_eq!(recorder.documents().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_metered_calls() {
+        let (metrics, recorder) = metrics();
+        let ok: Result<u8, ()> = async { Ok(1) }.metered(Some(&metrics), "DynamoDB", "GetItem").await;
+        let failed: Result<u8, ()> = async { Err(()) }.metered(Some(&metrics), "DynamoDB", "GetItem").await;
+        assert_eq!((ok, failed), (Ok(1), Err(())));
+        assert_eq!(async { Ok::<_, ()>(2) }.metered(None, "S3", "GetObject").await, Ok(2));
+
+        metrics.flush();
+        let get_item = [("Adapter", "DynamoDB"), ("Operation", "GetItem")];
+        assert_eq!(recorder.values("AdapterErrors", &get_item), [0.0, 1.0]);
+        assert_eq!(recorder.values("AdapterLatency", &get_item).len(), 2);
+        assert!(recorder.values("AdapterLatency", &[("Adapter", "S3")]).is_empty());
+    }
+
+    #[test]
+    fn test_values_are_split_across_documents() {
+        let (metrics, recorder) = metrics();
+        for _ in 0..150 {
+            metrics.record_call("S3", "UploadPart", Duration::from_millis(1), false);
+        }
+        metrics.flush();
+        let documents = recorder.documents();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0]["AdapterLatency"].as_array().unwrap().len(), 100);
+        assert_eq!(documents[1]["AdapterLatency"].as_array().unwrap().len(), 50);
+    }
+}